@@ -0,0 +1,141 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use sprout::{activation::{Activation, ActivationFunction::*}, conv_layer::ConvLayer, conv_params::PaddingType::*, dense_layer::DenseLayer, layer::Layer, matmul::matmul, shape::Shape};
+
+fn filled(len: usize) -> Vec<f64> {
+    (0..len).map(|i| ((i * 7919) % 101) as f64 / 101.0 - 0.5).collect()
+}
+
+fn image(channels: usize, size: usize) -> Vec<Vec<Vec<f64>>> {
+    filled(channels * size * size)
+        .chunks(size * size)
+        .map(|channel| channel.chunks(size).map(|row| row.to_vec()).collect())
+        .collect()
+}
+
+//the layers split batches and channels over rayon, the kernels are compared on one thread so only the lowering differs.
+//the *_parallel runs use the global pool on top of that
+fn single_thread() -> ThreadPool {
+    ThreadPoolBuilder::new().num_threads(1).build().unwrap()
+}
+
+//Reference kernels mirroring the nested Vec loops used before the GEMM lowering
+
+#[allow(clippy::needless_range_loop)]
+fn naive_matmul(a: &[Vec<f64>], b: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let mut c = vec![vec![0.0; b[0].len()]; a.len()];
+    for i in 0..a.len() {
+        for j in 0..b[0].len() {
+            for p in 0..b.len() {
+                c[i][j] += a[i][p] * b[p][j];
+            }
+        }
+    }
+    c
+}
+
+#[allow(clippy::needless_range_loop)]
+fn naive_conv(img: &[Vec<Vec<f64>>], weights: &[Vec<Vec<f64>>], bias: f64, kernel: usize, stride: usize, activation: &Activation) -> Vec<Vec<Vec<f64>>> {
+    let out_height = (img[0].len() - kernel) / stride + 1;
+    let out_width = (img[0][0].len() - kernel) / stride + 1;
+    let mut weighted_inputs = vec![vec![vec![0.0; out_width]; out_height]; img.len()];
+    let mut outputs = vec![vec![vec![0.0; out_width]; out_height]; img.len()];
+    for i in 0..img.len() {
+        for j in 0..out_height {
+            for k in 0..out_width {
+                weighted_inputs[i][j][k] += bias;
+                for kern_row in 0..kernel {
+                    for kern_col in 0..kernel {
+                        weighted_inputs[i][j][k] += img[i][j * stride + kern_row][k * stride + kern_col] * weights[i][kern_row][kern_col];
+                    }
+                }
+            }
+        }
+        for j in 0..out_height {
+            outputs[i][j] = activation.function(weighted_inputs[i][j].clone());
+        }
+    }
+    outputs
+}
+
+#[allow(clippy::needless_range_loop)]
+fn naive_dense(inputs: &[f64], weights: &[Vec<f64>], biases: &[f64]) -> Vec<f64> {
+    let mut weighted_inputs = biases.to_vec();
+    for i in 0..biases.len() {
+        for j in 0..inputs.len() {
+            weighted_inputs[i] += inputs[j] * weights[j][i];
+        }
+    }
+    weighted_inputs.iter().map(|x| 1.0 / (1.0 + (-x).exp())).collect()
+}
+
+fn bench_matmul(c: &mut Criterion) {
+    let mut group = c.benchmark_group("matmul");
+    for size in [64, 256] {
+        let a = filled(size * size);
+        let b = filled(size * size);
+        let a_nested: Vec<Vec<f64>> = a.chunks(size).map(|row| row.to_vec()).collect();
+        let b_nested: Vec<Vec<f64>> = b.chunks(size).map(|row| row.to_vec()).collect();
+
+        group.bench_with_input(BenchmarkId::new("naive", size), &size, |bench, _| {
+            bench.iter(|| naive_matmul(black_box(&a_nested), black_box(&b_nested)))
+        });
+        group.bench_with_input(BenchmarkId::new("blocked", size), &size, |bench, &size| {
+            bench.iter(|| {
+                let mut out = vec![0.0; size * size];
+                matmul(black_box(&a), black_box(&b), &mut out, size, size, size);
+                out
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_conv(c: &mut Criterion) {
+    let pool = single_thread();
+    let mut group = c.benchmark_group("conv_forward");
+    for size in [28, 64] {
+        let img = image(8, size);
+        let mut layer = ConvLayer::new(3, Valid, 1, ReLU);
+        layer.build(Shape::Image([8, size, size]), &mut rand::thread_rng()).unwrap();
+        let weights = layer.get_weights();
+        let bias = layer.get_bias();
+        let activation = Activation::new(ReLU);
+
+        group.bench_with_input(BenchmarkId::new("nested_loops", size), &size, |bench, _| {
+            pool.install(|| bench.iter(|| naive_conv(black_box(&img), &weights, bias, 3, 1, &activation)))
+        });
+        group.bench_with_input(BenchmarkId::new("im2col_gemm", size), &size, |bench, _| {
+            pool.install(|| bench.iter(|| layer.predict_image(black_box(&img))))
+        });
+        group.bench_with_input(BenchmarkId::new("im2col_gemm_parallel", size), &size, |bench, _| {
+            bench.iter(|| layer.predict_image(black_box(&img)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_dense(c: &mut Criterion) {
+    let pool = single_thread();
+    let mut group = c.benchmark_group("dense_forward");
+    let batch: Vec<Vec<f64>> = (0..64).map(|s| filled(784).into_iter().map(|x| x + s as f64 * 1e-3).collect()).collect();
+    let mut layer = DenseLayer::new([784, 128], Sigmoid);
+    layer.build(Shape::Flat(784), &mut rand::thread_rng()).unwrap();
+    let weights = layer.get_weights();
+    let biases = layer.get_biases();
+
+    group.bench_function("column_access", |bench| {
+        pool.install(|| bench.iter(|| batch.iter().map(|sample| naive_dense(black_box(sample), &weights, &biases)).collect::<Vec<_>>()))
+    });
+    group.bench_function("gemm", |bench| {
+        pool.install(|| bench.iter(|| layer.predict_batch(black_box(&batch))))
+    });
+    group.bench_function("gemm_parallel", |bench| {
+        bench.iter(|| layer.predict_batch(black_box(&batch)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_matmul, bench_conv, bench_dense);
+criterion_main!(benches);
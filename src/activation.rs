@@ -1,5 +1,7 @@
 use serde_derive::*;
 
+use crate::float::Float;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ActivationFunction {
     Sigmoid,
@@ -19,33 +21,33 @@ impl Activation {
             function
         }
     }
-    pub fn function(&self, inp: Vec<f64>) -> Vec<f64> {
-        let inputs = inp.clone();
+    pub fn function<T: Float>(&self, inp: Vec<T>) -> Vec<T> {
+        let inputs = inp;
         match self.function {
             ActivationFunction::Sigmoid => 
                 {
-                    let mut outputs = vec![0.0; inputs.len()];
+                    let mut outputs = vec![T::zero(); inputs.len()];
                     for i in 0..outputs.len() {
-                        outputs[i] = 1.0 / (1.0 + ((-inputs[i]).exp()));
+                        outputs[i] = T::one() / (T::one() + ((-inputs[i]).exp()));
                     }
                     outputs
                 },
             ActivationFunction::ReLU => 
                 {
-                    let mut outputs = vec![0.0; inputs.len()];
+                    let mut outputs = vec![T::zero(); inputs.len()];
                     for i in 0..outputs.len() {
                         let x = inputs[i];
-                        outputs[i] = if x > 0.0 {
+                        outputs[i] = if x > T::zero() {
                             x
                         } else {
-                            x * 0.01
+                            x * T::from_f64(0.01)
                         };
                     }
                     outputs  
                 },
             ActivationFunction::TanH =>
                 {
-                    let mut outputs = vec![0.0; inputs.len()];
+                    let mut outputs = vec![T::zero(); inputs.len()];
                     for i in 0..outputs.len() {
                         let x = inputs[i];
                         outputs[i] = x.tanh()
@@ -54,11 +56,11 @@ impl Activation {
                 },
             ActivationFunction::SoftMax => 
                 {
-                    let mean = inputs.clone().iter().sum::<f64>() / inputs.len() as f64;
+                    let mean = inputs.iter().copied().sum::<T>() / T::from_f64(inputs.len() as f64);
                     // let mean = 0.0;
-                    let sum_exp: f64 = inputs.clone().iter().map(|x| (x - mean).exp()).sum();
+                    let sum_exp: T = inputs.iter().map(|x| (*x - mean).exp()).sum();
 
-                    let mut outputs = vec![0.0; inputs.len()];
+                    let mut outputs = vec![T::zero(); inputs.len()];
                     for i in 0..outputs.len() {
                         outputs[i] = ((inputs[i] - mean).exp()) / sum_exp;
                     }
@@ -67,37 +69,37 @@ impl Activation {
         }
     }
 
-    pub fn derivative(&self, outs: Vec<f64>) -> Vec<f64> {
-        let outputs = outs.clone();
+    pub fn derivative<T: Float>(&self, outs: Vec<T>) -> Vec<T> {
+        let outputs = outs;
         match self.function {
             ActivationFunction::Sigmoid => 
                 {
-                    let mut gradients = vec![0.0; outputs.len()];
+                    let mut gradients = vec![T::zero(); outputs.len()];
                     for i in 0..outputs.len() {
-                        let x: f64 = outputs[i];
-                        gradients[i] = x * (1.0 - x);
+                        let x: T = outputs[i];
+                        gradients[i] = x * (T::one() - x);
                     }
                     gradients
                 },
             ActivationFunction::ReLU => 
                 {
-                    let mut gradients = vec![0.0; outputs.len()];
+                    let mut gradients = vec![T::zero(); outputs.len()];
                     for i in 0..outputs.len() {
                         let x = outputs[i];
-                        gradients[i] = if x < 0.0 {
-                            0.01
+                        gradients[i] = if x < T::zero() {
+                            T::from_f64(0.01)
                         } else {
-                            1.0
+                            T::one()
                         };
                     }
                     gradients
                 },
             ActivationFunction::TanH => 
             {
-                let mut gradients = vec![0.0; outputs.len()];
+                let mut gradients = vec![T::zero(); outputs.len()];
                 for i in 0..outputs.len() {
                     let x = outputs[i];
-                    gradients[i] = 1.0 - (x*x);
+                    gradients[i] = T::one() - (x*x);
                 }
                 gradients
             },
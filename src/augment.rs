@@ -0,0 +1,384 @@
+use rand::{seq::SliceRandom, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{dataset::Sample, error::SproutError, float::Float, initializer::standard_normal, network::Image, tensor::Tensor};
+
+//random transforms applied to training images as they are loaded, see DataLoader::augment.
+//everything is drawn from the rng passed in, so a seeded rng augments the same way every run
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    Crop { padding: usize }, //zero pads every side, then crops back to the original size at a random offset
+    HorizontalFlip(f64), //chance of mirroring left to right
+    VerticalFlip(f64), //chance of mirroring top to bottom
+    Rotation(f64), //up to this many degrees either way
+    Translation(f64), //shifts up to this fraction of the rows and cols either way
+    Scale { min: f64, max: f64 }, //zooms about the center, below 1 shrinks the image
+    Brightness(f64), //adds up to this to every value, either way
+    Contrast(f64), //stretches each channel about its mean by a factor of 1 +- up to this
+    Noise(f64), //gaussian noise with this standard deviation
+    Cutout(usize), //zeroes a size x size square centered anywhere on the image, clipped at the edges
+    Erasing { probability: f64, area: [f64; 2] }, //zeroes a rectangle of random aspect ratio covering a min to max fraction of the image
+}
+
+//mixes samples within a batch, alpha shapes the Beta(alpha, alpha) draw of how much of each sample is kept
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchTransform {
+    Mixup(f64), //blends every sample with another one from the batch
+    CutMix(f64), //pastes a rectangle from another sample of the batch
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Augmentation {
+    pub transforms: Vec<Transform>, //applied to each sample in order
+    pub batch: Option<BatchTransform>, //applied to the batch once its samples are transformed
+}
+
+impl Augmentation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn then(mut self, transform: Transform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    pub fn crop(self, padding: usize) -> Self {
+        self.then(Transform::Crop { padding })
+    }
+
+    pub fn flip_horizontal(self, probability: f64) -> Self {
+        self.then(Transform::HorizontalFlip(probability))
+    }
+
+    pub fn flip_vertical(self, probability: f64) -> Self {
+        self.then(Transform::VerticalFlip(probability))
+    }
+
+    pub fn rotate(self, degrees: f64) -> Self {
+        self.then(Transform::Rotation(degrees))
+    }
+
+    pub fn translate(self, fraction: f64) -> Self {
+        self.then(Transform::Translation(fraction))
+    }
+
+    pub fn scale(self, min: f64, max: f64) -> Self {
+        self.then(Transform::Scale { min, max })
+    }
+
+    pub fn brightness(self, delta: f64) -> Self {
+        self.then(Transform::Brightness(delta))
+    }
+
+    pub fn contrast(self, delta: f64) -> Self {
+        self.then(Transform::Contrast(delta))
+    }
+
+    pub fn noise(self, std_dev: f64) -> Self {
+        self.then(Transform::Noise(std_dev))
+    }
+
+    pub fn cutout(self, size: usize) -> Self {
+        self.then(Transform::Cutout(size))
+    }
+
+    pub fn erase(self, probability: f64, min_area: f64, max_area: f64) -> Self {
+        self.then(Transform::Erasing { probability, area: [min_area, max_area] })
+    }
+
+    pub fn mixup(mut self, alpha: f64) -> Self {
+        self.batch = Some(BatchTransform::Mixup(alpha));
+        self
+    }
+
+    pub fn cutmix(mut self, alpha: f64) -> Self {
+        self.batch = Some(BatchTransform::CutMix(alpha));
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), SproutError> {
+        let invalid = |message: String| Err(SproutError::InvalidConfig(message));
+        let probability = |p: f64| (0.0..=1.0).contains(&p);
+        let amount = |x: f64| x.is_finite() && x >= 0.0;
+        for (i, transform) in self.transforms.iter().enumerate() {
+            let valid = match *transform {
+                Transform::Crop { .. } => true,
+                Transform::HorizontalFlip(p) | Transform::VerticalFlip(p) => probability(p),
+                Transform::Rotation(degrees) => amount(degrees),
+                Transform::Translation(fraction) => probability(fraction),
+                Transform::Scale { min, max } => min.is_finite() && max.is_finite() && min > 0.0 && min <= max,
+                Transform::Brightness(delta) | Transform::Noise(delta) => amount(delta),
+                Transform::Contrast(delta) => probability(delta),
+                Transform::Cutout(size) => size > 0,
+                Transform::Erasing { probability: p, area: [min, max] } => probability(p) && min > 0.0 && min <= max && max <= 1.0,
+            };
+            if !valid {
+                return invalid(format!("augmentation {} has out of range parameters: {:?}", i, transform));
+            }
+        }
+        match self.batch {
+            Some(BatchTransform::Mixup(alpha) | BatchTransform::CutMix(alpha)) if !(alpha.is_finite() && alpha > 0.0) =>
+                invalid(format!("mixup/cutmix alpha must be positive, got {}", alpha)),
+            _ => Ok(()),
+        }
+    }
+
+    //runs the per sample transforms on one image
+    pub fn apply<T: Float>(&self, image: &mut Image<T>, rng: &mut dyn RngCore) {
+        for transform in &self.transforms {
+            transform.apply(image, rng);
+        }
+    }
+
+    //flat samples can only go through mixup
+    pub fn apply_sample<T: Float>(&self, sample: &mut Sample<T>, rng: &mut dyn RngCore) -> Result<(), SproutError> {
+        match &mut sample.input {
+            Tensor::Image(image) => self.apply(image, rng),
+            Tensor::Flat(values) if !self.transforms.is_empty() => return Err(SproutError::ShapeMismatch(format!(
+                "image augmentations need channels x rows x cols inputs but the sample has {} flat values", values.len()
+            ))),
+            Tensor::Flat(_) => {},
+        }
+        Ok(())
+    }
+
+    //every sample is mixed with the one a shuffle of the batch pairs it with, targets are mixed in the same proportion
+    pub fn apply_batch<T: Float>(&self, batch: &mut [Sample<T>], rng: &mut dyn RngCore) -> Result<(), SproutError> {
+        let Some(transform) = &self.batch else { return Ok(()) };
+        let Some(first) = batch.first() else { return Ok(()) };
+        let (shape, targets) = (first.input.shape(), first.target.len());
+        if let Some(i) = batch.iter().position(|sample| sample.input.shape() != shape || sample.target.len() != targets) {
+            return Err(SproutError::ShapeMismatch(format!(
+                "mixup/cutmix need samples of one shape, sample {} of the batch differs from the first", i
+            )));
+        }
+        let mut partners: Vec<usize> = (0..batch.len()).collect();
+        partners.shuffle(rng);
+        let originals = batch.to_vec();
+        match *transform {
+            BatchTransform::Mixup(alpha) => {
+                let keep = beta(alpha, rng);
+                let [a, b] = [keep, 1.0 - keep].map(T::from_f64);
+                for (sample, partner) in batch.iter_mut().zip(&partners) {
+                    let other = &originals[*partner];
+                    match (&mut sample.input, &other.input) {
+                        (Tensor::Flat(values), Tensor::Flat(others)) => blend(values, others, a, b),
+                        (Tensor::Image(image), Tensor::Image(others)) => {
+                            for (rows, other_rows) in image.iter_mut().zip(others) {
+                                for (row, other_row) in rows.iter_mut().zip(other_rows) {
+                                    blend(row, other_row, a, b);
+                                }
+                            }
+                        },
+                        _ => unreachable!("shapes were checked above"),
+                    }
+                    blend(&mut sample.target, &other.target, a, b);
+                }
+            },
+            BatchTransform::CutMix(alpha) => {
+                let Tensor::Image(image) = &first.input else {
+                    return Err(SproutError::ShapeMismatch("cutmix needs channels x rows x cols inputs but the samples are flat".to_string()));
+                };
+                let (rows, cols) = (image[0].len(), image[0][0].len());
+                let cut = (1.0 - beta(alpha, rng)).sqrt();
+                let [top, bottom, left, right] = random_box(rows, cols, (rows as f64 * cut) as usize, (cols as f64 * cut) as usize, rng);
+                //the targets follow the area that was actually pasted, which clipping may have shrunk
+                let keep = 1.0 - ((bottom - top) * (right - left)) as f64 / (rows * cols) as f64;
+                let [a, b] = [keep, 1.0 - keep].map(T::from_f64);
+                for (sample, partner) in batch.iter_mut().zip(&partners) {
+                    let other = &originals[*partner];
+                    let (Tensor::Image(image), Tensor::Image(others)) = (&mut sample.input, &other.input) else {
+                        unreachable!("shapes were checked above")
+                    };
+                    for (channel, other_channel) in image.iter_mut().zip(others) {
+                        for y in top..bottom {
+                            channel[y][left..right].copy_from_slice(&other_channel[y][left..right]);
+                        }
+                    }
+                    blend(&mut sample.target, &other.target, a, b);
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+impl Transform {
+    pub fn apply<T: Float>(&self, image: &mut Image<T>, rng: &mut dyn RngCore) {
+        let (rows, cols) = match image.first() {
+            Some(channel) if !channel.is_empty() && !channel[0].is_empty() => (channel.len(), channel[0].len()),
+            _ => return,
+        };
+        match *self {
+            Transform::Crop { padding } => {
+                let dy = rng.gen_range(0..=2 * padding) as isize - padding as isize;
+                let dx = rng.gen_range(0..=2 * padding) as isize - padding as isize;
+                for channel in image.iter_mut() {
+                    let source = channel.clone();
+                    for (y, row) in channel.iter_mut().enumerate() {
+                        for (x, value) in row.iter_mut().enumerate() {
+                            let (sy, sx) = (y as isize + dy, x as isize + dx);
+                            *value = match (0..rows as isize).contains(&sy) && (0..cols as isize).contains(&sx) {
+                                true => source[sy as usize][sx as usize],
+                                false => T::zero(),
+                            };
+                        }
+                    }
+                }
+            },
+            Transform::HorizontalFlip(probability) => {
+                if rng.gen_bool(probability) {
+                    image.iter_mut().flatten().for_each(|row| row.reverse());
+                }
+            },
+            Transform::VerticalFlip(probability) => {
+                if rng.gen_bool(probability) {
+                    image.iter_mut().for_each(|channel| channel.reverse());
+                }
+            },
+            Transform::Rotation(degrees) => {
+                let angle = rng.gen_range(-degrees..=degrees).to_radians();
+                affine(image, angle, [0.0, 0.0], 1.0);
+            },
+            Transform::Translation(fraction) => {
+                let shift = [rows, cols].map(|len| rng.gen_range(-fraction..=fraction) * len as f64);
+                affine(image, 0.0, shift, 1.0);
+            },
+            Transform::Scale { min, max } => {
+                let scale = rng.gen_range(min..=max);
+                affine(image, 0.0, [0.0, 0.0], scale);
+            },
+            Transform::Brightness(delta) => {
+                let offset = T::from_f64(rng.gen_range(-delta..=delta));
+                image.iter_mut().flatten().flatten().for_each(|value| *value += offset);
+            },
+            Transform::Contrast(delta) => {
+                let factor = rng.gen_range(1.0 - delta..=1.0 + delta);
+                for channel in image.iter_mut() {
+                    let mean = channel.iter().flatten().map(|value| value.as_f64()).sum::<f64>() / (rows * cols) as f64;
+                    for value in channel.iter_mut().flatten() {
+                        *value = T::from_f64(mean + (value.as_f64() - mean) * factor);
+                    }
+                }
+            },
+            Transform::Noise(std_dev) => {
+                for value in image.iter_mut().flatten().flatten() {
+                    *value += T::from_f64(standard_normal(rng) * std_dev);
+                }
+            },
+            Transform::Cutout(size) => {
+                let [top, bottom, left, right] = random_box(rows, cols, size, size, rng);
+                erase(image, top..bottom, left..right);
+            },
+            Transform::Erasing { probability, area: [min, max] } => {
+                if !rng.gen_bool(probability) {
+                    return;
+                }
+                //aspect ratios from 0.3 to 3.3 as in the random erasing paper, draws that don't fit are retried a few times
+                for _ in 0..10 {
+                    let area = rng.gen_range(min..=max) * (rows * cols) as f64;
+                    let ratio = rng.gen_range(0.3f64.ln()..=(1.0f64 / 0.3).ln()).exp();
+                    let (height, width) = ((area * ratio).sqrt().round() as usize, (area / ratio).sqrt().round() as usize);
+                    if (1..=rows).contains(&height) && (1..=cols).contains(&width) {
+                        let (top, left) = (rng.gen_range(0..=rows - height), rng.gen_range(0..=cols - width));
+                        erase(image, top..top + height, left..left + width);
+                        return;
+                    }
+                }
+            },
+        }
+    }
+}
+
+//rotates by angle (radians, counterclockwise), shifts by [rows, cols] and scales about the center,
+//each output pixel is sampled bilinearly from where it came from and is zero if that is off the image
+fn affine<T: Float>(image: &mut Image<T>, angle: f64, shift: [f64; 2], scale: f64) {
+    let (rows, cols) = (image[0].len(), image[0][0].len());
+    let (center_y, center_x) = ((rows as f64 - 1.0) / 2.0, (cols as f64 - 1.0) / 2.0);
+    let (sin, cos) = angle.sin_cos();
+    for channel in image.iter_mut() {
+        let source = channel.clone();
+        let pixel = |y: isize, x: isize| match (0..rows as isize).contains(&y) && (0..cols as isize).contains(&x) {
+            true => source[y as usize][x as usize].as_f64(),
+            false => 0.0,
+        };
+        for (y, row) in channel.iter_mut().enumerate() {
+            for (x, value) in row.iter_mut().enumerate() {
+                let (dy, dx) = ((y as f64 - center_y - shift[0]) / scale, (x as f64 - center_x - shift[1]) / scale);
+                //the inverse rotation, with rows growing downwards
+                let sy = center_y + dy * cos + dx * sin;
+                let sx = center_x + dx * cos - dy * sin;
+                let (y0, x0) = (sy.floor(), sx.floor());
+                let (fy, fx) = (sy - y0, sx - x0);
+                let (y0, x0) = (y0 as isize, x0 as isize);
+                let top = pixel(y0, x0) * (1.0 - fx) + pixel(y0, x0 + 1) * fx;
+                let bottom = pixel(y0 + 1, x0) * (1.0 - fx) + pixel(y0 + 1, x0 + 1) * fx;
+                *value = T::from_f64(top * (1.0 - fy) + bottom * fy);
+            }
+        }
+    }
+}
+
+fn erase<T: Float>(image: &mut Image<T>, rows: std::ops::Range<usize>, cols: std::ops::Range<usize>) {
+    for channel in image.iter_mut() {
+        for row in &mut channel[rows.clone()] {
+            row[cols.clone()].fill(T::zero());
+        }
+    }
+}
+
+//a height x width box centered on a random pixel, clipped to the image: [top, bottom, left, right)
+fn random_box(rows: usize, cols: usize, height: usize, width: usize, rng: &mut dyn RngCore) -> [usize; 4] {
+    let (y, x) = (rng.gen_range(0..rows), rng.gen_range(0..cols));
+    let (top, left) = (y.saturating_sub(height / 2), x.saturating_sub(width / 2));
+    [top, (y + height - height / 2).min(rows), left, (x + width - width / 2).min(cols)]
+}
+
+fn blend<T: Float>(values: &mut [T], others: &[T], a: T, b: T) {
+    for (value, other) in values.iter_mut().zip(others) {
+        *value = *value * a + *other * b;
+    }
+}
+
+//Beta(alpha, alpha) through two gamma draws
+fn beta(alpha: f64, rng: &mut dyn RngCore) -> f64 {
+    let (x, y) = (gamma(alpha, rng), gamma(alpha, rng));
+    match x + y {
+        //both draws can underflow for a tiny alpha, where the distribution is all at 0 and 1 anyway
+        sum if sum > 0.0 => x / sum,
+        _ => rng.gen_range(0..2) as f64,
+    }
+}
+
+//Marsaglia and Tsang, shapes below 1 are boosted by one and scaled back down
+fn gamma(shape: f64, rng: &mut dyn RngCore) -> f64 {
+    if shape < 1.0 {
+        let u: f64 = 1.0 - rng.gen::<f64>();
+        return gamma(shape + 1.0, rng) * u.powf(1.0 / shape);
+    }
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let x = standard_normal(rng);
+        let v = (1.0 + c * x).powi(3);
+        if v <= 0.0 {
+            continue;
+        }
+        let u: f64 = 1.0 - rng.gen::<f64>();
+        if u.ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+//the rng for one sample of one batch, drawn from the epoch's seed so the result doesn't depend on which thread loads it.
+//position len(batch) is the batch transform's
+pub(crate) fn sample_rng(seed: u64, batch: usize, position: usize) -> ChaCha8Rng {
+    let mut key = [0u8; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    key[8..16].copy_from_slice(&(batch as u64).to_le_bytes());
+    key[16..24].copy_from_slice(&(position as u64).to_le_bytes());
+    ChaCha8Rng::from_seed(key)
+}
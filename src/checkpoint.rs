@@ -0,0 +1,117 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use rand_chacha::ChaCha8Rng;
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{error::SproutError, float::Float, network::Network, schema};
+
+//checkpoints are the saved model plus what the training loop needs to carry on exactly where it stopped:
+//the shuffle RNG, the current sample order and the checkpoint settings themselves.
+//the epoch counter, optimizer state and learning rate schedule are part of the model
+
+const PREFIX: &str = "checkpoint-";
+const EXTENSION: &str = "json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpointing {
+    pub dir: PathBuf,
+    pub every: usize, //epochs between checkpoints
+    pub keep: usize, //newest checkpoints left on disk, 0 keeps all of them
+}
+
+impl Checkpointing {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Checkpointing { dir: dir.into(), every: 1, keep: 3 }
+    }
+
+    pub fn every(mut self, epochs: usize) -> Self {
+        self.every = epochs;
+        self
+    }
+
+    pub fn keep_last(mut self, checkpoints: usize) -> Self {
+        self.keep = checkpoints;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), SproutError> {
+        if self.every == 0 {
+            return Err(SproutError::InvalidConfig("checkpoints need to be at least one epoch apart".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrainingState {
+    rng: ChaCha8Rng,
+    order: Vec<usize>,
+    checkpointing: Option<Checkpointing>,
+}
+
+//called after every epoch, writes a checkpoint when one is due and drops the oldest ones
+pub(crate) fn after_epoch<T: Float>(network: &Network<T>) -> Result<(), SproutError> {
+    let Some(checkpointing) = &network.checkpointing else {
+        return Ok(());
+    };
+    if !network.epoch.is_multiple_of(checkpointing.every) {
+        return Ok(());
+    }
+    fs::create_dir_all(&checkpointing.dir)?;
+    save(network, &checkpointing.dir.join(format!("{}{:06}.{}", PREFIX, network.epoch, EXTENSION)))?;
+    if checkpointing.keep > 0 {
+        let checkpoints = list(&checkpointing.dir)?;
+        for old in &checkpoints[..checkpoints.len().saturating_sub(checkpointing.keep)] {
+            fs::remove_file(old)?;
+        }
+    }
+    Ok(())
+}
+
+//written next to the target and renamed over it, so an interrupted save never leaves half a checkpoint
+pub fn save<T: Float>(network: &Network<T>, path: &Path) -> Result<(), SproutError> {
+    let mut value = schema::to_value(network)?;
+    let state = TrainingState {
+        rng: network.rng.clone(),
+        order: network.order.clone(),
+        checkpointing: network.checkpointing.clone(),
+    };
+    value.as_object_mut().expect("network serializes to an object")
+        .insert("training_state".to_string(), serde_json::to_value(state)?);
+    let partial = path.with_extension("partial");
+    fs::write(&partial, serde_json::to_string(&value)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+pub fn load<T: Float>(path: &Path) -> Result<Network<T>, SproutError> {
+    let mut value: Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let state = value.as_object_mut().and_then(|object| object.remove("training_state"))
+        .ok_or_else(|| SproutError::Format(format!("{} is a saved model, not a checkpoint", path.display())))?;
+    let state: TrainingState = serde_json::from_value(state)?;
+    let mut network: Network<T> = schema::from_value(value)?;
+    network.rng = state.rng;
+    network.order = state.order;
+    network.checkpointing = state.checkpointing;
+    Ok(network)
+}
+
+//checkpoints in dir, oldest first
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>, SproutError> {
+    let mut checkpoints = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        if name.starts_with(PREFIX) && path.extension().and_then(|extension| extension.to_str()) == Some(EXTENSION) {
+            checkpoints.push(path);
+        }
+    }
+    //epochs are zero-padded, so names sort by epoch
+    checkpoints.sort();
+    Ok(checkpoints)
+}
+
+pub fn latest(dir: &Path) -> Result<Option<PathBuf>, SproutError> {
+    Ok(list(dir)?.pop())
+}
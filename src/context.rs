@@ -0,0 +1,6 @@
+use crate::layer::LayerCache;
+
+//everything one training step's backward pass needs from its forward pass, kept per call so the network itself stays untouched
+pub struct TrainingContext {
+    pub caches: Vec<LayerCache>, //one per layer
+}
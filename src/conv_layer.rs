@@ -0,0 +1,318 @@
+use rand::RngCore;
+use rayon::prelude::*;
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{
+    activation::{Activation, ActivationFunction},
+    conv_params::{ConvGradients, ConvParams, PaddingType},
+    dense_layer::{default_bias_init, default_weight_init},
+    error::SproutError,
+    float::Float,
+    initializer::Initializer,
+    layer::{Layer, LayerCache},
+    matmul::{matmul, transpose},
+    network::Image,
+    regularizer::{Regularizer, Units},
+    shape::Shape,
+    tensor::Tensor,
+};
+
+//depthwise convolution, one kernel per input channel sharing a single bias
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConvLayer<T = f64> {
+    pub activation: Activation,
+    pub params: ConvParams<T>,
+    #[serde(default = "default_weight_init")]
+    pub weight_init: Initializer,
+    #[serde(default = "default_bias_init")]
+    pub bias_init: Initializer,
+    #[serde(default)]
+    pub regularizer: Regularizer,
+}
+
+//what backward needs from a forward batch
+pub struct ConvCache<T = f64> {
+    pub data: Vec<Image<T>>, //sample > padded input
+    pub outputs: Vec<Image<T>>,
+}
+
+impl<T: Float> ConvLayer<T> {
+    //weights are created by build once the channel count is known
+    pub fn new(kernel: usize, padding_type: PaddingType, stride: usize, activation_fn: ActivationFunction) -> Self {
+        ConvLayer {
+            weight_init: Initializer::for_activation(&activation_fn),
+            bias_init: default_bias_init(),
+            regularizer: Regularizer::default(),
+            activation: Activation::new(activation_fn),
+            params: ConvParams::new(kernel, padding_type, stride),
+        }
+    }
+
+    pub fn weight_init(mut self, init: Initializer) -> Self {
+        self.weight_init = init;
+        self
+    }
+
+    pub fn bias_init(mut self, init: Initializer) -> Self {
+        self.bias_init = init;
+        self
+    }
+
+    pub fn regularize(mut self, regularizer: Regularizer) -> Self {
+        self.regularizer = regularizer;
+        self
+    }
+
+    pub fn predict_image(&self, inputs: &[Vec<Vec<T>>]) -> Image<T> {
+        self.check_built();
+        if self.params.padding_size() == 0 {
+            return self.conv_sample(inputs);
+        }
+        self.conv_sample(&self.params.pad(inputs))
+    }
+
+    fn check_built(&self) {
+        assert!(!self.params.weights.is_empty(), "conv layer has no weights yet, call Network::build with the input shape first");
+    }
+
+    fn conv_sample(&self, img: &[Vec<Vec<T>>]) -> Image<T> {
+        let params = &self.params;
+        let output_dims = params.get_output_dims(img);
+        let positions = output_dims[0] * output_dims[1];
+        let kernel_size = params.kernel * params.kernel;
+
+        img.par_iter().zip(params.weights.par_chunks(kernel_size)).map(|(channel, weights)| { //each channel
+            let cols = params.im2col(channel, output_dims);
+            let mut weighted_inputs = vec![params.bias; positions];
+            matmul(weights, &cols, &mut weighted_inputs, 1, kernel_size, positions);
+
+            weighted_inputs.chunks(output_dims[0]) //each img row
+                .map(|row| self.activation.function(row.to_vec()))
+                .collect()
+        }).collect()
+    }
+
+    fn conv_sample_gradients(&self, img: &[Vec<Vec<T>>], outputs: &[Vec<Vec<T>>], errors: Image<T>) -> (ConvGradients<T>, Image<T>) {
+        let params = &self.params;
+        let output_dims = params.get_output_dims(img);
+        let padded_dims = [img[0][0].len(), img[0].len()];
+        let positions = output_dims[0] * output_dims[1];
+        let kernel_size = params.kernel * params.kernel;
+        let padding = params.padding_size();
+
+        let channel_gradients: Vec<(Vec<T>, T, Vec<Vec<T>>)> = errors.into_par_iter()
+            .zip(outputs.par_iter())
+            .zip(img.par_iter())
+            .zip(params.weights.par_chunks(kernel_size))
+            .map(|(((delta_channel, output_channel), channel), weights)| { //each channel
+                let mut delta_output: Vec<T> = delta_channel.into_iter().flatten().collect();
+                if self.activation.function != ActivationFunction::SoftMax {
+                    for (delta_row, output_row) in delta_output.chunks_mut(output_dims[0]).zip(output_channel) {
+                        let activation_derivatives = self.activation.derivative(output_row.clone());
+                        for (delta, derivative) in delta_row.iter_mut().zip(activation_derivatives) {
+                            *delta *= derivative;
+                        }
+                    }
+                }
+
+                //dW = delta (1 x positions) * cols^T (positions x kernel_size)
+                let cols = params.im2col(channel, output_dims);
+                let mut weight_gradients = vec![T::zero(); kernel_size];
+                matmul(&delta_output, &transpose(&cols, kernel_size, positions), &mut weight_gradients, 1, positions, kernel_size);
+
+                //dCols = W^T (kernel_size x 1) * delta (1 x positions)
+                let mut delta_cols = vec![T::zero(); kernel_size * positions];
+                matmul(weights, &delta_output, &mut delta_cols, kernel_size, 1, positions);
+
+                //strip the padding added in the forward pass
+                let padded_delta = params.col2im(&delta_cols, output_dims, padded_dims);
+                let next_delta = padded_delta[padding..padded_dims[1] - padding].iter()
+                    .map(|row| row[padding..padded_dims[0] - padding].to_vec())
+                    .collect();
+
+                (weight_gradients, delta_output.iter().copied().sum(), next_delta)
+            }).collect();
+
+        let mut gradients = ConvGradients::zeros(img.len(), params.kernel);
+        let mut next_delta = Vec::with_capacity(img.len());
+        for (i, (weight_gradients, bias_gradient, channel_delta)) in channel_gradients.into_iter().enumerate() {
+            gradients.weights[i * kernel_size..(i + 1) * kernel_size].copy_from_slice(&weight_gradients);
+            gradients.bias += bias_gradient;
+            next_delta.push(channel_delta);
+        }
+
+        (gradients, next_delta)
+    }
+
+    pub fn rotate180(mat: &[Vec<T>]) -> Vec<Vec<T>> {
+        let mut new_mat = mat.to_vec();
+        for row in &mut new_mat {
+            row.reverse();
+        }
+        new_mat.reverse();
+        new_mat
+    }
+
+    pub fn add_padding_matrix(padding: usize, matrix: &[Vec<T>]) -> Vec<Vec<T>> {
+        let height = matrix.len();
+        let width = matrix[0].len();
+        let padded_height = height + 2 * padding;
+        let padded_width = width + 2 * padding;
+
+        let mut padded_image = vec![vec![T::zero(); padded_width]; padded_height];
+
+        for (i, row) in matrix.iter().enumerate() {
+            padded_image[i + padding][padding..padding + width].copy_from_slice(row);
+        }
+        padded_image
+    }
+
+    pub fn get_weights(&self) -> Vec<Vec<Vec<T>>> {
+        let params = &self.params;
+        params.weights.chunks(params.kernel * params.kernel)
+            .map(|channel| channel.chunks(params.kernel).map(|row| row.to_vec()).collect())
+            .collect()
+    }
+
+    pub fn get_bias(&self) -> T {
+        self.params.bias
+    }
+
+}
+
+//shape checks shared with pooling, which runs the same window over the input
+pub(crate) fn window_output_shape<T: Float>(name: &str, params: &ConvParams<T>, input: Shape) -> Result<Shape, String> {
+    let Shape::Image([channels, rows, cols]) = input else {
+        return Err(format!("{} layer needs an image input but receives {} flat values", name, input.size()));
+    };
+    if params.kernel == 0 || params.stride == 0 {
+        return Err(format!("kernel ({}) and stride ({}) must be at least 1", params.kernel, params.stride));
+    }
+    let padded_rows = rows + 2 * params.padding_size();
+    let padded_cols = cols + 2 * params.padding_size();
+    if params.kernel > padded_rows || params.kernel > padded_cols {
+        return Err(format!("{0} x {0} kernel does not fit the {1} x {2} (padded) input", params.kernel, padded_rows, padded_cols));
+    }
+    Ok(Shape::Image([
+        channels,
+        (padded_rows - params.kernel) / params.stride + 1,
+        (padded_cols - params.kernel) / params.stride + 1,
+    ]))
+}
+
+pub(crate) fn validate_window<T: Float>(params: &ConvParams<T>) -> Result<(), SproutError> {
+    if params.kernel == 0 || params.stride == 0 {
+        return Err(SproutError::InvalidConfig(format!("kernel ({}) and stride ({}) must be at least 1", params.kernel, params.stride)));
+    }
+    if !params.weights.len().is_multiple_of(params.kernel * params.kernel) {
+        return Err(SproutError::ShapeMismatch(format!(
+            "{} weights do not split into {1} x {1} kernels", params.weights.len(), params.kernel
+        )));
+    }
+    Ok(())
+}
+
+impl<T: Float> Layer<T> for ConvLayer<T> {
+    fn type_name(&self) -> &'static str {
+        "conv"
+    }
+
+    fn output_shape(&self, input: Shape) -> Result<Shape, String> {
+        let output = window_output_shape("convolutional", &self.params, input)?;
+        if self.params.weights.is_empty() {
+            return Err("convolutional layer has no weights yet, build the network first".to_string());
+        }
+        if let Shape::Image([channels, _, _]) = input {
+            if self.params.channels() != channels {
+                return Err(format!("convolutional layer has weights for {} channels but receives {}", self.params.channels(), channels));
+            }
+        }
+        Ok(output)
+    }
+
+    //creates the weights for the input's channel count
+    fn build(&mut self, input: Shape, rng: &mut dyn RngCore) -> Result<Shape, String> {
+        window_output_shape("convolutional", &self.params, input)?;
+        if let Shape::Image([channels, _, _]) = input {
+            self.params.init(channels, &self.weight_init, &self.bias_init, rng).map_err(|err| err.to_string())?;
+        }
+        self.output_shape(input)
+    }
+
+    fn forward(&self, inputs: Vec<Tensor<T>>) -> (Vec<Tensor<T>>, LayerCache) {
+        self.check_built();
+        let data: Vec<Image<T>> = inputs.par_iter().map(|sample| self.params.pad(sample.as_image())).collect();
+        let outputs: Vec<Image<T>> = data.par_iter().map(|sample| self.conv_sample(sample)).collect();
+        let tensors = outputs.iter().cloned().map(Tensor::Image).collect();
+        (tensors, Box::new(ConvCache { data, outputs }))
+    }
+
+    fn predict(&self, input: &Tensor<T>) -> Tensor<T> {
+        Tensor::Image(self.predict_image(input.as_image()))
+    }
+
+    fn backward(&self, cache: &LayerCache, errors: Vec<Tensor<T>>) -> (Vec<Tensor<T>>, Vec<Vec<T>>) {
+        let cache: &ConvCache<T> = cache.downcast_ref().expect("conv layer got another layer's cache");
+        let params = &self.params;
+        let (sample_gradients, next_deltas): (Vec<ConvGradients<T>>, Vec<Image<T>>) = errors.into_par_iter()
+            .zip(cache.data.par_iter().zip(&cache.outputs))
+            .map(|(sample_errors, (img, outputs))| self.conv_sample_gradients(img, outputs, sample_errors.into_image()))
+            .unzip();
+
+        //summed in sample order so training is reproducible, a parallel reduce regroups the float additions
+        let mut gradients = ConvGradients::zeros(params.channels(), params.kernel);
+        for sample in &sample_gradients {
+            gradients.accumulate(sample);
+        }
+        (next_deltas.into_iter().map(Tensor::Image).collect(), vec![gradients.weights, vec![gradients.bias]])
+    }
+
+    fn parameters(&self) -> Vec<&[T]> {
+        vec![&self.params.weights, std::slice::from_ref(&self.params.bias)]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut [T]> {
+        vec![&mut self.params.weights, std::slice::from_mut(&mut self.params.bias)]
+    }
+
+    fn parameter_names(&self) -> Vec<String> {
+        vec!["weights".to_string(), "bias".to_string()]
+    }
+
+    fn parameter_pointers(&self) -> Vec<String> {
+        vec!["/params/weights".to_string(), "/params/bias".to_string()]
+    }
+
+    //channel > kernel rows > kernel cols, the bias is a scalar
+    fn parameter_shapes(&self) -> Vec<Vec<usize>> {
+        vec![vec![self.params.channels(), self.params.kernel, self.params.kernel], vec![]]
+    }
+
+    fn to_json(&self) -> Result<Value, SproutError> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn validate(&self) -> Result<(), SproutError> {
+        self.regularizer.validate()?;
+        validate_window(&self.params)
+    }
+
+    fn reset(&mut self, rng: &mut dyn RngCore) -> Result<(), SproutError> {
+        //checked up front so a failed reset leaves the trained parameters alone
+        self.weight_init.validate()?;
+        self.bias_init.validate()?;
+        let channels = self.params.channels();
+        self.params.weights = vec![];
+        self.params.init(channels, &self.weight_init, &self.bias_init, rng)
+    }
+
+    fn regularizer(&self) -> Option<&Regularizer> {
+        Some(&self.regularizer)
+    }
+
+    //depthwise, each channel's kernel feeds only that channel's output map
+    fn weight_units(&self) -> Units {
+        Units::Blocks(self.params.channels())
+    }
+}
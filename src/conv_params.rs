@@ -1,7 +1,7 @@
 use serde_derive::{Serialize, Deserialize};
-use rand::prelude::*;
+use rand::RngCore;
 
-use crate::activation::{ActivationFunction};
+use crate::{error::SproutError, float::Float, initializer::{Fans, Initializer}};
 
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -12,151 +12,159 @@ pub enum PaddingType {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ConvParams {
+pub struct ConvParams<T = f64> {
     pub kernel: usize,
     pub padding_type: PaddingType,
     pub padding: usize,
     pub stride: usize,
-    pub data: Vec<Vec<Vec<f64>>>, //channel > img matrix
-    pub weights: Vec<Vec<Vec<f64>>>, //1 kernel > depth of kernel -> kernel weights
-    pub bias: f64, //bias for each kernel not including depth
-    pub outputs: Vec<Vec<Vec<f64>>>, //channel > mat
-    pub inputs: Vec<Vec<Vec<f64>>>, //channel > mat
+    pub weights: Vec<T>, //channel > kernel rows > kernel cols, row-major
+    pub bias: T, //bias for each kernel not including depth
 }
 
-impl ConvParams {
+#[derive(Debug, Clone)]
+pub struct ConvGradients<T = f64> {
+    pub weights: Vec<T>, //channel > kernel rows > kernel cols, row-major
+    pub bias: T,
+}
+
+impl<T: Float> ConvParams<T> {
     pub fn new(kernel: usize, padding_type: PaddingType, stride: usize) -> Self {
-        
-        let mut cp = ConvParams {
+        let mut params = ConvParams {
             kernel,
             padding_type,
             padding: 0,
             stride,
-            data: vec![],
             weights: vec![],
-            bias: 0.0,
-            outputs: vec![],
-            inputs: vec![],
+            bias: T::zero(),
         };
-        cp
+        params.padding = params.padding_size();
+        params
     }
 
-    pub fn init(&mut self, activation: ActivationFunction) {
-        if self.weights.len() != 0 as usize {
-            return;
-        }
-        for i in 0..self.data.len() {
-            self.weights.push(vec![vec![0.0; self.kernel]; self.kernel]);
+    //draws weights for the channel count, a layer that already has weights keeps them
+    pub fn init(&mut self, channels: usize, weight_init: &Initializer, bias_init: &Initializer, rng: &mut dyn RngCore) -> Result<(), SproutError> {
+        if !self.weights.is_empty() {
+            return Ok(());
         }
+        let area = self.kernel * self.kernel;
+        self.weights = vec![T::zero(); channels * area];
+        //depthwise, every kernel sees kernel x kernel inputs of its own channel
+        let fans = Fans { fan_in: area, fan_out: area, rows: channels, cols: area };
+        weight_init.fill(&mut self.weights, fans, rng)?;
+        let mut bias = [T::zero()];
+        bias_init.fill(&mut bias, Fans { rows: 1, cols: 1, ..fans }, rng)?;
+        self.bias = bias[0];
+        Ok(())
+    }
 
-        match activation {
-            ActivationFunction::Sigmoid => 
-                {
-                    let std_dev = (2.0 / (self.weights.len() * (self.weights[0].len() * self.weights[0].len())) as f64).sqrt();
-                    let limit = (3.0 * std_dev).sqrt();
-                    self.bias = 0.0;
-
-                    for i in 0..self.weights.len() {
-                        for j in 0..self.weights[i].len() {
-                            for k in 0..self.weights[i][j].len() {
-                                self.weights[i][j][k] = thread_rng().gen_range(-limit..limit) * std_dev;
-                            }
-                        }
-                    }
-                },
-            ActivationFunction::ReLU => 
-                {
-                    let std_dev = (2.0 / (self.weights.len() * (self.weights[0].len() * self.weights[0].len())) as f64).sqrt();
-                    let limit = (3.0 * std_dev).sqrt();
-                    self.bias = 0.0;
-
-                    for i in 0..self.weights.len() {
-                        for j in 0..self.weights[i].len() {
-                            for k in 0..self.weights[i][j].len() {
-                                self.weights[i][j][k] = thread_rng().gen_range(-std_dev..std_dev);
-                            }
-                        }
-                    }
-                },
-            ActivationFunction::TanH => 
-                {
-                    let std_dev = (1.0 / (self.weights.len() * (self.weights[0].len() * self.weights[0].len())) as f64).sqrt();
-                    let limit = (3.0 * std_dev).sqrt();
-                    self.bias = 0.0;
-
-                    for i in 0..self.weights.len() {
-                        for j in 0..self.weights[i].len() {
-                            for k in 0..self.weights[i][j].len() {
-                                self.weights[i][j][k] = thread_rng().gen_range(-limit..limit) * std_dev;
-                            }
-                        }
-                    }
-                },
-            ActivationFunction::SoftMax => 
-                {
-                    let std_dev = (1.0 / (self.weights.len() * (self.weights[0].len() * self.weights[0].len())) as f64).sqrt();
-                    let limit = (3.0 * std_dev).sqrt();
-                    self.bias = 0.0;
-
-                    for i in 0..self.weights.len() {
-                        for j in 0..self.weights[i].len() {
-                            for k in 0..self.weights[i][j].len() {
-                                self.weights[i][j][k] = thread_rng().gen_range(-limit..limit) * std_dev;
-                            }
-                        }
-                    }
-                },
-        }
+    pub fn channels(&self) -> usize {
+        self.weights.len() / (self.kernel * self.kernel)
     }
 
-    pub fn add_padding(&mut self, activation: ActivationFunction) {
-        if self.padding_type == PaddingType::Valid {
-            self.data = self.inputs.clone();
-            self.init(activation);
-            return;
+    //a 0 kernel gets no padding, it is rejected when the layer is built
+    pub fn padding_size(&self) -> usize {
+        match self.padding_type {
+            PaddingType::Valid => 0,
+            PaddingType::Same => self.kernel.saturating_sub(1) / 2,
+            PaddingType::Full => self.kernel.saturating_sub(1),
         }
-        let mut padded_image = vec![];
-        let mut padding = (self.kernel - 1) / 2;
+    }
 
-        if self.padding_type == PaddingType::Full {
-            padding = self.kernel - 1;
+    pub fn pad(&self, inputs: &[Vec<Vec<T>>]) -> Vec<Vec<Vec<T>>> {
+        let padding = self.padding_size();
+        if padding == 0 {
+            return inputs.to_vec();
         }
+        let height = inputs[0].len();
+        let width = inputs[0][0].len();
+        let padded_height = height + 2 * padding;
+        let padded_width = width + 2 * padding;
 
-        self.padding = padding;
-        let height = self.inputs[0].len();
-        let width = self.inputs[0][0].len();
-        let padded_height = height + 2 * self.padding;
-        let padded_width = width + 2 * self.padding;
-        
-        for i in 0..self.inputs.len() {
-            padded_image.push(vec![vec![0.0; padded_width]; padded_height]);
-        }
+        let mut padded_image = vec![vec![vec![T::zero(); padded_width]; padded_height]; inputs.len()];
 
-        for i in 0..padded_image.len() {
-            for j in 0..height {
-                for k in 0..width {
-                    padded_image[i][j + self.padding][k + self.padding] = self.inputs[i][j][k];
-                }
+        for (padded_channel, channel) in padded_image.iter_mut().zip(inputs) {
+            for (j, row) in channel.iter().enumerate() {
+                padded_channel[j + padding][padding..padding + width].copy_from_slice(row);
             }
         }
-
-        self.data = padded_image;
-        self.init(activation);
+        padded_image
     }
 
-    pub fn get_output_dims(&self) -> [usize; 2] {
-        let height = self.data[0].len();
-        let width = self.data[0][0].len();
+    pub fn get_output_dims(&self, data: &[Vec<Vec<T>>]) -> [usize; 2] {
+        let height = data[0].len();
+        let width = data[0][0].len();
 
         let out_width = (width - self.kernel) / self.stride + 1;
         let out_height = (height - self.kernel) / self.stride + 1;
 
         [out_width, out_height]
     }
+
+    //lowers one padded channel into a (kernel * kernel) x (out_height * out_width) matrix
+    pub fn im2col(&self, channel: &[Vec<T>], output_dims: [usize; 2]) -> Vec<T> {
+        let [out_width, out_height] = output_dims;
+        let positions = out_width * out_height;
+        let mut cols = vec![T::zero(); self.kernel * self.kernel * positions];
+
+        for kern_row in 0..self.kernel {
+            for kern_col in 0..self.kernel {
+                let col_row = &mut cols[(kern_row * self.kernel + kern_col) * positions..][..positions];
+                for (j, out_row) in col_row.chunks_exact_mut(out_width).enumerate() {
+                    let img_row = &channel[j * self.stride + kern_row][kern_col..];
+                    if self.stride == 1 {
+                        out_row.copy_from_slice(&img_row[..out_width]);
+                    } else {
+                        for (col, pixel) in out_row.iter_mut().zip(img_row.iter().step_by(self.stride)) {
+                            *col = *pixel;
+                        }
+                    }
+                }
+            }
+        }
+        cols
+    }
+
+    //inverse of im2col, overlapping windows are summed into a padded_height x padded_width channel
+    pub fn col2im(&self, cols: &[T], output_dims: [usize; 2], padded_dims: [usize; 2]) -> Vec<Vec<T>> {
+        let [out_width, out_height] = output_dims;
+        let [padded_width, padded_height] = padded_dims;
+        let positions = out_width * out_height;
+        let mut channel = vec![vec![T::zero(); padded_width]; padded_height];
+
+        for kern_row in 0..self.kernel {
+            for kern_col in 0..self.kernel {
+                let col_row = &cols[(kern_row * self.kernel + kern_col) * positions..][..positions];
+                for (j, out_row) in col_row.chunks_exact(out_width).enumerate() {
+                    let img_row = &mut channel[j * self.stride + kern_row];
+                    for (k, col) in out_row.iter().enumerate() {
+                        img_row[k * self.stride + kern_col] += *col;
+                    }
+                }
+            }
+        }
+        channel
+    }
+
     
     pub fn print_kernels(&self) {
         println!("--------------------------\nKernel Dimensions: {} x {}", self.kernel, self.kernel);
         println!("Weights: \n{:#?}", self.weights);
         println!("Biases: \n{:#?}\n-------------------------------", self.bias);
     }
-}
\ No newline at end of file
+}
+
+impl<T: Float> ConvGradients<T> {
+    pub fn zeros(channels: usize, kernel: usize) -> Self {
+        ConvGradients {
+            weights: vec![T::zero(); channels * kernel * kernel],
+            bias: T::zero(),
+        }
+    }
+
+    pub fn accumulate(&mut self, other: &ConvGradients<T>) {
+        for (grad, other_grad) in self.weights.iter_mut().zip(&other.weights) {
+            *grad += *other_grad;
+        }
+        self.bias += other.bias;
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    fs,
+    iter,
+    path::{Path, PathBuf},
+    sync::{mpsc::{self, Receiver}, Arc},
+    thread,
+    vec,
+};
+
+use rand::{seq::SliceRandom, RngCore};
+use rayon::prelude::*;
+
+use crate::{augment::{self, Augmentation}, error::SproutError, float::Float, network::ConvSample, tensor::Tensor};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample<T = f64> {
+    pub input: Tensor<T>,
+    pub target: Vec<T>,
+}
+
+impl<T: Float> From<[Vec<T>; 2]> for Sample<T> {
+    fn from([input, target]: [Vec<T>; 2]) -> Self {
+        Sample { input: Tensor::Flat(input), target }
+    }
+}
+
+impl<T: Float> From<ConvSample<T>> for Sample<T> {
+    fn from((input, target): ConvSample<T>) -> Self {
+        Sample { input: Tensor::Image(input), target }
+    }
+}
+
+//indexed samples, get may load from disk so it can fail and is called from the loader's threads
+pub trait Dataset<T: Float = f64>: Send + Sync {
+    fn len(&self) -> usize;
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError>;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Float> Dataset<T> for Vec<Sample<T>> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError> {
+        self.as_slice().get(index).cloned().ok_or_else(|| out_of_range(index, self.as_slice().len()))
+    }
+}
+
+impl<T: Float> Dataset<T> for Vec<[Vec<T>; 2]> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError> {
+        self.as_slice().get(index).cloned().map(Sample::from).ok_or_else(|| out_of_range(index, self.as_slice().len()))
+    }
+}
+
+impl<T: Float> Dataset<T> for Vec<ConvSample<T>> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError> {
+        self.as_slice().get(index).cloned().map(Sample::from).ok_or_else(|| out_of_range(index, self.as_slice().len()))
+    }
+}
+
+pub(crate) fn out_of_range(index: usize, len: usize) -> SproutError {
+    SproutError::InvalidConfig(format!("sample {} is out of range for a dataset of {}", index, len))
+}
+
+type FileLoader<T> = dyn Fn(&Path) -> Result<Sample<T>, SproutError> + Send + Sync;
+
+//one file per sample, read only when the sample is asked for so the dataset doesn't have to fit in memory
+#[derive(Clone)]
+pub struct FileDataset<T = f64> {
+    pub paths: Vec<PathBuf>,
+    loader: Arc<FileLoader<T>>,
+}
+
+impl<T: Float> FileDataset<T> {
+    pub fn new(paths: Vec<PathBuf>, loader: impl Fn(&Path) -> Result<Sample<T>, SproutError> + Send + Sync + 'static) -> Self {
+        FileDataset { paths, loader: Arc::new(loader) }
+    }
+
+    //every file in dir with the extension, sorted by name so the indices are stable
+    pub fn from_dir(
+        dir: impl AsRef<Path>,
+        extension: &str,
+        loader: impl Fn(&Path) -> Result<Sample<T>, SproutError> + Send + Sync + 'static,
+    ) -> Result<Self, SproutError> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().and_then(|ext| ext.to_str()) == Some(extension) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(Self::new(paths, loader))
+    }
+}
+
+impl<T: Float> Dataset<T> for FileDataset<T> {
+    fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError> {
+        let path = self.paths.get(index).ok_or_else(|| out_of_range(index, self.paths.len()))?;
+        (self.loader)(path).map_err(|err| err.context(&path.display().to_string()))
+    }
+}
+
+//the samples of another dataset at the given indices, in that order, e.g. one side of a train/validation split
+#[derive(Clone)]
+pub struct Subset<T = f64> {
+    dataset: Arc<dyn Dataset<T>>,
+    pub indices: Vec<usize>,
+}
+
+impl<T: Float> Subset<T> {
+    pub fn new(dataset: Arc<dyn Dataset<T>>, indices: Vec<usize>) -> Self {
+        Subset { dataset, indices }
+    }
+}
+
+impl<T: Float> Dataset<T> for Subset<T> {
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError> {
+        let inner = *self.indices.get(index).ok_or_else(|| out_of_range(index, self.indices.len()))?;
+        self.dataset.get(inner)
+    }
+}
+
+//batches a dataset, see Network::train
+#[derive(Clone)]
+pub struct DataLoader<T = f64> {
+    dataset: Arc<dyn Dataset<T>>,
+    pub batch_size: usize,
+    pub shuffle: bool, //reshuffled every epoch
+    pub drop_last: bool, //skip the last batch when it comes up short
+    pub prefetch: usize, //batches loaded ahead on a background thread, 0 loads them as they are needed
+    pub augmentation: Option<Augmentation>, //applied to every sample as it is loaded
+}
+
+impl<T: Float> DataLoader<T> {
+    //shuffles, keeps the short last batch and loads on the calling thread
+    pub fn new(dataset: impl Dataset<T> + 'static, batch_size: usize) -> Self {
+        Self::from_arc(Arc::new(dataset), batch_size)
+    }
+
+    pub fn from_arc(dataset: Arc<dyn Dataset<T>>, batch_size: usize) -> Self {
+        DataLoader { dataset, batch_size, shuffle: true, drop_last: false, prefetch: 0, augmentation: None }
+    }
+
+    pub fn shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    pub fn drop_last(mut self, drop_last: bool) -> Self {
+        self.drop_last = drop_last;
+        self
+    }
+
+    pub fn prefetch(mut self, batches: usize) -> Self {
+        self.prefetch = batches;
+        self
+    }
+
+    pub fn augment(mut self, augmentation: Augmentation) -> Self {
+        self.augmentation = Some(augmentation);
+        self
+    }
+
+    //the same loader over only the samples at indices
+    pub fn subset(&self, indices: Vec<usize>) -> Self {
+        DataLoader { dataset: Arc::new(Subset::new(self.dataset.clone(), indices)), augmentation: self.augmentation.clone(), ..*self }
+    }
+
+    pub fn dataset(&self) -> &Arc<dyn Dataset<T>> {
+        &self.dataset
+    }
+
+    pub fn len(&self) -> usize {
+        self.dataset.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dataset.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), SproutError> {
+        if self.batch_size == 0 {
+            return Err(SproutError::InvalidConfig("batch size must be at least 1".to_string()));
+        }
+        if self.dataset.is_empty() {
+            return Err(SproutError::InvalidConfig("training data is empty".to_string()));
+        }
+        if self.drop_last && self.dataset.len() < self.batch_size {
+            return Err(SproutError::InvalidConfig(format!(
+                "drop_last leaves no batches, {} samples is less than a batch of {}", self.dataset.len(), self.batch_size
+            )));
+        }
+        if let Some(augmentation) = &self.augmentation {
+            augmentation.validate()?;
+        }
+        Ok(())
+    }
+
+    //samples that make it into an epoch's batches
+    pub fn samples_per_epoch(&self) -> usize {
+        match self.drop_last {
+            true => self.len() - self.len() % self.batch_size.max(1),
+            false => self.len(),
+        }
+    }
+
+    //one epoch, in dataset order unless the loader shuffles
+    pub fn batches(&self, rng: &mut dyn RngCore) -> Batches<T> {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        if self.shuffle {
+            order.shuffle(rng);
+        }
+        self.batches_in(&order, rng)
+    }
+
+    //one epoch visiting the samples in the given order, rng only seeds the augmentation
+    pub fn batches_in(&self, order: &[usize], rng: &mut dyn RngCore) -> Batches<T> {
+        let mut chunks: Vec<Vec<usize>> = order.chunks(self.batch_size.max(1)).map(|chunk| chunk.to_vec()).collect();
+        if self.drop_last && chunks.last().is_some_and(|chunk| chunk.len() < self.batch_size) {
+            chunks.pop();
+        }
+        //one draw per epoch, every sample's transforms are seeded from it, see augment::sample_rng
+        let augmentation = self.augmentation.clone().map(|augmentation| (augmentation, rng.next_u64()));
+        if self.prefetch == 0 {
+            return Batches { dataset: Some(self.dataset.clone()), chunks: chunks.into_iter().enumerate(), augmentation, prefetched: None };
+        }
+        //the thread stops once the receiver is dropped and its next send fails
+        let (sender, receiver) = mpsc::sync_channel(self.prefetch);
+        let dataset = self.dataset.clone();
+        thread::spawn(move || {
+            for (i, chunk) in chunks.into_iter().enumerate() {
+                let batch = load_batch(dataset.as_ref(), &chunk, augmentation.as_ref(), i);
+                let failed = batch.is_err();
+                if sender.send(batch).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Batches { dataset: None, chunks: vec![].into_iter().enumerate(), augmentation: None, prefetched: Some(receiver) }
+    }
+}
+
+//samples of a batch are loaded (and augmented) in parallel, in batch order
+fn load_batch<T: Float>(
+    dataset: &dyn Dataset<T>,
+    indices: &[usize],
+    augmentation: Option<&(Augmentation, u64)>,
+    batch: usize,
+) -> Result<Vec<Sample<T>>, SproutError> {
+    let load = |position: usize, index: usize| -> Result<Sample<T>, SproutError> {
+        let mut sample = dataset.get(index)?;
+        if let Some((augmentation, seed)) = augmentation {
+            augmentation.apply_sample(&mut sample, &mut augment::sample_rng(*seed, batch, position))?;
+        }
+        Ok(sample)
+    };
+    let mut samples = indices.par_iter().enumerate()
+        .map(|(position, &index)| load(position, index).map_err(|err| err.context(&format!("sample {}", index))))
+        .collect::<Result<Vec<Sample<T>>, SproutError>>()?;
+    if let Some((augmentation, seed)) = augmentation {
+        augmentation.apply_batch(&mut samples, &mut augment::sample_rng(*seed, batch, indices.len()))?;
+    }
+    Ok(samples)
+}
+
+pub struct Batches<T = f64> {
+    dataset: Option<Arc<dyn Dataset<T>>>,
+    chunks: iter::Enumerate<vec::IntoIter<Vec<usize>>>,
+    augmentation: Option<(Augmentation, u64)>,
+    prefetched: Option<Receiver<Result<Vec<Sample<T>>, SproutError>>>,
+}
+
+impl<T: Float> Iterator for Batches<T> {
+    type Item = Result<Vec<Sample<T>>, SproutError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (&self.prefetched, &self.dataset) {
+            (Some(receiver), _) => receiver.recv().ok(),
+            (None, Some(dataset)) => self.chunks.next().map(|(i, chunk)| load_batch(dataset.as_ref(), &chunk, self.augmentation.as_ref(), i)),
+            (None, None) => None,
+        }
+    }
+}
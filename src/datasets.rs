@@ -0,0 +1,5 @@
+//loaders for standard datasets on local disk, each one is a Dataset that can go straight into a DataLoader
+
+pub mod csv;
+pub mod image_folder;
+pub mod mnist;
@@ -0,0 +1,377 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fs, path::{Path, PathBuf}};
+
+use crate::{
+    dataset::{out_of_range, Dataset, Sample},
+    error::SproutError,
+    float::Float,
+    tensor::Tensor,
+};
+
+//tabular data from a CSV file: feature columns become the inputs, target columns the outputs.
+//numeric targets are regression targets, categorical ones are classes
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Column {
+    Name(String), //header name, needs a header row
+    Index(usize), //0 based
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Self {
+        Column::Name(name.to_string())
+    }
+}
+
+impl From<String> for Column {
+    fn from(name: String) -> Self {
+        Column::Name(name)
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Self {
+        Column::Index(index)
+    }
+}
+
+//how the categories of a text column become numbers, categories are numbered in sorted order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Label, //one value, the category's index
+    OneHot, //one value per category
+}
+
+//what happens to a row with a missing feature, rows missing a target are always dropped.
+//categorical columns are imputed with their most frequent category whatever the strategy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Missing {
+    Drop,
+    Mean,
+    Median,
+    Constant(f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvLoader {
+    path: PathBuf,
+    delimiter: char,
+    header: bool,
+    features: Option<Vec<Column>>, //every column that isn't a target when None
+    targets: Vec<Column>,
+    categorical: Vec<(Column, Encoding)>,
+    missing: Missing,
+    missing_values: Vec<String>,
+}
+
+impl CsvLoader {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        CsvLoader {
+            path: path.into(),
+            delimiter: ',',
+            header: true,
+            features: None,
+            targets: vec![],
+            categorical: vec![],
+            missing: Missing::Drop,
+            missing_values: ["", "NA", "N/A", "NaN", "nan", "null", "?"].map(String::from).to_vec(),
+        }
+    }
+
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    pub fn features<C: Into<Column>>(mut self, columns: impl IntoIterator<Item = C>) -> Self {
+        self.features = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn targets<C: Into<Column>>(mut self, columns: impl IntoIterator<Item = C>) -> Self {
+        self.targets = columns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn target(self, column: impl Into<Column>) -> Self {
+        self.targets([column.into()])
+    }
+
+    pub fn categorical(mut self, column: impl Into<Column>, encoding: Encoding) -> Self {
+        self.categorical.push((column.into(), encoding));
+        self
+    }
+
+    pub fn missing(mut self, missing: Missing) -> Self {
+        self.missing = missing;
+        self
+    }
+
+    //cells (trimmed) that count as missing, replaces the defaults
+    pub fn missing_values<S: Into<String>>(mut self, values: impl IntoIterator<Item = S>) -> Self {
+        self.missing_values = values.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn load<T: Float>(&self) -> Result<Tabular<T>, SproutError> {
+        let text = fs::read_to_string(&self.path).map_err(|err| SproutError::from(err).context(&self.path.display().to_string()))?;
+        self.parse(&text).map_err(|err| err.context(&self.path.display().to_string()))
+    }
+
+    //same as load on the file's contents
+    pub fn parse<T: Float>(&self, text: &str) -> Result<Tabular<T>, SproutError> {
+        if self.targets.is_empty() {
+            return Err(SproutError::InvalidConfig("no target columns, set them with target/targets".to_string()));
+        }
+        let mut records = parse_records(text, self.delimiter)?;
+        let names: Vec<String> = match self.header {
+            true if records.is_empty() => return Err(SproutError::Format("CSV has no header row".to_string())),
+            true => records.remove(0).1.into_iter().map(|name| name.trim().to_string()).collect(),
+            false => (0..records.first().map_or(0, |(_, record)| record.len())).map(|i| i.to_string()).collect(),
+        };
+        if let Some((line, record)) = records.iter().find(|(_, record)| record.len() != names.len()) {
+            return Err(SproutError::Format(format!("line {} has {} fields but the CSV has {} columns", line, record.len(), names.len())));
+        }
+
+        let targets = self.targets.iter().map(|column| resolve(column, &names)).collect::<Result<Vec<_>, _>>()?;
+        let features = match &self.features {
+            Some(columns) => columns.iter().map(|column| resolve(column, &names)).collect::<Result<Vec<_>, _>>()?,
+            None => (0..names.len()).filter(|i| !targets.contains(i)).collect(),
+        };
+        if let Some(i) = features.iter().find(|i| targets.contains(i)) {
+            return Err(SproutError::InvalidConfig(format!("column '{}' is both a feature and a target", names[*i])));
+        }
+        let mut encodings = HashMap::new();
+        for (column, encoding) in &self.categorical {
+            encodings.insert(resolve(column, &names)?, *encoding);
+        }
+
+        let is_missing = |cell: &str| self.missing_values.iter().any(|missing| missing == cell.trim());
+        let total = records.len();
+        //targets can't be imputed, and Drop drops rows with missing features too
+        let dropped_columns: &[usize] = if self.missing == Missing::Drop { &features } else { &[] };
+        records.retain(|(_, record)| !targets.iter().chain(dropped_columns).any(|i| is_missing(&record[*i])));
+        if records.is_empty() {
+            return Err(SproutError::InvalidConfig(format!("none of the {} rows is left once rows with missing values are dropped", total)));
+        }
+
+        let mut columns = BTreeMap::new();
+        for &i in features.iter().chain(&targets) {
+            let cells: Vec<(usize, &str)> = records.iter().map(|(line, record)| (*line, record[i].trim())).collect();
+            let column = match encodings.get(&i) {
+                Some(encoding) => Encoded::categorical(&cells, *encoding, &is_missing),
+                None => Encoded::numeric(&cells, self.missing, &is_missing),
+            };
+            let column = column.map_err(|err| err.context(&format!("column '{}'", names[i])))?;
+            columns.insert(i, column);
+        }
+
+        let row = |indices: &[usize], r: usize| -> Vec<T> {
+            indices.iter().flat_map(|i| columns[i].values(r)).map(T::from_f64).collect()
+        };
+        let names_of = |indices: &[usize]| -> Vec<String> {
+            indices.iter().flat_map(|i| columns[i].names(&names[*i])).collect()
+        };
+        let categories = features.iter().chain(&targets)
+            .filter_map(|i| match &columns[i] {
+                Encoded::Categorical { categories, .. } => Some((names[*i].clone(), categories.clone())),
+                Encoded::Numeric(_) => None,
+            })
+            .collect();
+        Ok(Tabular {
+            features: (0..records.len()).map(|r| row(&features, r)).collect(),
+            targets: (0..records.len()).map(|r| row(&targets, r)).collect(),
+            feature_names: names_of(&features),
+            target_names: names_of(&targets),
+            categories,
+            dropped: total - records.len(),
+        })
+    }
+}
+
+fn resolve(column: &Column, names: &[String]) -> Result<usize, SproutError> {
+    match column {
+        Column::Index(i) if *i < names.len() => Ok(*i),
+        Column::Index(i) => Err(SproutError::InvalidConfig(format!("column {} is out of range, the CSV has {} columns", i, names.len()))),
+        Column::Name(name) => names.iter().position(|column| column == name)
+            .ok_or_else(|| SproutError::InvalidConfig(format!("no column named '{}' (columns: {})", name, names.join(", ")))),
+    }
+}
+
+enum Encoded {
+    Numeric(Vec<f64>),
+    Categorical { categories: Vec<String>, indices: Vec<usize>, encoding: Encoding },
+}
+
+impl Encoded {
+    fn numeric(cells: &[(usize, &str)], missing: Missing, is_missing: &dyn Fn(&str) -> bool) -> Result<Self, SproutError> {
+        let mut values = Vec::with_capacity(cells.len());
+        for (line, cell) in cells {
+            values.push(match is_missing(cell) {
+                true => None,
+                false => Some(cell.parse::<f64>().ok().filter(|x| x.is_finite()).ok_or_else(|| SproutError::InvalidConfig(format!(
+                    "line {}: '{}' is not a number, mark the column categorical if it holds categories", line, cell
+                )))?),
+            });
+        }
+        let mut present: Vec<f64> = values.iter().flatten().copied().collect();
+        let fill = match missing {
+            _ if present.len() == values.len() => 0.0,
+            Missing::Constant(value) => value,
+            _ if present.is_empty() => return Err(SproutError::InvalidConfig("every value is missing, there is nothing to impute from".to_string())),
+            Missing::Drop => unreachable!("rows with missing values are dropped before encoding"),
+            Missing::Mean => present.iter().sum::<f64>() / present.len() as f64,
+            Missing::Median =>
+                {
+                    present.sort_by(f64::total_cmp);
+                    let mid = present.len() / 2;
+                    match present.len() % 2 {
+                        0 => (present[mid - 1] + present[mid]) / 2.0,
+                        _ => present[mid],
+                    }
+                },
+        };
+        Ok(Encoded::Numeric(values.into_iter().map(|value| value.unwrap_or(fill)).collect()))
+    }
+
+    fn categorical(cells: &[(usize, &str)], encoding: Encoding, is_missing: &dyn Fn(&str) -> bool) -> Result<Self, SproutError> {
+        let categories: Vec<String> = cells.iter()
+            .filter(|(_, cell)| !is_missing(cell))
+            .map(|(_, cell)| cell.to_string())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        if categories.is_empty() {
+            return Err(SproutError::InvalidConfig("every value is missing, there are no categories to encode".to_string()));
+        }
+        let index: HashMap<&str, usize> = categories.iter().enumerate().map(|(i, category)| (category.as_str(), i)).collect();
+        let mut counts = vec![0; categories.len()];
+        for (_, cell) in cells {
+            if let Some(i) = index.get(cell) {
+                counts[*i] += 1;
+            }
+        }
+        //ties go to the first category in sorted order
+        let most_frequent = counts.iter().enumerate().rev().max_by_key(|(_, count)| **count).map_or(0, |(i, _)| i);
+        let indices = cells.iter().map(|(_, cell)| index.get(cell).copied().unwrap_or(most_frequent)).collect();
+        Ok(Encoded::Categorical { categories, indices, encoding })
+    }
+
+    fn values(&self, row: usize) -> Vec<f64> {
+        match self {
+            Encoded::Numeric(values) => vec![values[row]],
+            Encoded::Categorical { indices, encoding: Encoding::Label, .. } => vec![indices[row] as f64],
+            Encoded::Categorical { categories, indices, encoding: Encoding::OneHot } =>
+                (0..categories.len()).map(|i| if i == indices[row] { 1.0 } else { 0.0 }).collect(),
+        }
+    }
+
+    //one-hot columns expand to "column=category"
+    fn names(&self, name: &str) -> Vec<String> {
+        match self {
+            Encoded::Categorical { categories, encoding: Encoding::OneHot, .. } =>
+                categories.iter().map(|category| format!("{}={}", name, category)).collect(),
+            _ => vec![name.to_string()],
+        }
+    }
+}
+
+//records with the line they start on, quoted fields may hold delimiters, newlines and "" for a quote
+fn parse_records(text: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, SproutError> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let (mut line, mut start) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.strip_prefix('\u{feff}').unwrap_or(text).chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') =>
+                {
+                    field.push('"');
+                    chars.next();
+                },
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            },
+            '\n' if !quoted =>
+                {
+                    record.push(std::mem::take(&mut field));
+                    //blank lines are skipped
+                    if record.len() > 1 || !record[0].trim().is_empty() {
+                        records.push((start, std::mem::take(&mut record)));
+                    }
+                    record.clear();
+                    line += 1;
+                    start = line;
+                },
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {},
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            c =>
+                {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                },
+        }
+    }
+    if quoted {
+        return Err(SproutError::Format(format!("quoted field starting on line {} is never closed", start)));
+    }
+    record.push(field);
+    if record.len() > 1 || !record[0].trim().is_empty() {
+        records.push((start, record));
+    }
+    Ok(records)
+}
+
+//rows of encoded features and targets, ready for dense_train or a DataLoader
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tabular<T = f64> {
+    pub features: Vec<Vec<T>>,
+    pub targets: Vec<Vec<T>>,
+    pub feature_names: Vec<String>, //one per feature value
+    pub target_names: Vec<String>,
+    pub categories: BTreeMap<String, Vec<String>>, //column > categories in encoding order
+    pub dropped: usize, //rows dropped for missing values
+}
+
+impl<T: Float> Tabular<T> {
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    //for dense_train
+    pub fn samples(&self) -> Vec<[Vec<T>; 2]> {
+        self.features.iter().cloned().zip(self.targets.iter().cloned()).map(|(features, targets)| [features, targets]).collect()
+    }
+}
+
+impl<T: Float> Dataset<T> for Tabular<T> {
+    fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError> {
+        if index >= self.features.len() {
+            return Err(out_of_range(index, self.features.len()));
+        }
+        Ok(Sample { input: Tensor::Flat(self.features[index].clone()), target: self.targets[index].clone() })
+    }
+}
+
+//shorthand for CsvLoader::new(path).target(target).load(), every other column is a numeric feature
+pub fn load<T: Float>(path: impl AsRef<Path>, target: impl Into<Column>) -> Result<Tabular<T>, SproutError> {
+    CsvLoader::new(path.as_ref()).target(target).load()
+}
@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+use image::{imageops::FilterType, DynamicImage, ImageError};
+
+use crate::{
+    dataset::{out_of_range, Dataset, Sample},
+    error::SproutError,
+    float::Float,
+    network::Image,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+//root/<class>/<image> layout, classes are the subdirectories in sorted order and targets are one-hot.
+//images are decoded when a sample is asked for
+
+pub const EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "bmp"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    Gray, //1 channel
+    Rgb, //3 channels, alpha is dropped
+}
+
+impl ColorMode {
+    pub fn channels(&self) -> usize {
+        match self {
+            ColorMode::Gray => 1,
+            ColorMode::Rgb => 3,
+        }
+    }
+}
+
+//decodes an image into channel > rows > cols with values from 0 to 1, resized to [rows, cols] when given
+pub fn load_image<T: Float>(path: impl AsRef<Path>, color: ColorMode, size: Option<[usize; 2]>) -> Result<Image<T>, SproutError> {
+    let path = path.as_ref();
+    let img = image::open(path).map_err(|err| image_error(err).context(&path.display().to_string()))?;
+    Ok(to_channels(&img, color, size, FilterType::Triangle))
+}
+
+fn image_error(err: ImageError) -> SproutError {
+    match err {
+        ImageError::IoError(err) => SproutError::Io(err),
+        err => SproutError::Format(err.to_string()),
+    }
+}
+
+pub fn to_channels<T: Float>(img: &DynamicImage, color: ColorMode, size: Option<[usize; 2]>, filter: FilterType) -> Image<T> {
+    let resized;
+    let img = match size {
+        Some([rows, cols]) if (img.height() as usize, img.width() as usize) != (rows, cols) =>
+            {
+                resized = img.resize_exact(cols as u32, rows as u32, filter);
+                &resized
+            },
+        _ => img,
+    };
+    let (rows, cols) = (img.height() as usize, img.width() as usize);
+    let (pixels, channels) = match color {
+        ColorMode::Gray => (img.to_luma8().into_raw(), 1),
+        ColorMode::Rgb => (img.to_rgb8().into_raw(), 3),
+    };
+    //pixels are interleaved row-major, Sprout wants each channel as its own plane
+    (0..channels).map(|c| {
+        (0..rows).map(|y| {
+            (0..cols).map(|x| T::from_f64(pixels[(y * cols + x) * channels + c] as f64 / 255.0)).collect()
+        }).collect()
+    }).collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageFolder<T = f64> {
+    pub root: PathBuf,
+    pub classes: Vec<String>, //class index > directory name
+    pub samples: Vec<(PathBuf, usize)>, //image, class index
+    pub color: ColorMode,
+    pub size: Option<[usize; 2]>, //rows, cols every image is resized to, None keeps them as they are
+    pub filter: FilterType,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Float> ImageFolder<T> {
+    //grayscale at the images' own size, see color and resize
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, SproutError> {
+        let root = root.into();
+        let mut classes = vec![];
+        for entry in fs::read_dir(&root).map_err(|err| SproutError::from(err).context(&root.display().to_string()))? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("").to_string();
+            if path.is_dir() && !name.starts_with('.') {
+                classes.push(name);
+            }
+        }
+        classes.sort();
+        if classes.is_empty() {
+            return Err(SproutError::InvalidConfig(format!("{} has no class directories", root.display())));
+        }
+
+        let mut samples = vec![];
+        for (class, name) in classes.iter().enumerate() {
+            let mut images = vec![];
+            for entry in fs::read_dir(root.join(name))? {
+                let path = entry?.path();
+                let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+                if path.is_file() && EXTENSIONS.contains(&extension.as_str()) {
+                    images.push(path);
+                }
+            }
+            images.sort();
+            samples.extend(images.into_iter().map(|path| (path, class)));
+        }
+        if samples.is_empty() {
+            return Err(SproutError::InvalidConfig(format!("{} has no {} images", root.display(), EXTENSIONS.join("/"))));
+        }
+        Ok(ImageFolder { root, classes, samples, color: ColorMode::Gray, size: None, filter: FilterType::Triangle, marker: std::marker::PhantomData })
+    }
+
+    pub fn color(mut self, color: ColorMode) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn resize(mut self, rows: usize, cols: usize) -> Self {
+        self.size = Some([rows, cols]);
+        self
+    }
+
+    pub fn filter(mut self, filter: FilterType) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn class_index(&self, class: &str) -> Option<usize> {
+        self.classes.iter().position(|name| name == class)
+    }
+
+    pub fn class_to_index(&self) -> BTreeMap<String, usize> {
+        self.classes.iter().enumerate().map(|(i, name)| (name.clone(), i)).collect()
+    }
+
+    //the input shape for Network::build, known up front only when the images are resized
+    pub fn shape(&self) -> Option<Shape> {
+        self.size.map(|[rows, cols]| Shape::Image([self.color.channels(), rows, cols]))
+    }
+
+    pub fn image(&self, index: usize) -> Result<Image<T>, SproutError> {
+        let (path, _) = self.samples.get(index).ok_or_else(|| out_of_range(index, self.samples.len()))?;
+        let img = image::open(path).map_err(|err| image_error(err).context(&path.display().to_string()))?;
+        Ok(to_channels(&img, self.color, self.size, self.filter))
+    }
+
+    pub fn one_hot(&self, class: usize) -> Vec<T> {
+        let mut target = vec![T::zero(); self.classes.len()];
+        target[class] = T::one();
+        target
+    }
+}
+
+impl<T: Float> Dataset<T> for ImageFolder<T> {
+    fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError> {
+        let input = self.image(index)?;
+        Ok(Sample { input: Tensor::Image(input), target: self.one_hot(self.samples[index].1) })
+    }
+}
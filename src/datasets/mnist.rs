@@ -0,0 +1,195 @@
+use std::{fs, io::Read, path::{Path, PathBuf}};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::{
+    dataset::{out_of_range, Dataset, Sample},
+    error::SproutError,
+    float::Float,
+    network::{ConvSample, Image},
+    tensor::Tensor,
+};
+
+//MNIST and Fashion-MNIST, both ship as a pair of IDX files (images and labels) per split:
+//http://yann.lecun.com/exdb/mnist/ describes the format, gzipped files are read as they are
+
+pub const CLASSES: usize = 10;
+
+pub const FASHION_CLASSES: [&str; CLASSES] = [
+    "T-shirt/top", "Trouser", "Pullover", "Dress", "Coat", "Sandal", "Shirt", "Sneaker", "Bag", "Ankle boot",
+];
+
+const UNSIGNED_BYTE: u8 = 0x08;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    Train,
+    Test,
+}
+
+impl Split {
+    //file names as distributed, without the .gz
+    pub fn file_names(&self) -> [&'static str; 2] {
+        match self {
+            Split::Train => ["train-images-idx3-ubyte", "train-labels-idx1-ubyte"],
+            Split::Test => ["t10k-images-idx3-ubyte", "t10k-labels-idx1-ubyte"],
+        }
+    }
+}
+
+//how a 0-255 pixel becomes a network input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Normalization {
+    Raw, //0 to 255
+    Unit, //0 to 1
+    Symmetric, //-1 to 1
+    Standard { mean: f64, std_dev: f64 }, //(unit - mean) / std_dev
+}
+
+impl Normalization {
+    //mean and standard deviation of the MNIST training images on the unit scale
+    pub const MNIST: Normalization = Normalization::Standard { mean: 0.1307, std_dev: 0.3081 };
+
+    pub fn apply(&self, pixel: u8) -> f64 {
+        let unit = pixel as f64 / 255.0;
+        match self {
+            Normalization::Raw => pixel as f64,
+            Normalization::Unit => unit,
+            Normalization::Symmetric => unit * 2.0 - 1.0,
+            Normalization::Standard { mean, std_dev } => (unit - mean) / std_dev,
+        }
+    }
+}
+
+//an unsigned byte IDX array
+#[derive(Debug, Clone, PartialEq)]
+pub struct Idx {
+    pub dims: Vec<usize>,
+    pub data: Vec<u8>, //row-major
+}
+
+//reads an IDX file, gunzipping it first when it starts with the gzip magic
+pub fn read_idx(path: impl AsRef<Path>) -> Result<Idx, SproutError> {
+    let path = path.as_ref();
+    let mut bytes = fs::read(path).map_err(|err| SproutError::from(err).context(&path.display().to_string()))?;
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut decoded = vec![];
+        MultiGzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)
+            .map_err(|err| SproutError::Format(format!("{} is not valid gzip: {}", path.display(), err)))?;
+        bytes = decoded;
+    }
+    parse_idx(&bytes).map_err(|err| err.context(&path.display().to_string()))
+}
+
+pub fn parse_idx(bytes: &[u8]) -> Result<Idx, SproutError> {
+    let [0, 0, data_type, ndims] = *bytes.get(..4).ok_or_else(|| SproutError::Format("IDX file is shorter than its header".to_string()))? else {
+        return Err(SproutError::Format("not an IDX file, the magic number has to start with two zero bytes".to_string()));
+    };
+    if data_type != UNSIGNED_BYTE {
+        return Err(SproutError::Format(format!("IDX data type 0x{:02x} is not supported, only unsigned bytes (0x08) are", data_type)));
+    }
+    let header = 4 + 4 * ndims as usize;
+    let dims: Vec<usize> = bytes.get(4..header)
+        .ok_or_else(|| SproutError::Format("IDX file ends inside its dimensions".to_string()))?
+        .chunks_exact(4)
+        .map(|dim| u32::from_be_bytes(dim.try_into().unwrap()) as usize)
+        .collect();
+    let len = dims.iter().try_fold(1usize, |len, dim| len.checked_mul(*dim))
+        .ok_or_else(|| SproutError::Format(format!("IDX dimensions {:?} overflow", dims)))?;
+    if bytes.len() - header != len {
+        return Err(SproutError::Format(format!("IDX dimensions {:?} need {} bytes but the file has {}", dims, len, bytes.len() - header)));
+    }
+    Ok(Idx { dims, data: bytes[header..].to_vec() })
+}
+
+//pixels are kept as bytes and normalized per sample, 60000 images take 47MB rather than 376MB as f64
+#[derive(Debug, Clone)]
+pub struct Mnist<T = f64> {
+    pub pixels: Vec<u8>, //image > rows > cols
+    pub labels: Vec<u8>,
+    pub rows: usize,
+    pub cols: usize,
+    pub normalization: Normalization,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Float> Mnist<T> {
+    pub fn load(images: impl AsRef<Path>, labels: impl AsRef<Path>, normalization: Normalization) -> Result<Self, SproutError> {
+        let images = read_idx(images)?;
+        let labels = read_idx(labels)?;
+        let [count, rows, cols] = images.dims[..] else {
+            return Err(SproutError::Format(format!("image file has dimensions {:?}, expected [images, rows, cols]", images.dims)));
+        };
+        let [label_count] = labels.dims[..] else {
+            return Err(SproutError::Format(format!("label file has dimensions {:?}, expected [labels]", labels.dims)));
+        };
+        if count != label_count {
+            return Err(SproutError::ShapeMismatch(format!("{} images but {} labels", count, label_count)));
+        }
+        if let Some(i) = labels.data.iter().position(|label| *label as usize >= CLASSES) {
+            return Err(SproutError::Format(format!("label {} of sample {} is not one of the {} classes", labels.data[i], i, CLASSES)));
+        }
+        Ok(Mnist { pixels: images.data, labels: labels.data, rows, cols, normalization, marker: std::marker::PhantomData })
+    }
+
+    //dir holds the split's two files under their distributed names, gzipped or not
+    pub fn load_dir(dir: impl AsRef<Path>, split: Split, normalization: Normalization) -> Result<Self, SproutError> {
+        let [images, labels] = split.file_names().map(|name| find(dir.as_ref(), name));
+        Self::load(images?, labels?, normalization)
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    //a single channel image
+    pub fn image(&self, index: usize) -> Image<T> {
+        let area = self.rows * self.cols;
+        let pixels = &self.pixels[index * area..(index + 1) * area];
+        vec![pixels.chunks(self.cols)
+            .map(|row| row.iter().map(|pixel| T::from_f64(self.normalization.apply(*pixel))).collect())
+            .collect()]
+    }
+
+    pub fn one_hot(&self, index: usize) -> Vec<T> {
+        let mut target = vec![T::zero(); CLASSES];
+        target[self.labels[index] as usize] = T::one();
+        target
+    }
+
+    //for conv_train
+    pub fn conv_samples(&self) -> Vec<ConvSample<T>> {
+        (0..self.len()).map(|i| (self.image(i), self.one_hot(i))).collect()
+    }
+
+    //for dense_train, the image flattened row by row
+    pub fn dense_samples(&self) -> Vec<[Vec<T>; 2]> {
+        (0..self.len()).map(|i| [self.image(i).concat().concat(), self.one_hot(i)]).collect()
+    }
+}
+
+fn find(dir: &Path, name: &str) -> Result<PathBuf, SproutError> {
+    [name.to_string(), format!("{}.gz", name)].into_iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| SproutError::InvalidConfig(format!("{} has no {} or {}.gz", dir.display(), name, name)))
+}
+
+//images come out as 1 x rows x cols, dense layers flatten them so the same dataset trains either kind of network
+impl<T: Float> Dataset<T> for Mnist<T> {
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    fn get(&self, index: usize) -> Result<Sample<T>, SproutError> {
+        if index >= self.len() {
+            return Err(out_of_range(index, self.len()));
+        }
+        Ok(Sample { input: Tensor::Image(self.image(index)), target: self.one_hot(index) })
+    }
+}
@@ -0,0 +1,271 @@
+use rand::RngCore;
+use rayon::prelude::*;
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{
+    activation::{Activation, ActivationFunction},
+    dense_params::{DenseGradients, DenseParams},
+    error::SproutError,
+    float::Float,
+    initializer::Initializer,
+    layer::{par_chunk_size, Layer, LayerCache},
+    matmul::{matmul, transpose},
+    regularizer::{Regularizer, Units},
+    shape::Shape,
+    tensor::Tensor,
+};
+
+//fully connected layer, image inputs are flattened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DenseLayer<T = f64> {
+    pub activation: Activation,
+    pub params: DenseParams<T>,
+    #[serde(default = "default_weight_init")]
+    pub weight_init: Initializer,
+    #[serde(default = "default_bias_init")]
+    pub bias_init: Initializer,
+    #[serde(default)]
+    pub regularizer: Regularizer,
+}
+
+//what layers saved before initializers were configurable read as
+pub(crate) fn default_weight_init() -> Initializer {
+    Initializer::XavierUniform
+}
+
+pub(crate) fn default_bias_init() -> Initializer {
+    Initializer::Zeros
+}
+
+//what backward needs from a forward batch
+pub struct DenseCache<T = f64> {
+    pub inputs: Vec<Vec<T>>, //sample > nodes in
+    pub outputs: Vec<Vec<T>>, //sample > nodes out
+    pub input_shape: Shape, //deltas are handed back in this shape, e.g. unflattened for a conv layer before
+}
+
+impl<T: Float> DenseLayer<T> {
+    //weights are drawn when the network is built, from the network's rng
+    pub fn new(nodes: [usize; 2], activation_fn: ActivationFunction) -> Self {
+        DenseLayer {
+            weight_init: Initializer::for_activation(&activation_fn),
+            bias_init: default_bias_init(),
+            regularizer: Regularizer::default(),
+            activation: Activation::new(activation_fn),
+            params: DenseParams { nodes_in: nodes[0], nodes_out: nodes[1], weights: vec![], biases: vec![] },
+        }
+    }
+
+    pub fn weight_init(mut self, init: Initializer) -> Self {
+        self.weight_init = init;
+        self
+    }
+
+    pub fn bias_init(mut self, init: Initializer) -> Self {
+        self.bias_init = init;
+        self
+    }
+
+    pub fn regularize(mut self, regularizer: Regularizer) -> Self {
+        self.regularizer = regularizer;
+        self
+    }
+
+    pub fn predict_batch(&self, inputs: &[Vec<T>]) -> Vec<Vec<T>> {
+        inputs.par_chunks(par_chunk_size(inputs.len()))
+            .flat_map_iter(|chunk| self.dense_chunk(chunk))
+            .collect()
+    }
+
+    fn dense_chunk(&self, inputs: &[Vec<T>]) -> Vec<Vec<T>> {
+        let params = &self.params;
+        let rows = inputs.len();
+        let x: Vec<T> = inputs.iter().flatten().copied().collect();
+
+        //Z (rows x out) = X (rows x in) * W (in x out) + b
+        let mut weighted_inputs = params.biases.repeat(rows);
+        matmul(&x, &params.weights, &mut weighted_inputs, rows, params.nodes_in, params.nodes_out);
+
+        weighted_inputs.chunks(params.nodes_out)
+            .map(|row| self.activation.function(row.to_vec()))
+            .collect()
+    }
+
+    //summed gradients of a run of consecutive samples plus each sample's delta for the previous layer
+    fn dense_chunk_gradients(&self, errors: &[Vec<T>], inputs: &[Vec<T>], outputs: &[Vec<T>]) -> (DenseGradients<T>, Vec<Vec<T>>) {
+        let params = &self.params;
+        let rows = errors.len();
+        let mut delta_output: Vec<T> = errors.iter().flatten().copied().collect();
+
+        if self.activation.function != ActivationFunction::SoftMax {
+            for (delta_row, output) in delta_output.chunks_mut(params.nodes_out).zip(outputs) {
+                let activation_gradients = self.activation.derivative(output.clone());
+                for (delta, gradient) in delta_row.iter_mut().zip(activation_gradients) {
+                    *delta *= gradient;
+                }
+            }
+        }
+
+        //dW (in x out) = X^T (in x rows) * delta (rows x out)
+        let x: Vec<T> = inputs.iter().flatten().copied().collect();
+        let mut weights = vec![T::zero(); params.nodes_in * params.nodes_out];
+        matmul(&transpose(&x, rows, params.nodes_in), &delta_output, &mut weights, params.nodes_in, rows, params.nodes_out);
+
+        let mut biases = vec![T::zero(); params.nodes_out];
+        for delta_row in delta_output.chunks(params.nodes_out) {
+            for (bias, delta) in biases.iter_mut().zip(delta_row) {
+                *bias += *delta;
+            }
+        }
+
+        //next (rows x in) = delta (rows x out) * W^T (out x in)
+        let mut next_delta = vec![T::zero(); rows * params.nodes_in];
+        matmul(&delta_output, &transpose(&params.weights, params.nodes_in, params.nodes_out), &mut next_delta, rows, params.nodes_out, params.nodes_in);
+
+        (DenseGradients { weights, biases }, next_delta.chunks(params.nodes_in).map(|row| row.to_vec()).collect())
+    }
+
+    pub fn get_weights(&self) -> Vec<Vec<T>> {
+        self.params.weights.chunks(self.params.nodes_out).map(|row| row.to_vec()).collect()
+    }
+
+    pub fn get_biases(&self) -> Vec<T> {
+        self.params.biases.clone()
+    }
+
+    pub fn set_params(&mut self, weights: Vec<Vec<T>>, biases: Vec<T>) {
+        self.try_set_params(weights, biases).unwrap()
+    }
+
+    pub fn try_set_params(&mut self, weights: Vec<Vec<T>>, biases: Vec<T>) -> Result<(), SproutError> {
+        let params = &self.params;
+        if weights.len() != params.nodes_in || weights.iter().any(|row| row.len() != params.nodes_out) || biases.len() != params.nodes_out {
+            return Err(SproutError::ShapeMismatch(format!(
+                "dense layer takes {} x {} weights and {} biases", params.nodes_in, params.nodes_out, params.nodes_out
+            )));
+        }
+        self.params.weights = weights.into_iter().flatten().collect();
+        self.params.biases = biases;
+        Ok(())
+    }
+}
+
+impl<T: Float> Layer<T> for DenseLayer<T> {
+    fn type_name(&self) -> &'static str {
+        "dense"
+    }
+
+    fn output_shape(&self, input: Shape) -> Result<Shape, String> {
+        if input.size() != self.params.nodes_in {
+            return Err(format!("dense layer expects {} inputs but receives {}", self.params.nodes_in, input));
+        }
+        if self.params.weights.is_empty() {
+            return Err("dense layer has no weights yet, build the network first".to_string());
+        }
+        Ok(Shape::Flat(self.params.nodes_out))
+    }
+
+    //creates the weights, image inputs get flattened so the input size is taken from them
+    fn build(&mut self, input: Shape, rng: &mut dyn RngCore) -> Result<Shape, String> {
+        if let Shape::Image(_) = input {
+            if self.params.nodes_in != input.size() {
+                self.params.nodes_in = input.size();
+                self.params.weights = vec![];
+            }
+        }
+        if self.params.weights.is_empty() {
+            self.params.init(&self.weight_init, &self.bias_init, rng).map_err(|err| err.to_string())?;
+        }
+        self.output_shape(input)
+    }
+
+    fn forward(&self, inputs: Vec<Tensor<T>>) -> (Vec<Tensor<T>>, LayerCache) {
+        let input_shape = inputs.first().map_or(Shape::Flat(self.params.nodes_in), |input| input.shape());
+        let inputs: Vec<Vec<T>> = inputs.into_iter().map(Tensor::into_flat).collect();
+        let outputs = self.predict_batch(&inputs);
+        let tensors = outputs.iter().cloned().map(Tensor::Flat).collect();
+        (tensors, Box::new(DenseCache { inputs, outputs, input_shape }))
+    }
+
+    fn predict(&self, input: &Tensor<T>) -> Tensor<T> {
+        Tensor::Flat(self.dense_chunk(&[input.to_flat()]).remove(0))
+    }
+
+    fn backward(&self, cache: &LayerCache, errors: Vec<Tensor<T>>) -> (Vec<Tensor<T>>, Vec<Vec<T>>) {
+        let cache: &DenseCache<T> = cache.downcast_ref().expect("dense layer got another layer's cache");
+        let errors: Vec<Vec<T>> = errors.into_iter().map(Tensor::into_flat).collect();
+        let chunk_size = par_chunk_size(errors.len());
+        let params = &self.params;
+        let (chunk_gradients, next_deltas): (Vec<DenseGradients<T>>, Vec<Vec<Vec<T>>>) = errors.par_chunks(chunk_size)
+            .zip(cache.inputs.par_chunks(chunk_size))
+            .zip(cache.outputs.par_chunks(chunk_size))
+            .map(|((errors, inputs), outputs)| self.dense_chunk_gradients(errors, inputs, outputs))
+            .unzip();
+
+        //summed in chunk order so training is reproducible, a parallel reduce regroups the float additions
+        let mut gradients = DenseGradients::zeros(params.nodes_in, params.nodes_out);
+        for chunk in &chunk_gradients {
+            gradients.accumulate(chunk);
+        }
+
+        let next_deltas = next_deltas.into_iter().flatten().map(|delta| Tensor::from_flat(delta, cache.input_shape)).collect();
+        (next_deltas, vec![gradients.weights, gradients.biases])
+    }
+
+    fn parameters(&self) -> Vec<&[T]> {
+        vec![&self.params.weights, &self.params.biases]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut [T]> {
+        vec![&mut self.params.weights, &mut self.params.biases]
+    }
+
+    fn parameter_names(&self) -> Vec<String> {
+        vec!["weights".to_string(), "biases".to_string()]
+    }
+
+    fn parameter_pointers(&self) -> Vec<String> {
+        vec!["/params/weights".to_string(), "/params/biases".to_string()]
+    }
+
+    fn parameter_shapes(&self) -> Vec<Vec<usize>> {
+        vec![vec![self.params.nodes_in, self.params.nodes_out], vec![self.params.nodes_out]]
+    }
+
+    fn to_json(&self) -> Result<Value, SproutError> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn validate(&self) -> Result<(), SproutError> {
+        self.regularizer.validate()?;
+        let params = &self.params;
+        //an unbuilt layer has no parameters yet
+        if params.weights.is_empty() && params.biases.is_empty() {
+            return Ok(());
+        }
+        if params.weights.len() != params.nodes_in * params.nodes_out || params.biases.len() != params.nodes_out {
+            return Err(SproutError::ShapeMismatch(format!(
+                "dense layer is {} x {} but has {} weights and {} biases",
+                params.nodes_in, params.nodes_out, params.weights.len(), params.biases.len()
+            )));
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self, rng: &mut dyn RngCore) -> Result<(), SproutError> {
+        //checked up front so a failed reset leaves the trained parameters alone
+        self.weight_init.validate()?;
+        self.bias_init.validate()?;
+        self.params.init(&self.weight_init, &self.bias_init, rng)
+    }
+
+    fn regularizer(&self) -> Option<&Regularizer> {
+        Some(&self.regularizer)
+    }
+
+    //a unit's incoming weights are a column of the in x out weights
+    fn weight_units(&self) -> Units {
+        Units::Columns(self.params.nodes_out)
+    }
+}
@@ -1,81 +1,61 @@
-use rand::{thread_rng, Rng};
+use rand::RngCore;
 use serde_derive::{Serialize, Deserialize};
 
-use crate::activation::ActivationFunction;
+use crate::{error::SproutError, float::Float, initializer::{Fans, Initializer}};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DenseParams {
+pub struct DenseParams<T = f64> {
     pub nodes_in: usize,
     pub nodes_out: usize,
-    pub outputs: Vec<f64>, //nodes out
-    pub inputs: Vec<f64>,
-    pub weights: Vec<Vec<f64>>,
-    pub biases: Vec<f64>,
+    pub weights: Vec<T>, //in (rows) x out (cols), row-major
+    pub biases: Vec<T>,
 }
 
-impl DenseParams {
+#[derive(Debug, Clone)]
+pub struct DenseGradients<T = f64> {
+    pub weights: Vec<T>, //in (rows) x out (cols), row-major
+    pub biases: Vec<T>,
+}
+
+impl<T: Float> DenseParams<T> {
     pub fn new(
         nodes_in: usize,
         nodes_out: usize,
     ) -> Self {
-        let weights = vec![vec![0.0; nodes_out]; nodes_in];
-        let biases = vec![0.0; nodes_out];
-        let params = DenseParams {
+        let weights = vec![T::zero(); nodes_in * nodes_out];
+        let biases = vec![T::zero(); nodes_out];
+        DenseParams {
             nodes_in,
             nodes_out,
-            outputs: vec![],
-            inputs: vec![],
             weights,
             biases,
-        };
-        params
+        }
+    }
+
+    //sizes the weights for nodes_in x nodes_out and draws them from rng
+    pub fn init(&mut self, weight_init: &Initializer, bias_init: &Initializer, rng: &mut dyn RngCore) -> Result<(), SproutError> {
+        self.weights = vec![T::zero(); self.nodes_in * self.nodes_out];
+        self.biases = vec![T::zero(); self.nodes_out];
+        let fans = Fans { fan_in: self.nodes_in, fan_out: self.nodes_out, rows: self.nodes_in, cols: self.nodes_out };
+        weight_init.fill(&mut self.weights, fans, rng)?;
+        bias_init.fill(&mut self.biases, Fans { rows: 1, ..fans }, rng)
+    }
+}
+
+impl<T: Float> DenseGradients<T> {
+    pub fn zeros(nodes_in: usize, nodes_out: usize) -> Self {
+        DenseGradients {
+            weights: vec![T::zero(); nodes_in * nodes_out],
+            biases: vec![T::zero(); nodes_out],
+        }
     }
-    pub fn init(&mut self, activation: ActivationFunction) {
-        match activation {
-            ActivationFunction::Sigmoid => 
-                {
-                    let std_dev = (1.0 / ((self.nodes_in + self.nodes_out) as f64 / 2.0)).sqrt();
-                    let limit = (3.0 * std_dev).sqrt();
-                    for i in 0..self.weights.len() {
-                        for j in 0..self.weights[i].len() {
-                            self.weights[i][j] = thread_rng().gen_range(-limit..limit) * std_dev; //in (rows) - out (cols)
-                        }
-                    }
-                    self.biases = vec![0.0; self.biases.len()];
-                },
-            ActivationFunction::ReLU => 
-                {
-                    let std_dev = (2.0 / self.nodes_in as f64).sqrt();
-                    let limit = (3.0 * std_dev).sqrt();
-                    for i in 0..self.weights.len() {
-                        for j in 0..self.weights[i].len() {
-                            self.weights[i][j] = thread_rng().gen_range(-limit..limit) * std_dev; //in (rows) - out (cols)
-                        }
-                    }
-                    self.biases = vec![0.0; self.biases.len()];
-                },
-            ActivationFunction::TanH => 
-                {
-                    let std_dev = (1.0 / ((self.nodes_in + self.nodes_out) as f64 / 2.0)).sqrt();
-                    let limit = (3.0 * std_dev).sqrt();
-                    for i in 0..self.weights.len() {
-                        for j in 0..self.weights[i].len() {
-                            self.weights[i][j] = thread_rng().gen_range(-limit..limit) * std_dev; //in (rows) - out (cols)
-                        }
-                    }
-                    self.biases = vec![0.0; self.biases.len()];
-                },
-            ActivationFunction::SoftMax => 
-                {
-                    let std_dev = (1.0 / ((self.nodes_in + self.nodes_out) as f64 / 2.0)).sqrt();
-                    let limit = (3.0 * std_dev).sqrt();
-                    for i in 0..self.weights.len() {
-                        for j in 0..self.weights[i].len() {
-                            self.weights[i][j] = thread_rng().gen_range(-limit..limit) * std_dev; //in (rows) - out (cols)
-                        }
-                    }
-                    self.biases = vec![0.0; self.biases.len()];
-                },
+
+    pub fn accumulate(&mut self, other: &DenseGradients<T>) {
+        for (grad, other_grad) in self.weights.iter_mut().zip(&other.weights) {
+            *grad += *other_grad;
+        }
+        for (grad, other_grad) in self.biases.iter_mut().zip(&other.biases) {
+            *grad += *other_grad;
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,60 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum SproutError {
+    Io(io::Error),
+    Serialization(serde_json::Error),
+    ShapeMismatch(String),
+    InvalidConfig(String),
+    Numerical(String),
+    Format(String), //unreadable or unsupported model or data file
+}
+
+impl SproutError {
+    //prefixes the message with where the error happened, e.g. the layer or sample index
+    pub fn context(self, context: &str) -> Self {
+        match self {
+            SproutError::ShapeMismatch(message) => SproutError::ShapeMismatch(format!("{}: {}", context, message)),
+            SproutError::InvalidConfig(message) => SproutError::InvalidConfig(format!("{}: {}", context, message)),
+            SproutError::Numerical(message) => SproutError::Numerical(format!("{}: {}", context, message)),
+            SproutError::Format(message) => SproutError::Format(format!("{}: {}", context, message)),
+            SproutError::Io(err) => SproutError::Io(io::Error::new(err.kind(), format!("{}: {}", context, err))),
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for SproutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SproutError::Io(err) => write!(f, "I/O error: {}", err),
+            SproutError::Serialization(err) => write!(f, "serialization error: {}", err),
+            SproutError::ShapeMismatch(message) => write!(f, "shape mismatch: {}", message),
+            SproutError::InvalidConfig(message) => write!(f, "invalid configuration: {}", message),
+            SproutError::Numerical(message) => write!(f, "numerical failure: {}", message),
+            SproutError::Format(message) => write!(f, "model format error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SproutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SproutError::Io(err) => Some(err),
+            SproutError::Serialization(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SproutError {
+    fn from(err: io::Error) -> Self {
+        SproutError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SproutError {
+    fn from(err: serde_json::Error) -> Self {
+        SproutError::Serialization(err)
+    }
+}
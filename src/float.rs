@@ -0,0 +1,51 @@
+use std::{fmt::Debug, iter::Sum, ops::{AddAssign, DivAssign, MulAssign, SubAssign}};
+
+use serde::de::DeserializeOwned;
+use serde_derive::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub enum Precision {
+    F32,
+    #[default]
+    F64
+}
+
+//Scalar type a Network stores its parameters and activations in
+pub trait Float: num_traits::Float
+    + AddAssign + SubAssign + MulAssign + DivAssign + Sum
+    + Default + Debug + Send + Sync + serde::Serialize + DeserializeOwned + 'static
+{
+    const PRECISION: Precision;
+
+    fn from_f64(value: f64) -> Self;
+
+    fn as_f64(self) -> f64;
+
+    fn cast<U: Float>(self) -> U {
+        U::from_f64(self.as_f64())
+    }
+}
+
+impl Float for f32 {
+    const PRECISION: Precision = Precision::F32;
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Float for f64 {
+    const PRECISION: Precision = Precision::F64;
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn as_f64(self) -> f64 {
+        self
+    }
+}
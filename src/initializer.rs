@@ -0,0 +1,136 @@
+use std::{collections::HashMap, f64::consts::PI, sync::{Arc, OnceLock, RwLock}};
+
+use rand::{Rng, RngCore};
+use serde_derive::{Serialize, Deserialize};
+
+use crate::{activation::ActivationFunction, error::SproutError, float::Float};
+
+//how a layer's weights or biases are drawn when it is built or reset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Initializer {
+    XavierUniform, //Glorot, U(-l, l) with l = sqrt(6 / (fan_in + fan_out))
+    XavierNormal, //Glorot, N(0, 2 / (fan_in + fan_out))
+    HeUniform, //Kaiming, U(-l, l) with l = sqrt(6 / fan_in)
+    HeNormal, //Kaiming, N(0, 2 / fan_in)
+    LeCunUniform, //U(-l, l) with l = sqrt(3 / fan_in)
+    LeCunNormal, //N(0, 1 / fan_in)
+    Orthogonal { gain: f64 }, //rows (or columns, whichever are fewer) are orthonormal, then scaled by gain
+    Constant(f64),
+    Zeros,
+    Custom(String), //name given to register_initializer
+}
+
+//what an initializer gets to scale by, rows x cols is the parameter laid out as a matrix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fans {
+    pub fan_in: usize,
+    pub fan_out: usize,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+//fills values (rows x cols, row-major) for a custom initializer
+pub type InitFn = dyn Fn(&mut [f64], Fans, &mut dyn RngCore) + Send + Sync;
+
+type Registry = RwLock<HashMap<String, Arc<InitFn>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+//makes Initializer::Custom(name) usable, registering a name again replaces it
+pub fn register_initializer(name: &str, init: impl Fn(&mut [f64], Fans, &mut dyn RngCore) + Send + Sync + 'static) {
+    registry().write().unwrap().insert(name.to_string(), Arc::new(init));
+}
+
+impl Initializer {
+    //what layers use unless told otherwise, He for ReLU and Xavier for the rest
+    pub fn for_activation(activation: &ActivationFunction) -> Self {
+        match activation {
+            ActivationFunction::ReLU => Initializer::HeUniform,
+            _ => Initializer::XavierUniform,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), SproutError> {
+        match self {
+            Initializer::Orthogonal { gain } if !gain.is_finite() => Err(SproutError::InvalidConfig(format!("orthogonal gain must be finite, got {}", gain))),
+            Initializer::Constant(value) if !value.is_finite() => Err(SproutError::InvalidConfig(format!("constant initializer must be finite, got {}", value))),
+            Initializer::Custom(name) if !registry().read().unwrap().contains_key(name) =>
+                Err(SproutError::InvalidConfig(format!("unknown initializer '{}', register it with register_initializer", name))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn fill<T: Float>(&self, params: &mut [T], fans: Fans, rng: &mut dyn RngCore) -> Result<(), SproutError> {
+        self.validate()?;
+        let (fan_in, fan_out) = (fans.fan_in.max(1) as f64, fans.fan_out.max(1) as f64);
+        let mut values = vec![0.0; params.len()];
+        match self {
+            Initializer::XavierUniform => uniform(&mut values, (6.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::XavierNormal => gaussian(&mut values, (2.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::HeUniform => uniform(&mut values, (6.0 / fan_in).sqrt(), rng),
+            Initializer::HeNormal => gaussian(&mut values, (2.0 / fan_in).sqrt(), rng),
+            Initializer::LeCunUniform => uniform(&mut values, (3.0 / fan_in).sqrt(), rng),
+            Initializer::LeCunNormal => gaussian(&mut values, (1.0 / fan_in).sqrt(), rng),
+            Initializer::Orthogonal { gain } => orthogonal(&mut values, fans.rows, fans.cols, *gain, rng),
+            Initializer::Constant(value) => values.fill(*value),
+            Initializer::Zeros => {},
+            Initializer::Custom(name) =>
+                {
+                    let init = registry().read().unwrap()[name].clone();
+                    init(&mut values, fans, rng);
+                },
+        }
+        for (param, value) in params.iter_mut().zip(values) {
+            *param = T::from_f64(value);
+        }
+        Ok(())
+    }
+}
+
+fn uniform(values: &mut [f64], limit: f64, rng: &mut dyn RngCore) {
+    for value in values.iter_mut() {
+        *value = rng.gen_range(-limit..=limit);
+    }
+}
+
+fn gaussian(values: &mut [f64], std_dev: f64, rng: &mut dyn RngCore) {
+    for value in values.iter_mut() {
+        *value = standard_normal(rng) * std_dev;
+    }
+}
+
+//Box-Muller
+pub(crate) fn standard_normal(rng: &mut dyn RngCore) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>(); //(0, 1], keeps ln finite
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+//Gram-Schmidt over gaussian vectors, the shorter side of the matrix gets orthonormal vectors along the longer side
+fn orthogonal(values: &mut [f64], rows: usize, cols: usize, gain: f64, rng: &mut dyn RngCore) {
+    let (count, len) = (rows.min(cols), rows.max(cols));
+    let mut basis: Vec<Vec<f64>> = Vec::with_capacity(count);
+    while basis.len() < count {
+        let mut vector: Vec<f64> = (0..len).map(|_| standard_normal(rng)).collect();
+        for other in &basis {
+            let dot: f64 = vector.iter().zip(other).map(|(a, b)| a * b).sum();
+            for (x, o) in vector.iter_mut().zip(other) {
+                *x -= dot * o;
+            }
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+        //a draw that is (numerically) in the span of the others is thrown away
+        if norm > 1e-6 {
+            basis.push(vector.into_iter().map(|x| x / norm).collect());
+        }
+    }
+    for r in 0..rows {
+        for c in 0..cols {
+            let value = if rows <= cols { basis[r][c] } else { basis[c][r] };
+            values[r * cols + c] = gain * value;
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{activation::{Activation, ActivationFunction}, conv_params::PaddingType, layer::Layer};
+use crate::{activation::ActivationFunction, conv_params::PaddingType, error::SproutError, float::Float, layer::Layer, sequential::Sequential, shape::Shape};
 
 pub struct LayerBuilder {
     kernels: Vec<usize>,
@@ -10,6 +10,12 @@ pub struct LayerBuilder {
     dense_layers: Vec<usize>,
 }
 
+impl Default for LayerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl LayerBuilder {
     pub fn new() -> Self {
         LayerBuilder {
@@ -51,30 +57,31 @@ impl LayerBuilder {
         self.dense_layers = dense_layers;
     }
 
-    pub fn cnn(&self) -> Vec<Layer> {
-        let width = self.img[0];
-        let width = self.img[1];
-        let mut layers = vec![
-            Layer::conv(self.kernels[0].clone(), self.paddings[0].clone(), self.strides[0].clone(), self.activations[0].clone())
-        ];
+    pub fn cnn<T: Float>(&self) -> Vec<Box<dyn Layer<T>>> {
+        self.try_cnn().unwrap()
+    }
+
+    //img is [rows, cols] of a single channel input, dense_layers holds each dense layer's output nodes
+    pub fn try_cnn<T: Float>(&self) -> Result<Vec<Box<dyn Layer<T>>>, SproutError> {
         let layer_count = self.cn_layers + self.dense_layers.len();
-        let mut switch = false;
-        for i in 1..layer_count {
-            if i == self.cn_layers {
-                switch = true;
-            }
-            if !switch {
-                layers.push(
-                    Layer::conv(self.kernels[i].clone(), self.paddings[i].clone(), self.strides[i].clone(), self.activations[i].clone())
-                );
-            } else {
-                let nodes = [self.dense_layers[i - 1], self.dense_layers[i]];
-                layers.push(
-                    Layer::dense(nodes, self.activations[i].clone())
-                );
-            }
+        if self.kernels.len() != self.cn_layers || self.paddings.len() != self.cn_layers || self.strides.len() != self.cn_layers {
+            return Err(SproutError::InvalidConfig(format!(
+                "{} conv layers but {} kernels, {} paddings and {} strides",
+                self.cn_layers, self.kernels.len(), self.paddings.len(), self.strides.len()
+            )));
+        }
+        if self.activations.len() != layer_count {
+            return Err(SproutError::InvalidConfig(format!("{} layers but {} activations", layer_count, self.activations.len())));
         }
 
-        vec![]
+        let mut model = Sequential::new(Shape::Image([1, self.img[0], self.img[1]]));
+        for i in 0..self.cn_layers {
+            model = model.conv(self.kernels[i], self.paddings[i].clone(), self.strides[i], self.activations[i].clone());
+        }
+        model = model.flatten();
+        for (i, nodes) in self.dense_layers.iter().enumerate() {
+            model = model.dense(*nodes, self.activations[self.cn_layers + i].clone());
+        }
+        Ok(model.build::<T>()?.layers)
     }
-}
\ No newline at end of file
+}
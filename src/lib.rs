@@ -4,4 +4,29 @@ pub mod activation;
 pub mod conv_params;
 pub mod dense_params;
 pub mod layer_builder;
-pub mod loss_function;
\ No newline at end of file
+pub mod loss_function;
+pub mod matmul;
+pub mod float;
+pub mod shape;
+pub mod error;
+pub mod optimizer;
+pub mod sequential;
+pub mod tensor;
+pub mod dense_layer;
+pub mod conv_layer;
+pub mod pool_layer;
+pub mod context;
+pub mod model_format;
+pub mod schema;
+pub mod onnx;
+pub mod weights;
+pub mod checkpoint;
+pub mod initializer;
+pub mod dataset;
+pub mod datasets;
+pub mod augment;
+pub mod preprocessing;
+pub mod validation;
+pub mod search;
+pub mod regularizer;
+mod protobuf;
@@ -1,5 +1,7 @@
     use serde_derive::{Deserialize, Serialize};
 
+use crate::float::Float;
+
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub enum LossType {
@@ -7,7 +9,7 @@ pub enum LossType {
     CEL
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct LossFunction {
     pub loss_type: LossType
 }
@@ -20,37 +22,46 @@ impl LossFunction {
         }
     }
 
-    pub fn function(&self, outputs: &Vec<f64>, targets: &Vec<f64>, true_index: usize) -> f64 {
+    pub fn function<T: Float>(&self, outputs: &[T], targets: &[T]) -> T {
         match self.loss_type {
             LossType::MSE => 
                 {   
-                    let mut cost = 0.0;
+                    let mut cost = T::zero();
                     for i in 0..targets.len() {
-                        cost += (outputs[i] -  targets[i]).powf(2.0);
+                        cost += (outputs[i] -  targets[i]).powi(2);
                     }
                     cost
                 },
             LossType::CEL => 
             {
-                -outputs[true_index].ln()
+                //soft targets (e.g. from mixup) weight each class, zero targets are skipped so a zero output can't make it NaN
+                let mut cost = T::zero();
+                for (output, target) in outputs.iter().zip(targets) {
+                    if *target != T::zero() {
+                        cost -= *target * output.ln();
+                    }
+                }
+                cost
             },
         }
     }
 
-    pub fn derivative(&self, outputs: &Vec<f64>, targets: &Vec<f64>, true_index: usize) -> Vec<f64> {
+    pub fn derivative<T: Float>(&self, outputs: &[T], targets: &[T]) -> Vec<T> {
         match self.loss_type {
             LossType::MSE => 
                 {   
-                    let mut loss_gradient: Vec<f64> = vec![0.0; targets.len()];
+                    let mut loss_gradient: Vec<T> = vec![T::zero(); targets.len()];
                     for l in 0..targets.len() {
-                        loss_gradient[l] += 2.0 * (outputs[l] - targets[l]);
+                        loss_gradient[l] += T::from_f64(2.0) * (outputs[l] - targets[l]);
                     }
                     loss_gradient
                 },
             LossType::CEL => 
                 {
-                    let mut gradients = outputs.clone();
-                    gradients[true_index] -= 1.0;
+                    let mut gradients = outputs.to_vec();
+                    for (gradient, target) in gradients.iter_mut().zip(targets) {
+                        *gradient -= *target;
+                    }
                     gradients
                 },
         }
@@ -1,7 +1,7 @@
 use std::time;
 use image::*;
 
-use sprout::{conv_params::PaddingType::*, activation::ActivationFunction::*, loss_function::LossType::*, conv_params::PaddingType, layer::{Layer, LayerType::*}, network::Network};
+use sprout::{conv_params::PaddingType::*, activation::ActivationFunction::*, loss_function::LossType::*, layer, network::Network, sequential::Sequential, shape::Shape, tensor::Tensor, dataset::DataLoader, datasets::{image_folder::{load_image, ColorMode}, mnist::{Mnist, Normalization, Split}}, preprocessing::MinMaxScaler};
 
 fn main() {
     let time = time::Instant::now();
@@ -15,11 +15,15 @@ fn main() {
 
 pub fn conv_model() {
 
-    let layers = vec![
-        Layer::conv(3, Valid, 1, ReLU),
-        Layer::pool(2, 2),
-        Layer::dense([4, 3], Sigmoid),
-    ];
+    let mut nn: Network = Sequential::new(Shape::Image([1, 6, 6]))
+        .conv(3, Valid, 1, ReLU)
+        .pool(2, 2)
+        .flatten()
+        .dense(3, Sigmoid)
+        .learning_rate(0.01)
+        .batch_size(3)
+        .build()
+        .unwrap();
 
     let data = vec![
         (vec![
@@ -60,37 +64,27 @@ pub fn conv_model() {
         ),
     ];
 
-    let mut nn = Network::new(layers, 0.01, 3, MSE);
     nn.conv_train(data.clone(), 10000);
-    for i in 0..data.len() {
-        let inputs = data[i].0.clone();
-        println!("Output {}: {:?}", i + 1, nn.conv_forward(inputs));
+    for (i, sample) in data.iter().enumerate() {
+        println!("Output {}: {:?}", i + 1, nn.conv_forward(sample.0.clone()));
     }
 }
 
 fn conv_check() {
-    let img = image::open("mnist_7.png").unwrap();
-
-    let mut pixels = vec![vec![0.0; img.width() as usize]; img.height() as usize];
-
-    for y in 0..pixels.len() {
-        for x in 0..pixels[y].len() {
-            let pix = img.get_pixel(x as u32, y as u32).0;
-            let int = ((pix[0] / 3) + (pix[1] / 3) + (pix[2] / 3)) as f64 / 255.0;
-            pixels[y][x] = int;
-        }
-    }
+    let pixels = load_image::<f64>("mnist_7.png", ColorMode::Gray, None).unwrap();
+    let (rows, cols) = (pixels[0].len(), pixels[0][0].len());
 
-    let data = vec![(vec![pixels], vec![0.0, 1.0, 0.0])];
+    let data = [(pixels, vec![0.0, 1.0, 0.0])];
 
     let layers = vec![
-        Layer::pool(2, 2),
-        Layer::dense([2, 2], Sigmoid)
+        layer::pool(2, 2),
+        layer::dense([2, 2], Sigmoid)
     ];
 
     let mut nn = Network::new(layers, 0.1, 1, MSE);
+    nn.build(Shape::Image([1, rows, cols])).unwrap();
 
-    let pooled = nn.layers[0].pool_forward(data[0].0.clone());
+    let pooled = nn.layers[0].predict(&Tensor::Image(data[0].0.clone())).into_image();
 
     let new_img = ImageBuffer::from_fn(pooled[0][0].len() as u32, 
         pooled[0].len() as u32, 
@@ -99,15 +93,15 @@ fn conv_check() {
             Rgb([int, int, int])
         }
     );
-    new_img.save("pooled.png");
+    new_img.save("pooled.png").unwrap();
 
 }
 
 pub fn xor_mode(epochs: usize) {
 
     let layers = vec![
-        Layer::dense([2, 3], Sigmoid),
-        Layer::dense([3, 1], Sigmoid),
+        layer::dense([2, 3], Sigmoid),
+        layer::dense([3, 1], Sigmoid),
     ];
 
     let mut nn = Network::new(layers, 0.5, 3, MSE);
@@ -123,8 +117,8 @@ pub fn xor_mode(epochs: usize) {
     nn.dense_train(data.clone(), epochs);
     // nn.load_model("test1");
 
-    for i in 0..data.len() {
-        println!("Input: {:?} // Output: {:?} // Target: {:?}",data[i][0].clone(), nn.dense_forward(data[i][0].clone()), data[i][1].clone());
+    for sample in &data {
+        println!("Input: {:?} // Output: {:?} // Target: {:?}", sample[0], nn.dense_forward(sample[0].clone()), sample[1]);
     }
 }
 
@@ -135,13 +129,15 @@ pub fn digit_model(mut nn: Network, epochs: usize) {
 
     for y in 0..img.dimensions().1 {
         for x in 0..img.dimensions().0 {
-            let intensity;
             let pixel = img.get_pixel(x, y).0;
-            intensity = (pixel[0] / 3) + (pixel[1] / 3) + (pixel[2] / 3);
-            data.push([vec![x as f64, y as f64], vec![(intensity as f64 / 255 as f64)]]);
+            let intensity = (pixel[0] / 3) + (pixel[1] / 3) + (pixel[2] / 3);
+            data.push([vec![x as f64, y as f64], vec![intensity as f64 / 255.0]]);
         }
     }
 
+    //pixel coordinates go in as they are, the network scales them to 0-1 itself
+    let inputs: Vec<Vec<f64>> = data.iter().map(|sample| sample[0].clone()).collect();
+    nn.fit_preprocessing(vec![MinMaxScaler::default().into()], &inputs).unwrap();
     nn.dense_train(data.clone(), epochs);
 
     // for y in 0..img.dimensions().1 as usize {
@@ -174,5 +170,27 @@ pub fn digit_model(mut nn: Network, epochs: usize) {
         // println!();
     }
 
-    let _  = new_image.save("Output.png").unwrap();
+    new_image.save("Output.png").unwrap();
+}
+
+//dir holds the four IDX files from the MNIST (or Fashion-MNIST) site, gzipped or not
+pub fn mnist_model(dir: &str, epochs: usize) {
+    let train: Mnist = Mnist::load_dir(dir, Split::Train, Normalization::MNIST).unwrap();
+    let test: Mnist = Mnist::load_dir(dir, Split::Test, Normalization::MNIST).unwrap();
+
+    let mut nn: Network = Sequential::new(Shape::Image([1, 28, 28]))
+        .conv(3, Same, 1, ReLU)
+        .pool(2, 2)
+        .flatten()
+        .dense(64, ReLU)
+        .dense(10, SoftMax)
+        .loss(CEL)
+        .learning_rate(0.01)
+        .build()
+        .unwrap();
+    nn.print_progress(true);
+    nn.train(&DataLoader::new(train, 64).prefetch(4), epochs);
+
+    let metrics = nn.evaluate(&test).unwrap();
+    println!("Test loss: {:.4} // Test accuracy: {:.2}%", metrics.loss, 100.0 * metrics.accuracy);
 }
@@ -0,0 +1,52 @@
+use crate::float::Float;
+
+//Block sizes chosen so a block of A, B and C fits comfortably in L1/L2
+const BLOCK_M: usize = 32;
+const BLOCK_K: usize = 128;
+const BLOCK_N: usize = 256;
+
+//c (m x n) += a (m x k) * b (k x n), all row-major
+pub fn matmul<T: Float>(a: &[T], b: &[T], c: &mut [T], m: usize, k: usize, n: usize) {
+    assert_eq!(a.len(), m * k, "lhs is not {} x {}", m, k);
+    assert_eq!(b.len(), k * n, "rhs is not {} x {}", k, n);
+    assert_eq!(c.len(), m * n, "output is not {} x {}", m, n);
+
+    for i0 in (0..m).step_by(BLOCK_M) {
+        let i1 = (i0 + BLOCK_M).min(m);
+        for p0 in (0..k).step_by(BLOCK_K) {
+            let p1 = (p0 + BLOCK_K).min(k);
+            for j0 in (0..n).step_by(BLOCK_N) {
+                let j1 = (j0 + BLOCK_N).min(n);
+                for i in i0..i1 {
+                    let a_row = &a[i * k + p0..i * k + p1];
+                    let c_row = &mut c[i * n + j0..i * n + j1];
+                    for (p, a_ip) in (p0..p1).zip(a_row) {
+                        let b_row = &b[p * n + j0..p * n + j1];
+                        //contiguous slices of equal length so the compiler can vectorize this loop
+                        for (c_ij, b_pj) in c_row.iter_mut().zip(b_row) {
+                            *c_ij += *a_ip * *b_pj;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+//returns the (cols x rows) transpose of a row-major (rows x cols) matrix
+pub fn transpose<T: Float>(a: &[T], rows: usize, cols: usize) -> Vec<T> {
+    assert_eq!(a.len(), rows * cols, "matrix is not {} x {}", rows, cols);
+    let mut t = vec![T::zero(); a.len()];
+    for i0 in (0..rows).step_by(BLOCK_M) {
+        let i1 = (i0 + BLOCK_M).min(rows);
+        for j0 in (0..cols).step_by(BLOCK_M) {
+            let j1 = (j0 + BLOCK_M).min(cols);
+            for i in i0..i1 {
+                for j in j0..j1 {
+                    t[j * rows + i] = a[i * cols + j];
+                }
+            }
+        }
+    }
+    t
+}
@@ -0,0 +1,213 @@
+use std::{fs, fs::File};
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    error::SproutError,
+    float::{Float, Precision},
+    layer::layer_to_json,
+    network::Network,
+    schema,
+};
+
+//binary model layout, all integers little-endian:
+//  magic "SPRT" | version u32 | precision u8 (0 = f32, 1 = f64) | architecture length u64 | architecture JSON
+//  | blob count u32 | per blob: value count u64, values | CRC32 of everything before it, u32
+//the architecture is the saved JSON minus training leftovers. every parameter a layer gives a pointer for
+//(see Layer::parameter_pointers) is written as a blob in the layer's parameter order and left as null in the JSON,
+//the architecture's "blobs" list says which layer and pointer each blob goes back to
+pub const MAGIC: [u8; 4] = *b"SPRT";
+pub const FORMAT_VERSION: u32 = 1;
+
+const BLOBS_KEY: &str = "blobs";
+
+//where a blob goes back to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BlobSlot {
+    layer: usize,
+    pointer: String,
+    #[serde(default)]
+    scalar: bool, //a single number rather than an array, like the conv bias
+}
+
+pub fn encode<T: Float>(network: &Network<T>) -> Result<Vec<u8>, SproutError> {
+    let mut blobs: Vec<&[T]> = vec![];
+    let mut slots = vec![];
+    let mut layers = vec![];
+    for (i, layer) in network.layers.iter().enumerate() {
+        let context = format!("layer {}", i);
+        let mut value = layer_to_json(layer.as_ref()).map_err(|err| err.context(&context))?;
+        let pointers = layer.parameter_pointers();
+        let parameters = layer.parameters();
+        if !pointers.is_empty() && pointers.len() != parameters.len() {
+            return Err(SproutError::InvalidConfig(format!(
+                "{}: {} parameter pointers for {} parameters", context, pointers.len(), parameters.len()
+            )));
+        }
+        for (pointer, params) in pointers.into_iter().zip(parameters) {
+            let slot = value.pointer_mut(&pointer)
+                .ok_or_else(|| SproutError::InvalidConfig(format!("{}: parameter pointer {} is not in the layer's JSON", context, pointer)))?;
+            let scalar = match slot {
+                Value::Number(_) if params.len() == 1 => true,
+                Value::Array(values) if values.len() == params.len() => false,
+                _ => return Err(SproutError::InvalidConfig(format!("{}: {} does not hold the parameter's {} values", context, pointer, params.len()))),
+            };
+            *slot = Value::Null;
+            slots.push(BlobSlot { layer: i, pointer, scalar });
+            blobs.push(params);
+        }
+        layers.push(value);
+    }
+
+    let mut architecture = schema::to_value(network)?;
+    let object = architecture.as_object_mut().expect("network serializes to an object");
+    object.insert("layers".to_string(), Value::Array(layers));
+    object.insert(BLOBS_KEY.to_string(), serde_json::to_value(slots)?);
+    for transient in ["cost", "print_progress", "optimizer_states"] {
+        object.remove(transient);
+    }
+    let architecture = serde_json::to_vec(&architecture)?;
+
+    let mut bytes = vec![];
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.push(match T::PRECISION {
+        Precision::F32 => 0,
+        Precision::F64 => 1,
+    });
+    bytes.extend_from_slice(&(architecture.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&architecture);
+    bytes.extend_from_slice(&(blobs.len() as u32).to_le_bytes());
+    for blob in blobs {
+        bytes.extend_from_slice(&(blob.len() as u64).to_le_bytes());
+        for value in blob {
+            match T::PRECISION {
+                Precision::F32 => bytes.extend_from_slice(&(value.as_f64() as f32).to_le_bytes()),
+                Precision::F64 => bytes.extend_from_slice(&value.as_f64().to_le_bytes()),
+            }
+        }
+    }
+    let checksum = crc32fast::hash(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    Ok(bytes)
+}
+
+//rebuilds the full JSON form of the model, returns it with the precision it was saved in
+pub fn decode(bytes: &[u8]) -> Result<(Precision, Value), SproutError> {
+    if bytes.len() < MAGIC.len() + 4 || bytes[..MAGIC.len()] != MAGIC {
+        return Err(SproutError::Format("not a Sprout binary model".to_string()));
+    }
+    let (body, checksum) = bytes.split_at(bytes.len() - 4);
+    let mut reader = Reader { bytes: body, position: MAGIC.len() };
+    let version = u32::from_le_bytes(reader.take_array()?);
+    if version > FORMAT_VERSION {
+        return Err(SproutError::Format(format!(
+            "model was saved in binary format version {} but this version of Sprout reads up to {}", version, FORMAT_VERSION
+        )));
+    }
+    if crc32fast::hash(body) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err(SproutError::Format("checksum mismatch, the file is corrupt or truncated".to_string()));
+    }
+
+    let precision = match reader.take(1)?[0] {
+        0 => Precision::F32,
+        1 => Precision::F64,
+        byte => return Err(SproutError::Format(format!("unknown precision tag {}", byte))),
+    };
+    let architecture_len = u64::from_le_bytes(reader.take_array()?) as usize;
+    let mut architecture: Value = serde_json::from_slice(reader.take(architecture_len)?)?;
+
+    let blob_count = u32::from_le_bytes(reader.take_array()?) as usize;
+    let width = match precision {
+        Precision::F32 => 4,
+        Precision::F64 => 8,
+    };
+    let mut blobs = Vec::with_capacity(blob_count);
+    for _ in 0..blob_count {
+        let len = u64::from_le_bytes(reader.take_array()?) as usize;
+        let raw = reader.take(len.checked_mul(width).ok_or_else(|| SproutError::Format("blob size overflows".to_string()))?)?;
+        let values: Vec<f64> = match precision {
+            Precision::F32 => raw.chunks_exact(4).map(|x| f32::from_le_bytes(x.try_into().unwrap()) as f64).collect(),
+            Precision::F64 => raw.chunks_exact(8).map(|x| f64::from_le_bytes(x.try_into().unwrap())).collect(),
+        };
+        blobs.push(values);
+    }
+    if reader.position != body.len() {
+        return Err(SproutError::Format(format!("{} unexpected bytes after the last blob", body.len() - reader.position)));
+    }
+
+    let object = architecture.as_object_mut().ok_or_else(|| SproutError::Format("architecture is not a JSON object".to_string()))?;
+    let slots = object.remove(BLOBS_KEY).ok_or_else(|| SproutError::Format("architecture has no blob list".to_string()))?;
+    put_back(object, serde_json::from_value(slots)?, blobs)?;
+    object.insert("precision".to_string(), serde_json::to_value(precision)?);
+    Ok((precision, architecture))
+}
+
+//memory-maps the file when the platform allows it and falls back to reading it
+pub fn read(path: &str) -> Result<(Precision, Value), SproutError> {
+    let file = File::open(path)?;
+    //safety: the map is only read while decoding and dropped right after, a file changed underneath it fails the checksum
+    match unsafe { memmap2::Mmap::map(&file) } {
+        Ok(map) => decode(&map),
+        Err(_) => decode(&fs::read(path)?),
+    }
+}
+
+//writes name.sprout from name.json, custom layers in the model need to be registered
+pub fn json_to_binary(name: &str) -> Result<(), SproutError> {
+    let mut value: Value = serde_json::from_str(&fs::read_to_string(format!("{}.json", name))?)?;
+    schema::migrate(&mut value)?;
+    let precision = match value.get("precision") {
+        Some(precision) => serde_json::from_value(precision.clone())?,
+        None => Precision::F64,
+    };
+    match precision {
+        Precision::F32 => serde_json::from_value::<Network<f32>>(value)?.try_save_binary(name),
+        Precision::F64 => serde_json::from_value::<Network<f64>>(value)?.try_save_binary(name),
+    }
+}
+
+//writes name.json from name.sprout
+pub fn binary_to_json(name: &str) -> Result<(), SproutError> {
+    let (_, value) = read(&format!("{}.sprout", name))?;
+    fs::write(format!("{}.json", name), serde_json::to_string(&value)?)?;
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SproutError> {
+        let end = self.position.checked_add(len).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| SproutError::Format("file ends early".to_string()))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], SproutError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+fn put_back(architecture: &mut Map<String, Value>, slots: Vec<BlobSlot>, blobs: Vec<Vec<f64>>) -> Result<(), SproutError> {
+    if slots.len() != blobs.len() {
+        return Err(SproutError::Format(format!("{} blobs but {} places to put them", blobs.len(), slots.len())));
+    }
+    let layers = architecture.get_mut("layers").and_then(Value::as_array_mut)
+        .ok_or_else(|| SproutError::Format("architecture has no layers".to_string()))?;
+    for (i, (slot, blob)) in slots.into_iter().zip(blobs).enumerate() {
+        let target = layers.get_mut(slot.layer).and_then(|layer| layer.pointer_mut(&slot.pointer))
+            .ok_or_else(|| SproutError::Format(format!("blob {} belongs to {} of layer {}, which does not exist", i, slot.pointer, slot.layer)))?;
+        *target = match (slot.scalar, blob.as_slice()) {
+            (true, [value]) => Value::from(*value),
+            (true, _) => return Err(SproutError::Format(format!("blob {} should hold one value but has {}", i, blob.len()))),
+            (false, _) => Value::from(blob),
+        };
+    }
+    Ok(())
+}
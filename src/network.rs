@@ -1,35 +1,105 @@
-use rand::seq::SliceRandom;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
 use serde_derive::{Serialize, Deserialize};
 
-use crate::{layer::{Layer, LayerType}, loss_function::{LossFunction, LossType}};
-use std::{fs::File, io::{Read, Write}};
+use crate::{float::{Float, Precision}, layer::Layer, loss_function::{LossFunction, LossType}, shape::Shape, error::SproutError, model_format, onnx, optimizer::{Optimizer, OptimizerState, Schedule}, schema, tensor::Tensor, weights::{self, LoadReport, Weights}, dense_layer::DenseLayer, conv_layer::{ConvCache, ConvLayer}, pool_layer::PoolCache, context::TrainingContext, checkpoint::{self, Checkpointing}, dataset::{DataLoader, Dataset, Sample}, preprocessing::{self, LabelEncoder, Preprocessor}, validation::{self, Metrics}};
+use std::{borrow::Cow, fs::File, io::{Read, Write}, path::Path};
 
-#[derive(Serialize, Deserialize, PartialEq)]
+pub type Image<T = f64> = Vec<Vec<Vec<T>>>; //channel > rows > cols
+pub type ConvSample<T = f64> = (Image<T>, Vec<T>); //inputs, targets
+
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub enum NetworkType {
     FCN,
     CNN
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct Network {
-    pub layers: Vec<Layer>,
+#[serde(bound(serialize = "T: Float", deserialize = "T: Float"))]
+pub struct Network<T = f64> {
+    #[serde(with = "crate::layer::tagged")]
+    pub layers: Vec<Box<dyn Layer<T>>>,
     pub learning_rate: f64,
     pub batch_size: usize,
+    #[serde(default)]
     pub cost: f64,
+    #[serde(default)]
     pub print_progress: bool,
     pub network_type: NetworkType,
     pub loss_function: LossFunction,
     pub grad_threshold: f64,
+    #[serde(default)]
+    pub precision: Precision,
+    #[serde(default)]
+    pub input_shape: Option<Shape>,
+    #[serde(default)]
+    pub optimizer: Optimizer,
+    #[serde(default)]
+    pub optimizer_states: Vec<Vec<OptimizerState<T>>>, //layer > parameter tensor
+    #[serde(default)]
+    pub schedule: Schedule,
+    #[serde(default)]
+    pub epoch: usize, //epochs trained so far, drives the schedule
+    #[serde(default = "random_seed")]
+    pub seed: u64, //seeds rng, random unless set with set_seed
+    #[serde(skip, default = "entropy_rng")]
+    pub(crate) rng: ChaCha8Rng, //draws initial weights and the shuffle order
+    #[serde(skip, default = "Vec::default")]
+    pub(crate) order: Vec<usize>, //sample order of the last epoch, reshuffled in place every epoch
+    #[serde(skip, default = "Option::default")]
+    pub checkpointing: Option<Checkpointing>,
+    #[serde(default)]
+    pub preprocessing: Vec<Preprocessor>, //applied in order to every input, see fit_preprocessing
+    #[serde(default)]
+    pub label_encoder: Option<LabelEncoder>, //names the outputs for predict_label
+}
+
+fn random_seed() -> u64 {
+    rand::random()
 }
 
-impl Network {
-    pub fn new(layers: Vec<Layer>, learning_rate: f64, batch_size: usize, loss_type: LossType) -> Self {
+fn entropy_rng() -> ChaCha8Rng {
+    ChaCha8Rng::from_entropy()
+}
+
+//written out since derive would ask for T: Clone rather than what Box<dyn Layer<T>> needs
+impl<T: Float> Clone for Network<T> {
+    fn clone(&self) -> Self {
+        Network {
+            layers: self.layers.clone(),
+            learning_rate: self.learning_rate,
+            batch_size: self.batch_size,
+            cost: self.cost,
+            print_progress: self.print_progress,
+            network_type: self.network_type.clone(),
+            loss_function: self.loss_function.clone(),
+            grad_threshold: self.grad_threshold,
+            precision: self.precision,
+            input_shape: self.input_shape,
+            optimizer: self.optimizer.clone(),
+            optimizer_states: self.optimizer_states.clone(),
+            schedule: self.schedule.clone(),
+            epoch: self.epoch,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            order: self.order.clone(),
+            checkpointing: self.checkpointing.clone(),
+            preprocessing: self.preprocessing.clone(),
+            label_encoder: self.label_encoder.clone(),
+        }
+    }
+}
+
+impl<T: Float> Network<T> {
+    pub fn new(layers: Vec<Box<dyn Layer<T>>>, learning_rate: f64, batch_size: usize, loss_type: LossType) -> Self {
         let mut network_type = NetworkType::FCN;
-        for i in 0..layers.len() {
-            if layers[i].layer_type == LayerType::Convolutional || layers[i].layer_type == LayerType::Pooling {
+        for layer in layers.iter() {
+            if matches!(layer.type_name(), "conv" | "pool") {
                 network_type = NetworkType::CNN;
             }
         }
+        let seed = random_seed();
         Network {
             layers,
             learning_rate,
@@ -39,6 +109,143 @@ impl Network {
             network_type,
             loss_function: LossFunction::new(loss_type),
             grad_threshold: 0.2,
+            precision: T::PRECISION,
+            input_shape: None,
+            optimizer: Optimizer::SGD,
+            optimizer_states: vec![],
+            schedule: Schedule::Constant,
+            epoch: 0,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            order: vec![],
+            checkpointing: None,
+            preprocessing: vec![],
+            label_encoder: None,
+        }
+    }
+
+    pub fn try_new(layers: Vec<Box<dyn Layer<T>>>, learning_rate: f64, batch_size: usize, loss_type: LossType) -> Result<Self, SproutError> {
+        if layers.is_empty() {
+            return Err(SproutError::InvalidConfig("network has no layers".to_string()));
+        }
+        if batch_size == 0 {
+            return Err(SproutError::InvalidConfig("batch size must be at least 1".to_string()));
+        }
+        if !learning_rate.is_finite() || learning_rate <= 0.0 {
+            return Err(SproutError::InvalidConfig(format!("learning rate must be positive, got {}", learning_rate)));
+        }
+        Ok(Self::new(layers, learning_rate, batch_size, loss_type))
+    }
+
+    //propagates the input shape through every layer, returns the output shape or the first layer that doesn't fit
+    pub fn build(&mut self, input_shape: Shape) -> Result<Shape, SproutError> {
+        if self.layers.is_empty() {
+            return Err(SproutError::InvalidConfig("network has no layers".to_string()));
+        }
+        let mut shape = input_shape;
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            shape = layer.build(shape, &mut self.rng).map_err(|message| SproutError::ShapeMismatch(format!("layer {}: {}", i, message)))?;
+        }
+        self.input_shape = Some(input_shape);
+        Self::final_shape(shape)
+    }
+
+    //same walk as build without touching the layers, used to vet samples before they reach the kernels
+    fn check_shape(&self, input_shape: Shape) -> Result<Shape, SproutError> {
+        if self.layers.is_empty() {
+            return Err(SproutError::InvalidConfig("network has no layers".to_string()));
+        }
+        let mut shape = input_shape;
+        for (i, layer) in self.layers.iter().enumerate() {
+            shape = layer.output_shape(shape).map_err(|message| SproutError::ShapeMismatch(format!("layer {}: {}", i, message)))?;
+        }
+        Self::final_shape(shape)
+    }
+
+    fn final_shape(shape: Shape) -> Result<Shape, SproutError> {
+        if let Shape::Image(_) = shape {
+            return Err(SproutError::ShapeMismatch(format!("network has to end in a dense layer but outputs a {} image", shape)));
+        }
+        Ok(shape)
+    }
+
+    fn check_dense_sample(&self, inputs: &[T]) -> Result<Shape, SproutError> {
+        if inputs.iter().any(|x| !x.is_finite()) {
+            return Err(SproutError::Numerical("input contains NaN or infinite values".to_string()));
+        }
+        self.check_shape(Shape::Flat(inputs.len()))
+    }
+
+    fn check_conv_sample(&self, inputs: &[Vec<Vec<T>>]) -> Result<Shape, SproutError> {
+        let rows = inputs.first().map_or(0, |channel| channel.len());
+        let cols = inputs.first().and_then(|channel| channel.first()).map_or(0, |row| row.len());
+        if rows == 0 || cols == 0 {
+            return Err(SproutError::ShapeMismatch("input image is empty".to_string()));
+        }
+        if inputs.iter().any(|channel| channel.len() != rows || channel.iter().any(|row| row.len() != cols)) {
+            return Err(SproutError::ShapeMismatch("input image channels and rows are not all the same size".to_string()));
+        }
+        if inputs.iter().flatten().flatten().any(|x| !x.is_finite()) {
+            return Err(SproutError::Numerical("input contains NaN or infinite values".to_string()));
+        }
+        self.check_shape(Shape::Image([inputs.len(), rows, cols]))
+    }
+
+    fn check_sample(&self, sample: &Sample<T>) -> Result<(), SproutError> {
+        let output_shape = match &sample.input {
+            Tensor::Flat(inputs) => self.check_dense_sample(inputs)?,
+            Tensor::Image(inputs) => self.check_conv_sample(inputs)?,
+        };
+        Self::check_targets(output_shape, &sample.target)
+    }
+
+    fn check_targets(output_shape: Shape, targets: &[T]) -> Result<(), SproutError> {
+        if targets.len() != output_shape.size() {
+            return Err(SproutError::ShapeMismatch(format!("{} targets for {} network outputs", targets.len(), output_shape.size())));
+        }
+        if targets.iter().any(|x| !x.is_finite()) {
+            return Err(SproutError::Numerical("targets contain NaN or infinite values".to_string()));
+        }
+        Ok(())
+    }
+
+    fn check_training(&self, samples: usize) -> Result<(), SproutError> {
+        if samples == 0 {
+            return Err(SproutError::InvalidConfig("training data is empty".to_string()));
+        }
+        if self.batch_size == 0 {
+            return Err(SproutError::InvalidConfig("batch size must be at least 1".to_string()));
+        }
+        self.optimizer.validate()?;
+        self.schedule.validate()?;
+        for (i, layer) in self.layers.iter().enumerate() {
+            if let Some(regularizer) = layer.regularizer() {
+                regularizer.validate().map_err(|err| err.context(&format!("layer {}", i)))?;
+            }
+        }
+        if let Some(checkpointing) = &self.checkpointing {
+            checkpointing.validate()?;
+        }
+        Ok(())
+    }
+
+    //next epoch's sample order, the previous order is reshuffled so a resumed run repeats it exactly
+    fn shuffle_order(&mut self, samples: usize) -> Vec<usize> {
+        let mut order = std::mem::take(&mut self.order);
+        if order.len() != samples {
+            order = (0..samples).collect();
+        }
+        order.shuffle(&mut self.rng);
+        order
+    }
+
+    fn check_parameters(&self, epoch: usize) -> Result<(), SproutError> {
+        let is_finite = |layer: &dyn Layer<T>| layer.parameters().iter().all(|params| params.iter().all(|x| x.is_finite()));
+        match self.layers.iter().position(|layer| !is_finite(layer.as_ref())) {
+            Some(i) => Err(SproutError::Numerical(format!(
+                "layer {} has NaN or infinite parameters after epoch {}, try a lower learning rate", i, epoch
+            ))),
+            None => Ok(()),
         }
     }
 
@@ -46,337 +253,584 @@ impl Network {
         self.print_progress = value
     }
 
-    pub fn dense_forward(&mut self, inputs: Vec<f64>) -> Vec<f64> {
-        let mut current: Vec<f64> = inputs; 
-        for i in 0..self.layers.len() {
-            current = self.layers[i].dense_forward(current);
-        }
-        current
-    }
-
-    pub fn conv_forward(&mut self, inputs: Vec<Vec<Vec<f64>>>) -> Vec<f64> {
-        let mut conv_current = inputs; 
-        let mut dense_current = vec![]; 
-        for i in 0..self.layers.len() {
-            match self.layers[i].layer_type {
-                LayerType::Dense => 
-                    {
-                        if i != 0 {
-                            if self.layers[i - 1].layer_type != LayerType::Dense {
-                                dense_current = Self::flatten(&conv_current);
-                            }
-                            dense_current = self.layers[i].dense_forward(dense_current);
-                        } 
-                    },
-                LayerType::Convolutional => 
-                    {
-                        conv_current = self.layers[i].conv_forward(conv_current);
-                    },
-                LayerType::Pooling => 
-                    {
-                        conv_current = self.layers[i].pool_forward(conv_current);
-                    },
-            }
+    //restarts the rng from seed, set it before the network is built (or reset) for the initial weights to follow it
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        self.order = vec![];
+    }
+
+    //same as predict_batch for a single sample, none of the forward/predict functions change the network
+    pub fn dense_forward(&self, inputs: Vec<T>) -> Vec<T> {
+        self.predict(&Tensor::Flat(inputs)).into_flat()
+    }
+
+    pub fn try_dense_forward(&self, inputs: Vec<T>) -> Result<Vec<T>, SproutError> {
+        self.try_predict(&Tensor::Flat(inputs)).map(Tensor::into_flat)
+    }
+
+    pub fn dense_forward_batch(&self, inputs: Vec<Vec<T>>) -> Vec<Vec<T>> {
+        self.predict_batch(&inputs)
+    }
+
+    pub fn conv_forward(&self, inputs: Vec<Vec<Vec<T>>>) -> Vec<T> {
+        self.predict(&Tensor::Image(inputs)).into_flat()
+    }
+
+    pub fn try_conv_forward(&self, inputs: Vec<Vec<Vec<T>>>) -> Result<Vec<T>, SproutError> {
+        self.try_predict(&Tensor::Image(inputs)).map(Tensor::into_flat)
+    }
+
+    pub fn conv_forward_batch(&self, inputs: Vec<Vec<Vec<Vec<T>>>>) -> Vec<Vec<T>> {
+        self.conv_predict_batch(&inputs)
+    }
+
+    //inference only takes &self, so one network can be shared between threads behind an Arc.
+    //inputs aren't checked here, try_predict is the checked version and returns a wrong shape as an error
+    pub fn predict(&self, input: &Tensor<T>) -> Tensor<T> {
+        let input = self.preprocess(input)
+            .unwrap_or_else(|err| panic!("input of shape {:?} doesn't fit the network's preprocessing ({}), use try_predict to check inputs", input.shape(), err));
+        self.predict_preprocessed(&input)
+    }
+
+    pub fn try_predict(&self, input: &Tensor<T>) -> Result<Tensor<T>, SproutError> {
+        let input = self.preprocess(input)?;
+        match input.as_ref() {
+            Tensor::Flat(values) => self.check_dense_sample(values)?,
+            Tensor::Image(img) => self.check_conv_sample(img)?,
+        };
+        Ok(self.predict_preprocessed(&input))
+    }
+
+    fn predict_preprocessed(&self, input: &Tensor<T>) -> Tensor<T> {
+        let mut layers = self.layers.iter();
+        let Some(first) = layers.next() else {
+            return input.clone();
+        };
+        layers.fold(first.predict(input), |current, layer| layer.predict(&current))
+    }
+
+    //the label encoder's name for the highest output
+    pub fn predict_label(&self, input: &Tensor<T>) -> Result<String, SproutError> {
+        let encoder = self.label_encoder.as_ref()
+            .ok_or_else(|| SproutError::InvalidConfig("network has no label encoder to name its outputs".to_string()))?;
+        let output = self.try_predict(input)?.into_flat();
+        encoder.decode_output(&output).map(str::to_string).ok_or_else(|| SproutError::ShapeMismatch(format!(
+            "label encoder has {} classes but the network has {} outputs", encoder.classes.len(), output.len()
+        )))
+    }
+
+    //fits the steps in order on raw training inputs (images flattened) and keeps them, from then on every input the network
+    //is given, in training or prediction, goes through them first. the network is built for their output
+    pub fn fit_preprocessing(&mut self, steps: Vec<Preprocessor>, inputs: &[Vec<T>]) -> Result<(), SproutError> {
+        let mut steps = steps;
+        preprocessing::fit_all(&mut steps, inputs)?;
+        self.preprocessing = steps;
+        Ok(())
+    }
+
+    //images are flattened for the steps and have to come out the same size so they can be put back together
+    pub fn preprocess<'a>(&self, input: &'a Tensor<T>) -> Result<Cow<'a, Tensor<T>>, SproutError> {
+        if self.preprocessing.is_empty() {
+            return Ok(Cow::Borrowed(input));
+        }
+        let output = preprocessing::transform_all(&self.preprocessing, &input.to_flat())?;
+        match input.shape() {
+            Shape::Image([channels, rows, cols]) if output.len() != channels * rows * cols => Err(SproutError::ShapeMismatch(format!(
+                "preprocessing turns {} x {} x {} images into {} values, images have to keep their size", channels, rows, cols, output.len()
+            ))),
+            shape => Ok(Cow::Owned(Tensor::from_flat(output, shape))),
+        }
+    }
+
+    fn preprocess_sample(&self, mut sample: Sample<T>) -> Result<Sample<T>, SproutError> {
+        if !self.preprocessing.is_empty() {
+            sample.input = self.preprocess(&sample.input)?.into_owned();
         }
-        dense_current
+        Ok(sample)
+    }
+
+    //samples are run in parallel
+    pub fn predict_batch(&self, inputs: &[Vec<T>]) -> Vec<Vec<T>> {
+        inputs.par_iter().map(|sample| self.dense_forward(sample.clone())).collect()
+    }
+
+    pub fn try_predict_batch(&self, inputs: &[Vec<T>]) -> Result<Vec<Vec<T>>, SproutError> {
+        inputs.par_iter().enumerate()
+            .map(|(i, sample)| self.try_dense_forward(sample.clone()).map_err(|err| err.context(&format!("sample {}", i))))
+            .collect()
+    }
+
+    pub fn try_conv_predict_batch(&self, inputs: &[Image<T>]) -> Result<Vec<Vec<T>>, SproutError> {
+        inputs.par_iter().enumerate()
+            .map(|(i, sample)| self.try_conv_forward(sample.clone()).map_err(|err| err.context(&format!("sample {}", i))))
+            .collect()
     }
 
-    pub fn flatten(inputs: &Vec<Vec<Vec<f64>>>) -> Vec<f64> {
-        let flat = inputs.iter()
+    pub fn conv_predict_batch(&self, inputs: &[Image<T>]) -> Vec<Vec<T>> {
+        inputs.par_iter().map(|sample| self.conv_forward(sample.clone())).collect()
+    }
+
+    pub fn flatten(inputs: &[Vec<Vec<T>>]) -> Vec<T> {
+        inputs.iter()
             .flat_map(|row| row.iter())
             .flat_map(|col| col.iter())
             .cloned() // Cloned to avoid borrowing issues
-            .collect();
-        flat
-    }
-
-    pub fn reshape(input: Vec<f64>, channels: usize, rows: usize, cols: usize) -> Vec<Vec<Vec<f64>>> {
-        let mut output = vec![vec![vec![0.0; cols]; rows]; channels];
-        
-        for i in 0..channels {
-            let mut index = 0;
-            for j in 0..rows {
-                for k in 0..cols {
-                    output[i][j][k] = input[index].clone();
-                    index += 1;
+            .collect()
+    }
+
+    pub fn reshape(input: Vec<T>, channels: usize, rows: usize, cols: usize) -> Vec<Vec<Vec<T>>> {
+        let mut values = input.into_iter();
+        (0..channels).map(|_| {
+            (0..rows).map(|_| values.by_ref().take(cols).collect()).collect()
+        }).collect()
+    }
+
+    //training forward pass, what backward needs is kept in the returned context rather than in the layers
+    pub fn forward_batch(&self, inputs: Vec<Tensor<T>>) -> (Vec<Tensor<T>>, TrainingContext) {
+        let mut caches = Vec::with_capacity(self.layers.len());
+        let mut current = inputs;
+        for layer in self.layers.iter() {
+            let (outputs, cache) = layer.forward(current);
+            caches.push(cache);
+            current = outputs;
+        }
+        (current, TrainingContext { caches })
+    }
+
+    //loss_gradients[s] belongs to the s-th sample of the forward pass that made the context,
+    //backpropagates through every layer and then steps the optimizer on the summed gradients
+    pub fn backward_batch(&mut self, context: TrainingContext, loss_gradients: Vec<Tensor<T>>) {
+        let batch_size = loss_gradients.len();
+        let mut delta_output = loss_gradients;
+        let mut gradients = Vec::with_capacity(self.layers.len());
+        for (layer, cache) in self.layers.iter().zip(&context.caches).rev() {
+            let (next_delta, layer_gradients) = layer.backward(cache, delta_output);
+            gradients.push(layer_gradients);
+            delta_output = next_delta;
+        }
+        gradients.reverse();
+        self.apply_gradients(gradients, batch_size);
+    }
+
+    //regularized weights (see Regularizer) add their penalty to the cost and its gradient to theirs before the update,
+    //then get decayed and constrained
+    fn apply_gradients(&mut self, mut gradients: Vec<Vec<Vec<T>>>, batch_size: usize) {
+        let learning_rate = self.schedule.learning_rate(self.learning_rate, self.epoch);
+        self.optimizer_states.resize_with(self.layers.len(), Vec::new);
+        for ((layer, gradients), states) in self.layers.iter_mut().zip(gradients.iter_mut()).zip(self.optimizer_states.iter_mut()) {
+            let regularizer = layer.regularizer().cloned();
+            let units = layer.weight_units();
+            states.resize_with(gradients.len(), OptimizerState::default);
+            for (i, ((params, gradients), state)) in layer.parameters_mut().into_iter().zip(gradients.iter_mut()).zip(states.iter_mut()).enumerate() {
+                let regularizer = regularizer.as_ref().filter(|_| i == 0);
+                if let Some(regularizer) = regularizer {
+                    //counted once per sample, like the loss, so it averages out with it at the end of the epoch
+                    self.cost += regularizer.penalty(params) * batch_size as f64;
+                    regularizer.add_gradients(params, gradients, batch_size);
+                }
+                self.optimizer.update(params, gradients, state, learning_rate, batch_size);
+                if let Some(regularizer) = regularizer {
+                    regularizer.after_update(params, units, learning_rate);
                 }
             }
         }
-    
-        output
-    }
-
-    pub fn conv_backward(&mut self, loss_gradient: Vec<f64>) {
-        let mut delta_output = loss_gradient.clone();
-        let mut conv_delta = vec![];
-
-        for i in (0..self.layers.len()).rev() {
-            match self.layers[i].layer_type {
-                LayerType::Dense => 
-                    {
-                        let layer = &mut self.layers[i];
-                        delta_output = layer.dense_backward(delta_output.clone(), self.learning_rate);  
-                        if i - 1 >= 0 as usize {
-                            if self.layers[i - 1].layer_type != LayerType::Dense {
-                                let params = self.layers[i - 1].conv_params.as_ref().unwrap();
-                                conv_delta = Self::reshape(delta_output.clone(), 
-                                    params.outputs.len(), 
-                                    params.outputs[0].len(), 
-                                    params.outputs[0][0].len()
-                                );
-                            }
-                        } 
-                    },
-                LayerType::Convolutional => 
-                    {
-                        let layer = &mut self.layers[i];
-                        conv_delta = layer.conv_backward(conv_delta.clone(), self.learning_rate);
-                    },
-                LayerType::Pooling => 
-                    {
-                        let layer = &mut self.layers[i];
-                        conv_delta = layer.pool_backward(conv_delta.clone());
-                    },
+    }
+
+    //a network that was never built gets built from the first training sample
+    fn ensure_built(&mut self, input_shape: Shape) -> Result<(), SproutError> {
+        if self.input_shape.is_none() {
+            self.build(input_shape)?;
+        }
+        Ok(())
+    }
+
+    //one forward/backward pass over a batch of samples
+    fn train_batch(&mut self, inputs: Vec<Tensor<T>>, targets: &[&Vec<T>]) {
+        let (outputs, context) = self.forward_batch(inputs);
+        let loss_gradients = outputs.iter().zip(targets)
+            .map(|(output, target)| Tensor::Flat(self.sample_loss(&output.to_flat(), target)))
+            .collect();
+        self.backward_batch(context, loss_gradients);
+    }
+
+    //adds the sample's cost and returns its clipped loss gradient
+    fn sample_loss(&mut self, output: &[T], target: &[T]) -> Vec<T> {
+        let cost = self.loss_function.function(output, target).as_f64();
+        if cost.is_finite() {
+            self.cost += cost;
+        }
+
+        let mut loss_gradient = self.loss_function.derivative(output, target);
+        let l2_norm = loss_gradient.iter().map(|x| x.as_f64().powf(2.0)).sum::<f64>().sqrt();
+
+        if l2_norm > self.grad_threshold {
+            let scale = T::from_f64(self.grad_threshold / l2_norm);
+            for gradient in loss_gradient.iter_mut() {
+                *gradient *= scale;
             }
         }
+        loss_gradient
     }
 
-    pub fn dense_backward(&mut self, loss_gradient: Vec<f64>) {
-        let mut delta_output = loss_gradient.clone();
+    pub fn conv_train(&mut self, data: Vec<ConvSample<T>>, epochs: usize) {
+        self.try_conv_train(data, epochs).unwrap()
+    }
+
+    pub fn try_conv_train(&mut self, data: Vec<ConvSample<T>>, epochs: usize) -> Result<(), SproutError> {
+        self.train_samples(data.into_iter().map(Sample::from).collect(), epochs)
+    }
+
+    pub fn dense_train(&mut self, data: Vec<[Vec<T>; 2]>, epochs: usize) {
+        self.try_dense_train(data, epochs).unwrap()
+    }
+
+    pub fn try_dense_train(&mut self, data: Vec<[Vec<T>; 2]>, epochs: usize) -> Result<(), SproutError> {
+        self.train_samples(data.into_iter().map(Sample::from).collect(), epochs)
+    }
 
-        for i in (0..self.layers.len()).rev() {
-            let layer = &mut self.layers[i];
-            delta_output = layer.dense_backward(delta_output.clone(), self.learning_rate);
+    //every sample is preprocessed and checked before training starts, so bad data never leaves the network half trained
+    fn train_samples(&mut self, samples: Vec<Sample<T>>, epochs: usize) -> Result<(), SproutError> {
+        self.check_training(samples.len())?;
+        let samples = samples.into_iter().enumerate()
+            .map(|(i, sample)| self.preprocess_sample(sample).map_err(|err| err.context(&format!("sample {}", i))))
+            .collect::<Result<Vec<Sample<T>>, SproutError>>()?;
+        self.ensure_built(samples[0].input.shape())?;
+        for (i, sample) in samples.iter().enumerate() {
+            self.check_sample(sample).map_err(|err| err.context(&format!("sample {}", i)))?;
         }
+        self.train_loader(&DataLoader::new(samples, self.batch_size), epochs, false)
+    }
+
+    //trains on batches from the loader, its batch size is used in place of the network's,
+    //samples are checked as they are loaded so lazily loaded data can fail partway through an epoch
+    pub fn train(&mut self, loader: &DataLoader<T>, epochs: usize) {
+        self.try_train(loader, epochs).unwrap()
     }
 
-    pub fn conv_train(&mut self, mut data: Vec<(Vec<Vec<Vec<f64>>>, Vec<f64>)>, epochs: usize) {
-        let samples = data.len() as f64;
+    pub fn try_train(&mut self, loader: &DataLoader<T>, epochs: usize) -> Result<(), SproutError> {
+        self.train_loader(loader, epochs, true)
+    }
+
+    //preprocess is false when the samples already went through the network's preprocessing
+    fn train_loader(&mut self, loader: &DataLoader<T>, epochs: usize, preprocess: bool) -> Result<(), SproutError> {
+        loader.validate()?;
+        self.check_training(loader.len())?;
+        let mut first = loader.dataset().get(0).map_err(|err| err.context("sample 0"))?;
+        if preprocess {
+            first = self.preprocess_sample(first).map_err(|err| err.context("sample 0"))?;
+        }
+        self.ensure_built(first.input.shape())?;
+
+        let samples = loader.samples_per_epoch() as f64;
         for i in 0..epochs {
             if i % 1000 == 0 && self.print_progress {
                 println!("Progress: {}%", 100.0 * (i as f64 / epochs as f64));
             }
 
             self.cost = 0.0; // Reset cost
-            
-            Self::shuffle_tensor(&mut data);
-            
-            let batches = (samples / self.batch_size as f64).ceil() as usize;
-    
-            for batch in 0..batches { //each batch
-                let mut loss_gradient: Vec<f64> = vec![0.0; data[0].1.len()]; //output nodes
-                let mut sample_max = self.batch_size;
-                let current_sample = batch * self.batch_size;
-                if  current_sample + self.batch_size >= samples as usize && self.batch_size != 1 {
-                    sample_max = samples as usize - current_sample;
+
+            //the network's rng shuffles so the order is seeded and survives checkpoints
+            let order = match loader.shuffle {
+                true => self.shuffle_order(loader.len()),
+                false => (0..loader.len()).collect(),
+            };
+
+            for (indices, batch) in order.chunks(loader.batch_size).zip(loader.batches_in(&order, &mut self.rng)) { //each batch
+                let mut batch = batch?;
+                if preprocess {
+                    batch = batch.into_iter().zip(indices)
+                        .map(|(sample, index)| self.preprocess_sample(sample).map_err(|err| err.context(&format!("sample {}", index))))
+                        .collect::<Result<Vec<Sample<T>>, SproutError>>()?;
                 }
-                for s in 0..sample_max { //each sample
-                    let sample = data[current_sample + s].clone();
-                    let output = self.conv_forward(sample.0.clone());
-                    let target = &sample.1;
-                    let mut true_index = 0;
-                    for i in 0..target.len() {
-                        if target[i] == 1.0 {
-                            true_index = i;
-                            break;
-                        }
-                    }
-                    let mut cost = self.loss_function.function(&output, &target, true_index);
-                    if cost.is_nan() || cost.is_infinite() {
-                        cost = 0.0;
-                    }
-
-                    self.cost += cost;
-        
-                    let sample_loss = self.loss_function.derivative(&output, target, true_index);
-
-                    for i in 0..loss_gradient.len() { //Sum Gradients
-                        loss_gradient[i] += sample_loss[i];
-                    }
-
-                    let l2_norm = loss_gradient.iter().map(|x| x.powf(2.0)).sum::<f64>().sqrt();
-
-                    if l2_norm > self.grad_threshold {
-                        let scale = self.grad_threshold / l2_norm;
-                        for i in 0..loss_gradient.len() {
-                            loss_gradient[i] *= scale;
-                        }
-                    }
-                    
+                for (index, sample) in indices.iter().zip(&batch) {
+                    self.check_sample(sample).map_err(|err| err.context(&format!("sample {}", index)))?;
                 }
-                // for i in 0..loss_gradient.len() {
-                //     loss_gradient[i] /= self.batch_size as f64;
-                // }
-
-                self.conv_backward(loss_gradient);
-                
+                let (inputs, targets): (Vec<Tensor<T>>, Vec<Vec<T>>) = batch.into_iter().map(|sample| (sample.input, sample.target)).unzip();
+                let targets: Vec<&Vec<T>> = targets.iter().collect();
+                self.train_batch(inputs, &targets);
+            }
+            if loader.shuffle {
+                self.order = order;
             }
             self.cost /= samples; // Compute average cost per sample
+            self.check_parameters(i)?;
+            self.epoch += 1;
+            checkpoint::after_epoch(self)?;
         }
-    
+
         if self.print_progress {
             println!("Training Complete");
         }
+        Ok(())
     }
 
-    pub fn dense_train(&mut self, mut data: Vec<[Vec<f64>; 2]>, epochs: usize) {
-        let samples = data.len() as f64;
-    
-        for i in 0..epochs {
-            if i % 1000 == 0 && self.print_progress {
-                println!("Progress: {}%", 100.0 * (i as f64 / epochs as f64));
-            }
-            
-            Self::shuffle_vector(&mut data);
-            self.cost = 0.0; // Reset cost for each epoch
-
-            let batches = (samples / self.batch_size as f64).ceil() as usize;
-    
-            for batch in 0..batches { //each batch
-                let mut loss_gradient: Vec<f64> = vec![0.0; data[0][1].len()]; //output nodes
-                let mut sample_max = self.batch_size;
-                let current_sample = batch * self.batch_size;
-                if  current_sample + self.batch_size >= samples as usize  && self.batch_size != 1 {
-                    sample_max = samples as usize - current_sample;
-                }
-                for s in 0..sample_max { //each sample
-                    let sample = data[batch * self.batch_size + s].clone();
-                    let output = self.dense_forward(sample[0].clone());
-                    let target = &sample[1];
-                    let mut true_index = 0;
-                    for i in 0..target.len() {
-                        if target[i] == 1.0 {
-                            true_index = i;
-                            break;
-                        }
-                    }
-                    self.cost += self.loss_function.function(&output, &target, true_index);
-        
-                    let sample_loss = self.loss_function.derivative(&output, target, true_index);
-
-                    for i in 0..loss_gradient.len() { //Sum Gradients
-                        loss_gradient[i] += sample_loss[i];
-                    }
-
-                    let l2_norm = loss_gradient.iter().map(|x| x.powf(2.0)).sum::<f64>().sqrt();
-
-                    if l2_norm > self.grad_threshold {
-                        let scale = self.grad_threshold / l2_norm;
-                        for i in 0..loss_gradient.len() {
-                            loss_gradient[i] *= scale;
-                        }
-                    }
-                }
-                let _ = loss_gradient.iter().map(|x| x / self.batch_size as f64).collect::<Vec<f64>>();
-                
-                self.dense_backward(loss_gradient);
-        
-                self.cost /= self.batch_size as f64; // Compute average cost per sample
-            }
+    //same layers, settings and preprocessing, with new weights drawn from seed and no training history
+    pub fn fresh(&self, seed: u64) -> Self {
+        self.try_fresh(seed).unwrap()
+    }
+
+    pub fn try_fresh(&self, seed: u64) -> Result<Self, SproutError> {
+        let mut network = self.clone();
+        network.set_seed(seed);
+        network.cost = 0.0;
+        network.epoch = 0;
+        network.optimizer_states = vec![];
+        network.checkpointing = None;
+        //an unbuilt network draws its weights when it is built
+        if network.input_shape.is_some() {
+            network.try_reset()?;
         }
-    
-        if self.print_progress {
-            println!("Training Complete");
+        Ok(network)
+    }
+
+    //loss, accuracy and mean absolute error over a dataset, samples go through the preprocessing like in predict
+    pub fn evaluate(&self, dataset: &dyn Dataset<T>) -> Result<Metrics, SproutError> {
+        if dataset.is_empty() {
+            return Err(SproutError::InvalidConfig("evaluation data is empty".to_string()));
         }
+        let score = |index: usize| -> Result<[f64; 3], SproutError> {
+            let sample = dataset.get(index)?;
+            let output = self.try_predict(&sample.input)?.into_flat();
+            Self::check_targets(Shape::Flat(output.len()), &sample.target)?;
+            let loss = self.loss_function.function(&output, &sample.target).as_f64();
+            let correct = validation::class_of(&output) == validation::class_of(&sample.target);
+            let error = output.iter().zip(&sample.target).map(|(o, t)| (o.as_f64() - t.as_f64()).abs()).sum::<f64>() / output.len() as f64;
+            Ok([loss, f64::from(u8::from(correct)), error])
+        };
+        let scores = (0..dataset.len()).into_par_iter()
+            .map(|i| score(i).map_err(|err| err.context(&format!("sample {}", i))))
+            .collect::<Result<Vec<[f64; 3]>, SproutError>>()?;
+        let mean = |k: usize| scores.iter().map(|score| score[k]).sum::<f64>() / scores.len() as f64;
+        Ok(Metrics { loss: mean(0), accuracy: mean(1), mean_absolute_error: mean(2) })
     }
 
     pub fn reset(&mut self) {
+        self.try_reset().unwrap()
+    }
+
+    //a model loaded in another process can name custom initializers that aren't registered here
+    pub fn try_reset(&mut self) -> Result<(), SproutError> {
         self.cost = 0.0;
-        for i in 0..self.layers.len() {
-            self.layers[i].reset();
+        self.optimizer_states = vec![];
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            layer.reset(&mut self.rng).map_err(|err| err.context(&format!("layer {}", i)))?;
         }
+        Ok(())
     }
 
     pub fn print_weights(&self) {
-        for i in 0..self.layers.len() {
-            println!("{:#?}", self.layers[i].get_dense_weights());
+        for layer in self.layers.iter() {
+            println!("{:#?}", layer.parameters().first());
         }
     }
 
     pub fn print_biases(&self) {
-        for i in 0..self.layers.len() {
-            println!("{:#?}", self.layers[i].get_dense_biases());
+        for layer in self.layers.iter() {
+            println!("{:#?}", layer.parameters().get(1));
         }
     }
 
-    pub fn get_weights(&self) -> (Vec<Vec<Vec<Vec<f64>>>>, Vec<Vec<Vec<f64>>>) {
+    pub fn get_weights(&self) -> (Vec<Image<T>>, Vec<Vec<Vec<T>>>) {
         let mut dense_weights = vec![];
         let mut conv_weights = vec![];
-        for i in 0..self.layers.len() {
-            if self.layers[i].layer_type == LayerType::Dense {
-                dense_weights.push(self.layers[i].get_dense_weights());
-            } else {
-                conv_weights.push(self.layers[i].get_conv_weights());
+        for layer in self.layers.iter() {
+            if let Some(layer) = layer.downcast_ref::<DenseLayer<T>>() {
+                dense_weights.push(layer.get_weights());
+            } else if let Some(layer) = layer.downcast_ref::<ConvLayer<T>>() {
+                conv_weights.push(layer.get_weights());
             }
         }
         (conv_weights, dense_weights)
     }
 
-    pub fn get_biases(&self) -> (Vec<f64>, Vec<Vec<f64>>) {
+    pub fn get_biases(&self) -> (Vec<T>, Vec<Vec<T>>) {
         let mut dense_biases = vec![];
         let mut conv_biases = vec![];
-        for i in 0..self.layers.len() {
-            if self.layers[i].layer_type == LayerType::Dense {
-                dense_biases.push(self.layers[i].get_dense_biases());
-            } else {
-                conv_biases.push(self.layers[i].get_conv_biases());
+        for layer in self.layers.iter() {
+            if let Some(layer) = layer.downcast_ref::<DenseLayer<T>>() {
+                dense_biases.push(layer.get_biases());
+            } else if let Some(layer) = layer.downcast_ref::<ConvLayer<T>>() {
+                conv_biases.push(layer.get_bias());
             }
         }
         (conv_biases, dense_biases)
     }
 
-    pub fn get_conv_outputs(&self) -> Vec<Vec<Vec<Vec<f64>>>> {
+    //outputs of the forward pass behind context for each conv and pooling layer before the first dense layer
+    pub fn get_conv_outputs(&self, context: &TrainingContext) -> Vec<Vec<Vec<Vec<Vec<T>>>>> {
         let mut outputs = vec![];
-        for i in 0..self.layers.len() {
-            if self.layers[i].layer_type == LayerType::Dense {
+        for (layer, cache) in self.layers.iter().zip(&context.caches) {
+            if let Some(cache) = cache.downcast_ref::<ConvCache<T>>() {
+                outputs.push(cache.outputs.clone());
+            } else if let Some(cache) = cache.downcast_ref::<PoolCache<T>>() {
+                outputs.push(cache.outputs.clone());
+            } else if layer.type_name() == "dense" {
                 break;
             }
-            let output = self.layers[i].get_conv_outputs();
-            outputs.push(output)
         }
         outputs
     }
 
+    //node counts of the dense layers, inputs first
     pub fn get_nodes(&self) -> Vec<usize>{
-        let mut nodes = vec![];
-        for i in 0..self.layers.len() {
-            if self.layers[i].layer_type == LayerType::Convolutional {
-                continue;
-            }
-            nodes.push(self.layers[i].get_input_nodes());
+        let dense_layers: Vec<&DenseLayer<T>> = self.layers.iter().filter_map(|layer| layer.downcast_ref()).collect();
+        let mut nodes: Vec<usize> = dense_layers.iter().map(|layer| layer.params.nodes_in).collect();
+        if let Some(layer) = dense_layers.last() {
+            nodes.push(layer.params.nodes_out);
         }
-        nodes.push(self.layers[self.layers.len() - 1].get_nodes());
         nodes
     }
 
-    pub fn shuffle_vector(vec: &mut Vec<[Vec<f64>; 2]>) {
-        let mut rng = rand::thread_rng();
-        vec.shuffle(&mut rng);
+    pub fn shuffle_vector(&mut self, vec: &mut [[Vec<T>; 2]]) {
+        vec.shuffle(&mut self.rng);
     }
 
-    pub fn shuffle_tensor(vec: &mut Vec<(Vec<Vec<Vec<f64>>>, Vec<f64>)>) {
-        let mut rng = rand::thread_rng();
-        vec.shuffle(&mut rng);
+    pub fn shuffle_tensor(&mut self, vec: &mut [ConvSample<T>]) {
+        vec.shuffle(&mut self.rng);
     }
 
     pub fn save_model(&self, name: &str) {
-        let model: &Network = self;
-        let serialized = serde_json::to_string(&model).unwrap();
-        let mut json = File::create(format!("{}.json", name)).unwrap();
-        json.write_all(serialized.as_bytes()).expect("Error writing bytes to JSON");
+        self.try_save_model(name).unwrap()
+    }
+
+    pub fn try_save_model(&self, name: &str) -> Result<(), SproutError> {
+        let serialized = serde_json::to_string(&schema::to_value(self)?)?;
+        let mut json = File::create(format!("{}.json", name))?;
+        json.write_all(serialized.as_bytes())?;
+        Ok(())
     }
 
     pub fn load_model(&mut self, name: &str) {
-        let mut str = String::new();
-        let _ = File::open(format!("{}.json", name)).expect("Error opening file").read_to_string(&mut str);
-        let mut model: Network = serde_json::from_str(&str).unwrap();
+        self.try_load_model(name).unwrap()
+    }
+
+    pub fn try_load_model(&mut self, name: &str) -> Result<(), SproutError> {
+        let mut model = Self::try_from_load(name)?;
         std::mem::swap(self, &mut model);
+        Ok(())
     }
 
     pub fn from_load(name: &str) -> Self {
+        Self::try_from_load(name).unwrap()
+    }
+
+    //JSON numbers read into either precision, so models saved as f32 or f64 load into both,
+    //models saved by older versions are upgraded to the current layout on the way in
+    pub fn try_from_load(name: &str) -> Result<Self, SproutError> {
         let mut str = String::new();
-        let _ = File::open(format!("{}.json", name)).expect("Error opening file").read_to_string(&mut str);
-        let model: Network = serde_json::from_str(&str).unwrap();
-        model
+        File::open(format!("{}.json", name))?.read_to_string(&mut str)?;
+        schema::from_value(serde_json::from_str(&str)?)
+    }
+
+    //compact binary form holding only the parameters and config, see model_format
+    pub fn save_binary(&self, name: &str) {
+        self.try_save_binary(name).unwrap()
+    }
+
+    pub fn try_save_binary(&self, name: &str) -> Result<(), SproutError> {
+        let bytes = model_format::encode(self)?;
+        File::create(format!("{}.sprout", name))?.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn from_binary(name: &str) -> Self {
+        Self::try_from_binary(name).unwrap()
+    }
+
+    //like try_from_load, a model saved in either precision loads into both
+    pub fn try_from_binary(name: &str) -> Result<Self, SproutError> {
+        let (_, value) = model_format::read(&format!("{}.sprout", name))?;
+        schema::from_value(value)
+    }
+
+    //writes a checkpoint every few epochs during training, see checkpoint::Checkpointing
+    pub fn set_checkpointing(&mut self, checkpointing: Checkpointing) {
+        self.checkpointing = Some(checkpointing);
+    }
+
+    pub fn save_checkpoint(&self, path: &str) {
+        self.try_save_checkpoint(path).unwrap()
+    }
+
+    pub fn try_save_checkpoint(&self, path: &str) -> Result<(), SproutError> {
+        checkpoint::save(self, Path::new(path))
+    }
+
+    //picks training back up from a checkpoint, training for the remaining epochs then gives the same
+    //network bit for bit as a run that was never interrupted
+    pub fn resume_from(path: &str) -> Self {
+        Self::try_resume_from(path).unwrap()
+    }
+
+    pub fn try_resume_from(path: &str) -> Result<Self, SproutError> {
+        checkpoint::load(Path::new(path))
+    }
+
+    //layer names used as weight keys, e.g. ["conv_0", "pool_0", "dense_0", "dense_1"]
+    pub fn layer_names(&self) -> Vec<String> {
+        weights::layer_names(self)
+    }
+
+    pub fn named_weights(&self) -> Weights<T> {
+        weights::collect(self)
     }
-}
\ No newline at end of file
+
+    pub fn load_named_weights(&mut self, weights: &Weights<T>, strict: bool) -> Result<LoadReport, SproutError> {
+        weights::apply(self, weights, strict)
+    }
+
+    //writes only the parameters to name.weights.json
+    pub fn save_weights(&self, name: &str) {
+        self.try_save_weights(name).unwrap()
+    }
+
+    pub fn try_save_weights(&self, name: &str) -> Result<(), SproutError> {
+        let serialized = serde_json::to_string(&self.named_weights())?;
+        File::create(format!("{}.weights.json", name))?.write_all(serialized.as_bytes())?;
+        Ok(())
+    }
+
+    //loads name.weights.json into this architecture, see weights::apply for strict vs non-strict
+    pub fn load_weights(&mut self, name: &str, strict: bool) -> LoadReport {
+        self.try_load_weights(name, strict).unwrap()
+    }
+
+    pub fn try_load_weights(&mut self, name: &str, strict: bool) -> Result<LoadReport, SproutError> {
+        let mut str = String::new();
+        File::open(format!("{}.weights.json", name))?.read_to_string(&mut str)?;
+        let weights: Weights<T> = serde_json::from_str(&str)?;
+        self.load_named_weights(&weights, strict)
+    }
+
+    //writes an ONNX model of the dense, conv and pool layers, in the network's precision
+    pub fn export_onnx(&self, path: &str) {
+        self.try_export_onnx(path).unwrap()
+    }
+
+    pub fn try_export_onnx(&self, path: &str) -> Result<(), SproutError> {
+        let bytes = onnx::export(self)?;
+        File::create(path)?.write_all(&bytes)?;
+        Ok(())
+    }
+
+    pub fn from_onnx(path: &str) -> Self {
+        Self::try_from_onnx(path).unwrap()
+    }
+
+    pub fn try_from_onnx(path: &str) -> Result<Self, SproutError> {
+        onnx::import(&std::fs::read(path)?)
+    }
+
+    //copies the network into another precision, e.g. to shrink a trained f64 model to f32
+    pub fn convert<U: Float>(&self) -> Network<U> {
+        self.try_convert().unwrap()
+    }
+
+    //goes through the saved JSON form, so custom layers have to be registered for U as well
+    pub fn try_convert<U: Float>(&self) -> Result<Network<U>, SproutError> {
+        let mut network: Network<U> = serde_json::from_value(serde_json::to_value(self)?)?;
+        network.precision = U::PRECISION;
+        Ok(network)
+    }
+}
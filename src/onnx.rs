@@ -0,0 +1,719 @@
+use std::collections::HashMap;
+
+use crate::{
+    activation::ActivationFunction,
+    conv_layer::ConvLayer,
+    conv_params::{ConvParams, PaddingType},
+    dense_layer::DenseLayer,
+    dense_params::DenseParams,
+    error::SproutError,
+    float::{Float, Precision},
+    layer::Layer,
+    loss_function::LossType,
+    network::Network,
+    pool_layer::PoolLayer,
+    protobuf::{read_fields, Field, Message},
+    shape::Shape,
+};
+
+//ONNX export and import for networks of dense, conv and pool layers
+//dense -> [Flatten] Gemm + activation, conv -> depthwise Conv (group = channels) + activation, pool -> MaxPool
+//ReLU is Sprout's leaky ReLU, exported as LeakyRelu(alpha = 0.01). a plain Relu doesn't leak, so it isn't imported
+
+const IR_VERSION: i64 = 7;
+const OPSET_VERSION: i64 = 13;
+const FLOAT: i64 = 1;
+const DOUBLE: i64 = 11;
+const RELU_LEAK: f32 = 0.01;
+
+pub const SUPPORTED_OPS: [&str; 10] = ["Gemm", "Conv", "MaxPool", "LeakyRelu", "Sigmoid", "Tanh", "Softmax", "Flatten", "Identity", "Dropout"];
+
+pub fn export<T: Float>(network: &Network<T>) -> Result<Vec<u8>, SproutError> {
+    let unsupported: Vec<&str> = network.layers.iter()
+        .map(|layer| layer.type_name())
+        .filter(|name| !matches!(*name, "dense" | "conv" | "pool"))
+        .collect();
+    if !unsupported.is_empty() {
+        return Err(SproutError::InvalidConfig(format!("no ONNX mapping for layer types: {}", unsupported.join(", "))));
+    }
+    let input_shape = match (network.input_shape, network.layers.first()) {
+        (Some(shape), _) => shape,
+        (None, Some(layer)) if layer.type_name() == "dense" => Shape::Flat(layer.downcast_ref::<DenseLayer<T>>().unwrap().params.nodes_in),
+        _ => return Err(SproutError::InvalidConfig("network has no input shape, call build before exporting".to_string())),
+    };
+    let elem_type = match T::PRECISION {
+        Precision::F32 => FLOAT,
+        Precision::F64 => DOUBLE,
+    };
+
+    let mut graph = Message::new();
+    graph.string(2, "sprout");
+    let mut initializers = vec![];
+    let mut shape = input_shape;
+    let mut current = "input".to_string();
+    for (i, layer) in network.layers.iter().enumerate() {
+        let next_shape = layer.output_shape(shape).map_err(|message| SproutError::ShapeMismatch(format!("layer {}: {}", i, message)))?;
+        let output = if i + 1 == network.layers.len() { "output".to_string() } else { format!("layer{}", i) };
+        let linear = format!("layer{}_linear", i);
+        if let Some(dense) = layer.downcast_ref::<DenseLayer<T>>() {
+            let params = &dense.params;
+            if let Shape::Image(_) = shape {
+                let flat = format!("layer{}_flat", i);
+                graph.message(1, &node("Flatten", &[&current], &flat, vec![int_attribute("axis", 1)]));
+                current = flat;
+            }
+            let (weight, bias) = (format!("layer{}.weight", i), format!("layer{}.bias", i));
+            initializers.push(tensor(&weight, &[params.nodes_in as i64, params.nodes_out as i64], &params.weights, elem_type));
+            initializers.push(tensor(&bias, &[params.nodes_out as i64], &params.biases, elem_type));
+            graph.message(1, &node("Gemm", &[&current, &weight, &bias], &linear, vec![]));
+            graph.message(1, &activation_node(&dense.activation.function, &linear, &output));
+        } else if let Some(conv) = layer.downcast_ref::<ConvLayer<T>>() {
+            let params = &conv.params;
+            let (channels, kernel) = (params.channels() as i64, params.kernel as i64);
+            let (weight, bias) = (format!("layer{}.weight", i), format!("layer{}.bias", i));
+            initializers.push(tensor(&weight, &[channels, 1, kernel, kernel], &params.weights, elem_type));
+            initializers.push(tensor(&bias, &[channels], &vec![params.bias; params.channels()], elem_type));
+            let attributes = vec![
+                ints_attribute("kernel_shape", &[kernel, kernel]),
+                ints_attribute("strides", &[params.stride as i64; 2]),
+                ints_attribute("pads", &[params.padding_size() as i64; 4]),
+                int_attribute("group", channels),
+            ];
+            graph.message(1, &node("Conv", &[&current, &weight, &bias], &linear, attributes));
+            graph.message(1, &activation_node(&conv.activation.function, &linear, &output));
+        } else if let Some(pool) = layer.downcast_ref::<PoolLayer<T>>() {
+            let params = &pool.params;
+            let attributes = vec![
+                ints_attribute("kernel_shape", &[params.kernel as i64; 2]),
+                ints_attribute("strides", &[params.stride as i64; 2]),
+            ];
+            graph.message(1, &node("MaxPool", &[&current], &output, attributes));
+        }
+        current = output;
+        shape = next_shape;
+    }
+    for initializer in &initializers {
+        graph.message(5, initializer);
+    }
+    graph.message(11, &value_info("input", input_shape, elem_type));
+    graph.message(12, &value_info("output", shape, elem_type));
+
+    let mut opset = Message::new();
+    opset.int(2, OPSET_VERSION);
+    let mut model = Message::new();
+    model.int(1, IR_VERSION)
+        .string(2, "sprout")
+        .string(3, env!("CARGO_PKG_VERSION"))
+        .message(7, &graph)
+        .message(8, &opset);
+    Ok(model.bytes)
+}
+
+//builds a network from a chain of supported nodes, training settings start at the Sequential defaults
+pub fn import<T: Float>(bytes: &[u8]) -> Result<Network<T>, SproutError> {
+    let mut graph = None;
+    for (field, value) in read_fields(bytes)? {
+        if field == 7 {
+            graph = Some(Graph::parse(value.as_bytes()?)?);
+        }
+    }
+    let graph = graph.ok_or_else(|| SproutError::Format("ONNX model has no graph".to_string()))?;
+
+    let mut unsupported: Vec<String> = graph.nodes.iter()
+        .filter(|node| !SUPPORTED_OPS.contains(&node.op_type.as_str()) || !matches!(node.domain.as_str(), "" | "ai.onnx"))
+        .map(|node| if node.domain.is_empty() { node.op_type.clone() } else { format!("{}.{}", node.domain, node.op_type) })
+        .collect();
+    unsupported.sort();
+    unsupported.dedup();
+    if !unsupported.is_empty() {
+        return Err(SproutError::Format(format!(
+            "unsupported ONNX operators: {} (supported: {})", unsupported.join(", "), SUPPORTED_OPS.join(", ")
+        )));
+    }
+
+    let input = graph.inputs.iter()
+        .find(|input| !graph.initializers.contains_key(&input.name))
+        .ok_or_else(|| SproutError::Format("ONNX graph has no data input".to_string()))?;
+    let mut layers: Vec<Box<dyn Layer<T>>> = vec![];
+    let mut pending: Option<Pending<T>> = None;
+    let mut current = input.name.clone();
+    for node in &graph.nodes {
+        let context = format!("node '{}' ({})", node.name, node.op_type);
+        import_node(&graph, node, &mut current, &mut pending, &mut layers).map_err(|err| err.context(&context))?;
+    }
+    if let Some(pending) = pending {
+        return Err(pending.missing_activation());
+    }
+    if !graph.outputs.contains(&current) {
+        return Err(SproutError::Format(format!("the last node's output '{}' is not a graph output", current)));
+    }
+
+    let mut network = Network::new(layers, 0.01, 32, LossType::MSE);
+    //dims past the batch dim, left unbuilt when they are symbolic
+    let dims: Option<Vec<usize>> = input.dims.iter().skip(1)
+        .map(|dim| dim.map(|dim| size(dim, "input dim")).transpose())
+        .collect::<Result<_, _>>()?;
+    match dims.as_deref() {
+        Some([nodes]) => { network.build(Shape::Flat(*nodes))?; },
+        Some([channels, rows, cols]) => { network.build(Shape::Image([*channels, *rows, *cols]))?; },
+        _ => {},
+    }
+    Ok(network)
+}
+
+//a Gemm or Conv waiting for the activation that completes the Sprout layer
+enum Pending<T> {
+    Dense(DenseParams<T>),
+    Conv(ConvParams<T>),
+}
+
+impl<T: Float> Pending<T> {
+    fn into_layer(self, function: ActivationFunction) -> Box<dyn Layer<T>> {
+        match self {
+            Pending::Dense(params) =>
+                {
+                    let mut layer = DenseLayer::new([params.nodes_in, params.nodes_out], function);
+                    layer.params = params;
+                    Box::new(layer)
+                },
+            Pending::Conv(params) =>
+                {
+                    let mut layer = ConvLayer::new(params.kernel, params.padding_type.clone(), params.stride, function);
+                    layer.params = params;
+                    Box::new(layer)
+                },
+        }
+    }
+
+    fn missing_activation(&self) -> SproutError {
+        let op = match self {
+            Pending::Dense(_) => "Gemm",
+            Pending::Conv(_) => "Conv",
+        };
+        SproutError::Format(format!("{} has to be followed by Sigmoid, LeakyRelu, Tanh or Softmax, Sprout layers always apply an activation", op))
+    }
+}
+
+fn import_node<T: Float>(graph: &Graph, node: &Node, current: &mut String, pending: &mut Option<Pending<T>>, layers: &mut Vec<Box<dyn Layer<T>>>) -> Result<(), SproutError> {
+    if node.inputs.first() != Some(current) {
+        return Err(SproutError::Format(format!("only chains of nodes are supported, but this node doesn't take the previous output '{}'", current)));
+    }
+    let output = node.outputs.first().ok_or_else(|| SproutError::Format("node has no output".to_string()))?;
+    *current = output.clone();
+
+    let function = match node.op_type.as_str() {
+        "Sigmoid" => Some(ActivationFunction::Sigmoid),
+        "LeakyRelu" =>
+            {
+                let alpha = node.float("alpha", 0.01)?;
+                if (alpha - RELU_LEAK).abs() > 1e-6 {
+                    return Err(SproutError::Format(format!("LeakyRelu alpha {} is not supported, Sprout's ReLU leaks {}", alpha, RELU_LEAK)));
+                }
+                Some(ActivationFunction::ReLU)
+            },
+        "Tanh" => Some(ActivationFunction::TanH),
+        "Softmax" =>
+            {
+                if node.int("axis", -1)? != -1 {
+                    return Err(SproutError::Format("Softmax is only supported over the last axis".to_string()));
+                }
+                Some(ActivationFunction::SoftMax)
+            },
+        _ => None,
+    };
+    if let Some(function) = function {
+        let layer = pending.take()
+            .ok_or_else(|| SproutError::Format("activations are only supported directly after a Gemm or Conv node".to_string()))?
+            .into_layer(function);
+        layers.push(layer);
+        return Ok(());
+    }
+    if let Some(pending) = pending {
+        return Err(pending.missing_activation());
+    }
+
+    match node.op_type.as_str() {
+        //dense layers flatten their inputs and inference has no dropout
+        "Flatten" | "Identity" | "Dropout" => {},
+        "Gemm" => *pending = Some(Pending::Dense(gemm_params(graph, node)?)),
+        "Conv" => *pending = Some(Pending::Conv(conv_params(graph, node)?)),
+        "MaxPool" =>
+            {
+                let kernel = square(&node.ints("kernel_shape", &[])?, "kernel_shape")?;
+                let stride = square(&node.ints("strides", &[1, 1])?, "strides")?;
+                if node.ints("pads", &[0; 4])?.iter().any(|pad| *pad != 0) || !matches!(node.string("auto_pad", "NOTSET")?.as_str(), "NOTSET" | "VALID") {
+                    return Err(SproutError::Format("padded MaxPool is not supported".to_string()));
+                }
+                if node.int("ceil_mode", 0)? != 0 || node.ints("dilations", &[1, 1])?.iter().any(|dilation| *dilation != 1) {
+                    return Err(SproutError::Format("MaxPool with ceil_mode or dilations is not supported".to_string()));
+                }
+                layers.push(Box::new(PoolLayer::new(kernel, stride)));
+            },
+        op => return Err(SproutError::Format(format!("unsupported ONNX operator {}", op))),
+    }
+    Ok(())
+}
+
+fn gemm_params<T: Float>(graph: &Graph, node: &Node) -> Result<DenseParams<T>, SproutError> {
+    if node.int("transA", 0)? != 0 {
+        return Err(SproutError::Format("Gemm with transA is not supported".to_string()));
+    }
+    let weights = graph.initializer(node, 1)?
+        .ok_or_else(|| SproutError::Format("Gemm needs its weights as an initializer".to_string()))?;
+    let [rows, cols] = weights.dims[..] else {
+        return Err(SproutError::Format(format!("Gemm weights have to be 2D but have dims {:?}", weights.dims)));
+    };
+    let (rows, cols) = (size(rows, "Gemm weight dim")?, size(cols, "Gemm weight dim")?);
+    let alpha = node.float("alpha", 1.0)? as f64;
+    let beta = node.float("beta", 1.0)? as f64;
+    let transposed = node.int("transB", 0)? != 0;
+    let (nodes_in, nodes_out) = if transposed { (cols, rows) } else { (rows, cols) };
+
+    let mut params = DenseParams::new(nodes_in, nodes_out);
+    for i in 0..nodes_in {
+        for o in 0..nodes_out {
+            let value = if transposed { weights.values[o * cols + i] } else { weights.values[i * cols + o] };
+            params.weights[i * nodes_out + o] = T::from_f64(alpha * value);
+        }
+    }
+    if let Some(bias) = graph.initializer(node, 2)? {
+        if bias.values.len() != nodes_out && bias.values.len() != 1 {
+            return Err(SproutError::Format(format!("Gemm bias has {} values for {} outputs", bias.values.len(), nodes_out)));
+        }
+        for (o, value) in params.biases.iter_mut().enumerate() {
+            *value = T::from_f64(beta * bias.values[o % bias.values.len()]);
+        }
+    }
+    Ok(params)
+}
+
+fn conv_params<T: Float>(graph: &Graph, node: &Node) -> Result<ConvParams<T>, SproutError> {
+    let weights = graph.initializer(node, 1)?
+        .ok_or_else(|| SproutError::Format("Conv needs its weights as an initializer".to_string()))?;
+    let [channels, 1, rows, cols] = weights.dims[..] else {
+        return Err(SproutError::Format(format!(
+            "only depthwise Conv (weights of dims [channels, 1, k, k]) is supported, got dims {:?}", weights.dims
+        )));
+    };
+    size(channels, "Conv channel count")?;
+    if node.int("group", 1)? != channels {
+        return Err(SproutError::Format(format!("depthwise Conv needs group = {} (the channel count)", channels)));
+    }
+    let kernel = square(&[rows, cols], "kernel")?;
+    let stride = square(&node.ints("strides", &[1, 1])?, "strides")?;
+    if node.ints("dilations", &[1, 1])?.iter().any(|dilation| *dilation != 1) {
+        return Err(SproutError::Format("dilated Conv is not supported".to_string()));
+    }
+    let padding = match node.string("auto_pad", "NOTSET")?.as_str() {
+        "NOTSET" =>
+            {
+                let pads = node.ints("pads", &[0; 4])?;
+                if pads.len() != 4 || pads.iter().any(|pad| *pad != pads[0]) {
+                    return Err(SproutError::Format(format!("Conv pads {:?} are not the same on every side", pads)));
+                }
+                if pads[0] < 0 {
+                    return Err(SproutError::Format(format!("Conv pads {:?} are negative", pads)));
+                }
+                pads[0] as usize
+            },
+        "VALID" => 0,
+        "SAME_UPPER" | "SAME_LOWER" if stride == 1 && kernel % 2 == 1 => (kernel - 1) / 2,
+        auto_pad => return Err(SproutError::Format(format!("Conv auto_pad {} is not supported for a {} kernel with stride {}", auto_pad, kernel, stride))),
+    };
+    let padding_type = [PaddingType::Valid, PaddingType::Same, PaddingType::Full].into_iter()
+        .find(|padding_type| ConvParams::<T>::new(kernel, padding_type.clone(), stride).padding_size() == padding)
+        .ok_or_else(|| SproutError::Format(format!("padding {} for a {} kernel is neither valid, same nor full", padding, kernel)))?;
+
+    let mut params = ConvParams::new(kernel, padding_type, stride);
+    params.weights = weights.values.iter().map(|value| T::from_f64(*value)).collect();
+    if let Some(bias) = graph.initializer(node, 2)? {
+        if bias.values.iter().any(|value| *value != bias.values[0]) {
+            return Err(SproutError::Format("Sprout conv layers share one bias across channels, but the Conv biases differ".to_string()));
+        }
+        params.bias = T::from_f64(bias.values.first().copied().unwrap_or(0.0));
+    }
+    Ok(params)
+}
+
+//sizes are i64 in ONNX, a negative or zero one would wrap around to a huge usize
+fn size(value: i64, what: &str) -> Result<usize, SproutError> {
+    match value {
+        value if value > 0 => Ok(value as usize),
+        value => Err(SproutError::Format(format!("{} {} has to be positive", what, value))),
+    }
+}
+
+fn square(values: &[i64], what: &str) -> Result<usize, SproutError> {
+    match values {
+        [a, b] if a == b && *a > 0 => Ok(*a as usize),
+        _ => Err(SproutError::Format(format!("{} {:?} has to be square", what, values))),
+    }
+}
+
+fn tensor<T: Float>(name: &str, dims: &[i64], values: &[T], elem_type: i64) -> Message {
+    let mut raw = Vec::with_capacity(values.len() * 8);
+    for value in values {
+        match T::PRECISION {
+            Precision::F32 => raw.extend_from_slice(&(value.as_f64() as f32).to_le_bytes()),
+            Precision::F64 => raw.extend_from_slice(&value.as_f64().to_le_bytes()),
+        }
+    }
+    let mut tensor = Message::new();
+    tensor.ints(1, dims).int(2, elem_type).string(8, name).bytes(9, &raw);
+    tensor
+}
+
+//the batch dim is left symbolic
+fn value_info(name: &str, shape: Shape, elem_type: i64) -> Message {
+    let dims = match shape {
+        Shape::Flat(nodes) => vec![nodes],
+        Shape::Image(dims) => dims.to_vec(),
+    };
+    let mut tensor_shape = Message::new();
+    let mut batch = Message::new();
+    batch.string(2, "batch");
+    tensor_shape.message(1, &batch);
+    for dim in dims {
+        let mut value = Message::new();
+        value.int(1, dim as i64);
+        tensor_shape.message(1, &value);
+    }
+    let mut tensor_type = Message::new();
+    tensor_type.int(1, elem_type).message(2, &tensor_shape);
+    let mut type_proto = Message::new();
+    type_proto.message(1, &tensor_type);
+    let mut info = Message::new();
+    info.string(1, name).message(2, &type_proto);
+    info
+}
+
+fn node(op_type: &str, inputs: &[&str], output: &str, attributes: Vec<Message>) -> Message {
+    let mut node = Message::new();
+    for input in inputs {
+        node.string(1, input);
+    }
+    node.string(2, output).string(3, output).string(4, op_type);
+    for attribute in &attributes {
+        node.message(5, attribute);
+    }
+    node
+}
+
+fn activation_node(function: &ActivationFunction, input: &str, output: &str) -> Message {
+    match function {
+        ActivationFunction::Sigmoid => node("Sigmoid", &[input], output, vec![]),
+        ActivationFunction::ReLU =>
+            {
+                let mut alpha = Message::new();
+                alpha.string(1, "alpha").float(2, RELU_LEAK).int(20, 1);
+                node("LeakyRelu", &[input], output, vec![alpha])
+            },
+        ActivationFunction::TanH => node("Tanh", &[input], output, vec![]),
+        //conv layers apply softmax along each image row, which is the last axis as well
+        ActivationFunction::SoftMax => node("Softmax", &[input], output, vec![int_attribute("axis", -1)]),
+    }
+}
+
+fn int_attribute(name: &str, value: i64) -> Message {
+    let mut attribute = Message::new();
+    attribute.string(1, name).int(3, value).int(20, 2);
+    attribute
+}
+
+fn ints_attribute(name: &str, values: &[i64]) -> Message {
+    let mut attribute = Message::new();
+    attribute.string(1, name).ints(8, values).int(20, 7);
+    attribute
+}
+
+struct Graph {
+    nodes: Vec<Node>,
+    initializers: HashMap<String, TensorData>,
+    inputs: Vec<ValueInfo>,
+    outputs: Vec<String>,
+}
+
+struct Node {
+    name: String,
+    op_type: String,
+    domain: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    attributes: HashMap<String, Attribute>,
+}
+
+#[derive(Default)]
+struct Attribute {
+    float: Option<f32>,
+    int: Option<i64>,
+    string: Option<String>,
+    ints: Vec<i64>,
+}
+
+struct TensorData {
+    dims: Vec<i64>,
+    values: Vec<f64>,
+}
+
+struct ValueInfo {
+    name: String,
+    dims: Vec<Option<i64>>, //None for symbolic dims
+}
+
+impl Graph {
+    fn parse(bytes: &[u8]) -> Result<Self, SproutError> {
+        let mut graph = Graph { nodes: vec![], initializers: HashMap::new(), inputs: vec![], outputs: vec![] };
+        for (field, value) in read_fields(bytes)? {
+            match field {
+                1 => graph.nodes.push(Node::parse(value.as_bytes()?)?),
+                5 =>
+                    {
+                        let (name, tensor) = TensorData::parse(value.as_bytes()?)?;
+                        graph.initializers.insert(name, tensor);
+                    },
+                11 => graph.inputs.push(ValueInfo::parse(value.as_bytes()?)?),
+                12 => graph.outputs.push(ValueInfo::parse(value.as_bytes()?)?.name),
+                _ => {},
+            }
+        }
+        Ok(graph)
+    }
+
+    //the node's i-th input, None when it's left out
+    fn initializer(&self, node: &Node, i: usize) -> Result<Option<&TensorData>, SproutError> {
+        match node.inputs.get(i).filter(|name| !name.is_empty()) {
+            None => Ok(None),
+            Some(name) => self.initializers.get(name).map(Some)
+                .ok_or_else(|| SproutError::Format(format!("input '{}' has to be an initializer", name))),
+        }
+    }
+}
+
+impl Node {
+    fn parse(bytes: &[u8]) -> Result<Self, SproutError> {
+        let mut node = Node { name: String::new(), op_type: String::new(), domain: String::new(), inputs: vec![], outputs: vec![], attributes: HashMap::new() };
+        for (field, value) in read_fields(bytes)? {
+            match field {
+                1 => node.inputs.push(value.as_string()?),
+                2 => node.outputs.push(value.as_string()?),
+                3 => node.name = value.as_string()?,
+                4 => node.op_type = value.as_string()?,
+                5 =>
+                    {
+                        let (name, attribute) = Attribute::parse(value.as_bytes()?)?;
+                        node.attributes.insert(name, attribute);
+                    },
+                7 => node.domain = value.as_string()?,
+                _ => {},
+            }
+        }
+        Ok(node)
+    }
+
+    fn attribute<V>(&self, name: &str, default: V, get: impl Fn(&Attribute) -> Option<V>) -> Result<V, SproutError> {
+        match self.attributes.get(name) {
+            None => Ok(default),
+            Some(attribute) => get(attribute).ok_or_else(|| SproutError::Format(format!("attribute {} has the wrong type", name))),
+        }
+    }
+
+    fn float(&self, name: &str, default: f32) -> Result<f32, SproutError> {
+        self.attribute(name, default, |attribute| attribute.float)
+    }
+
+    fn int(&self, name: &str, default: i64) -> Result<i64, SproutError> {
+        self.attribute(name, default, |attribute| attribute.int)
+    }
+
+    fn ints(&self, name: &str, default: &[i64]) -> Result<Vec<i64>, SproutError> {
+        self.attribute(name, default.to_vec(), |attribute| Some(attribute.ints.clone()))
+    }
+
+    fn string(&self, name: &str, default: &str) -> Result<String, SproutError> {
+        self.attribute(name, default.to_string(), |attribute| attribute.string.clone())
+    }
+}
+
+impl Attribute {
+    fn parse(bytes: &[u8]) -> Result<(String, Self), SproutError> {
+        let mut name = String::new();
+        let mut attribute = Attribute::default();
+        for (field, value) in read_fields(bytes)? {
+            match field {
+                1 => name = value.as_string()?,
+                2 => attribute.float = Some(value.as_f32()?),
+                3 => attribute.int = Some(value.as_int()?),
+                4 => attribute.string = Some(value.as_string()?),
+                8 => value.push_ints(&mut attribute.ints)?,
+                _ => {},
+            }
+        }
+        Ok((name, attribute))
+    }
+}
+
+impl TensorData {
+    fn parse(bytes: &[u8]) -> Result<(String, Self), SproutError> {
+        let mut name = String::new();
+        let mut dims = vec![];
+        let mut data_type = 0;
+        let mut values = vec![];
+        let mut raw = None;
+        for (field, value) in read_fields(bytes)? {
+            match field {
+                1 => value.push_ints(&mut dims)?,
+                2 => data_type = value.as_int()?,
+                4 => value.push_f32s(&mut values)?,
+                8 => name = value.as_string()?,
+                9 => raw = Some(value.as_bytes()?),
+                10 => value.push_f64s(&mut values)?,
+                14 if value.as_int()? != 0 => return Err(SproutError::Format("tensors with external data are not supported".to_string())),
+                _ => {},
+            }
+        }
+        if let Some(raw) = raw {
+            let raw = Field::Bytes(raw);
+            match data_type {
+                FLOAT => raw.push_f32s(&mut values)?,
+                DOUBLE => raw.push_f64s(&mut values)?,
+                _ => {},
+            }
+        }
+        if data_type != FLOAT && data_type != DOUBLE {
+            return Err(SproutError::Format(format!("initializer '{}' has data type {}, only float and double are supported", name, data_type)));
+        }
+        if dims.iter().any(|dim| *dim < 0) {
+            return Err(SproutError::Format(format!("initializer '{}' has negative dims {:?}", name, dims)));
+        }
+        let expected: i64 = dims.iter().product();
+        if values.len() as i64 != expected {
+            return Err(SproutError::Format(format!("initializer '{}' has {} values for dims {:?}", name, values.len(), dims)));
+        }
+        Ok((name, TensorData { dims, values }))
+    }
+}
+
+impl ValueInfo {
+    fn parse(bytes: &[u8]) -> Result<Self, SproutError> {
+        let mut info = ValueInfo { name: String::new(), dims: vec![] };
+        for (field, value) in read_fields(bytes)? {
+            match field {
+                1 => info.name = value.as_string()?,
+                2 => info.dims = tensor_dims(value.as_bytes()?)?,
+                _ => {},
+            }
+        }
+        Ok(info)
+    }
+}
+
+//TypeProto > tensor_type > shape > dims
+fn tensor_dims(type_proto: &[u8]) -> Result<Vec<Option<i64>>, SproutError> {
+    let mut dims = vec![];
+    for (field, tensor_type) in read_fields(type_proto)? {
+        if field != 1 {
+            continue;
+        }
+        for (field, shape) in read_fields(tensor_type.as_bytes()?)? {
+            if field != 2 {
+                continue;
+            }
+            for (field, dim) in read_fields(shape.as_bytes()?)? {
+                if field != 1 {
+                    continue;
+                }
+                let mut value = None;
+                for (field, dim_field) in read_fields(dim.as_bytes()?)? {
+                    if field == 1 {
+                        value = Some(dim_field.as_int()?);
+                    }
+                }
+                dims.push(value);
+            }
+        }
+    }
+    Ok(dims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a graph from input to output with the given input dims, batch dim first, and the nodes and initializers layers adds
+    fn model(input_dims: &[i64], layers: impl FnOnce(&mut Message, &mut Vec<Message>)) -> Vec<u8> {
+        let mut graph = Message::new();
+        let mut initializers = vec![];
+        layers(&mut graph, &mut initializers);
+        for initializer in &initializers {
+            graph.message(5, initializer);
+        }
+        let mut shape = Message::new();
+        for dim in input_dims {
+            let mut value = Message::new();
+            value.int(1, *dim);
+            shape.message(1, &value);
+        }
+        let mut tensor_type = Message::new();
+        tensor_type.int(1, DOUBLE).message(2, &shape);
+        let mut type_proto = Message::new();
+        type_proto.message(1, &tensor_type);
+        let mut input = Message::new();
+        input.string(1, "input").message(2, &type_proto);
+        let mut output = Message::new();
+        output.string(1, "output");
+        graph.message(11, &input).message(12, &output);
+        let mut model = Message::new();
+        model.message(7, &graph);
+        model.bytes
+    }
+
+    fn gemm(weight_dims: &[i64], activation: Message) -> impl FnOnce(&mut Message, &mut Vec<Message>) {
+        let weight_dims = weight_dims.to_vec();
+        move |graph, initializers| {
+            initializers.push(tensor("w", &weight_dims, &[0.5, -1.0, 0.25, 2.0, -0.5, 1.0], DOUBLE));
+            graph.message(1, &node("Gemm", &["input", "w"], "linear", vec![]));
+            graph.message(1, &activation);
+        }
+    }
+
+    fn format_error(bytes: &[u8], expected: &str) {
+        match import::<f64>(bytes) {
+            Err(SproutError::Format(message)) => assert!(message.contains(expected), "{:?} doesn't mention {:?}", message, expected),
+            Err(err) => panic!("expected a format error, got {}", err),
+            Ok(_) => panic!("expected a format error mentioning {:?}", expected),
+        }
+    }
+
+    #[test]
+    fn plain_relu_is_not_imported() {
+        format_error(&model(&[1, 2], gemm(&[2, 3], node("Relu", &["linear"], "output", vec![]))), "unsupported ONNX operators: Relu");
+        let leaky = activation_node(&ActivationFunction::ReLU, "linear", "output");
+        let network = import::<f64>(&model(&[1, 2], gemm(&[2, 3], leaky))).unwrap();
+        assert_eq!(network.input_shape, Some(Shape::Flat(2)));
+        //the leak is kept for negative inputs
+        assert_eq!(network.predict(&crate::tensor::Tensor::Flat(vec![1.0, 0.0])).into_flat(), [0.5, -0.01, 0.25]);
+    }
+
+    #[test]
+    fn negative_and_zero_sizes_are_rejected() {
+        let sigmoid = || node("Sigmoid", &["linear"], "output", vec![]);
+        format_error(&model(&[1, -1], gemm(&[2, 3], sigmoid())), "input dim -1 has to be positive");
+        format_error(&model(&[1, 0], gemm(&[2, 3], sigmoid())), "input dim 0 has to be positive");
+        format_error(&model(&[1, 2], gemm(&[-2, -3], sigmoid())), "negative dims [-2, -3]");
+
+        let conv = |weight_dims: &[i64], values: &[f64], pads: &[i64]| {
+            let (weight_dims, values, pads) = (weight_dims.to_vec(), values.to_vec(), pads.to_vec());
+            move |graph: &mut Message, initializers: &mut Vec<Message>| {
+                initializers.push(tensor("w", &weight_dims, &values, DOUBLE));
+                let attributes = vec![ints_attribute("pads", &pads), int_attribute("group", weight_dims[0])];
+                graph.message(1, &node("Conv", &["input", "w"], "linear", attributes));
+                graph.message(1, &node("Tanh", &["linear"], "output", vec![]));
+            }
+        };
+        format_error(&model(&[1, 1, 4, 4], conv(&[1, 1, 3, 3], &[0.1; 9], &[-1; 4])), "pads [-1, -1, -1, -1] are negative");
+        format_error(&model(&[1, 1, 4, 4], conv(&[1, 1, 0, 0], &[], &[0; 4])), "kernel [0, 0] has to be square");
+        format_error(&model(&[1, 0, 4, 4], conv(&[0, 1, 3, 3], &[], &[0; 4])), "channel count 0");
+        //valid pads get through to building, where a network ending in an image is turned down
+        assert!(matches!(import::<f64>(&model(&[1, 1, 4, 4], conv(&[1, 1, 3, 3], &[0.1; 9], &[1; 4]))), Err(SproutError::ShapeMismatch(_))));
+    }
+}
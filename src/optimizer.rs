@@ -0,0 +1,138 @@
+use serde_derive::{Serialize, Deserialize};
+
+use crate::{error::SproutError, float::Float};
+
+//how a layer turns its summed batch gradients into a parameter update, the step size is the network's learning rate
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub enum Optimizer {
+    #[default]
+    SGD,
+    Momentum { momentum: f64 },
+    Adam { beta1: f64, beta2: f64, epsilon: f64 },
+}
+
+//learning rate for an epoch, scaling the network's learning rate
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub enum Schedule {
+    #[default]
+    Constant,
+    Step { every: usize, gamma: f64 }, //multiplied by gamma every `every` epochs
+    Exponential { gamma: f64 }, //multiplied by gamma every epoch
+    Cosine { epochs: usize, min_rate: f64 }, //annealed down to min_rate over `epochs` epochs, then held there
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<(), SproutError> {
+        let valid = match self {
+            Schedule::Constant => true,
+            Schedule::Step { every, gamma } => *every > 0 && *gamma > 0.0,
+            Schedule::Exponential { gamma } => *gamma > 0.0,
+            Schedule::Cosine { epochs, min_rate } => *epochs > 0 && *min_rate >= 0.0,
+        };
+        if !valid {
+            return Err(SproutError::InvalidConfig(format!("{:?} needs a positive period and decay", self)));
+        }
+        Ok(())
+    }
+
+    //epoch counts from 0
+    pub fn learning_rate(&self, base: f64, epoch: usize) -> f64 {
+        match self {
+            Schedule::Constant => base,
+            Schedule::Step { every, gamma } => base * gamma.powi((epoch / every) as i32),
+            Schedule::Exponential { gamma } => base * gamma.powi(epoch as i32),
+            Schedule::Cosine { epochs, min_rate } =>
+                {
+                    let progress = epoch.min(*epochs) as f64 / *epochs as f64;
+                    min_rate + (base - min_rate) * (1.0 + (std::f64::consts::PI * progress).cos()) / 2.0
+                },
+        }
+    }
+}
+
+//per parameter tensor history kept by Momentum and Adam, empty for SGD
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizerState<T = f64> {
+    pub step: usize,
+    pub velocity: Vec<T>, //momentum / Adam first moment
+    pub square: Vec<T>, //Adam second moment
+}
+
+impl<T> Default for OptimizerState<T> {
+    fn default() -> Self {
+        OptimizerState {
+            step: 0,
+            velocity: vec![],
+            square: vec![],
+        }
+    }
+}
+
+impl Optimizer {
+    pub fn momentum() -> Self {
+        Optimizer::Momentum { momentum: 0.9 }
+    }
+
+    pub fn adam() -> Self {
+        Optimizer::Adam { beta1: 0.9, beta2: 0.999, epsilon: 1e-8 }
+    }
+
+    pub fn validate(&self) -> Result<(), SproutError> {
+        let valid = match self {
+            Optimizer::SGD => true,
+            Optimizer::Momentum { momentum } => (0.0..1.0).contains(momentum),
+            Optimizer::Adam { beta1, beta2, epsilon } => (0.0..1.0).contains(beta1) && (0.0..1.0).contains(beta2) && *epsilon > 0.0,
+        };
+        if !valid {
+            return Err(SproutError::InvalidConfig(format!("{:?} needs momentum/betas in [0, 1) and a positive epsilon", self)));
+        }
+        Ok(())
+    }
+
+    //gradients are summed over the batch and averaged here
+    pub fn update<T: Float>(&self, params: &mut [T], gradients: &[T], state: &mut OptimizerState<T>, learning_rate: f64, batch_size: usize) {
+        match self {
+            Optimizer::SGD =>
+                {
+                    let step = T::from_f64(learning_rate / batch_size as f64);
+                    for (param, grad) in params.iter_mut().zip(gradients) {
+                        *param -= step * *grad;
+                    }
+                },
+            Optimizer::Momentum { momentum } =>
+                {
+                    if state.velocity.len() != params.len() {
+                        state.velocity = vec![T::zero(); params.len()];
+                    }
+                    let momentum = T::from_f64(*momentum);
+                    let scale = T::from_f64(1.0 / batch_size as f64);
+                    let learning_rate = T::from_f64(learning_rate);
+                    for ((param, grad), velocity) in params.iter_mut().zip(gradients).zip(state.velocity.iter_mut()) {
+                        *velocity = momentum * *velocity + *grad * scale;
+                        *param -= learning_rate * *velocity;
+                    }
+                },
+            Optimizer::Adam { beta1, beta2, epsilon } =>
+                {
+                    if state.velocity.len() != params.len() || state.square.len() != params.len() {
+                        state.velocity = vec![T::zero(); params.len()];
+                        state.square = vec![T::zero(); params.len()];
+                        state.step = 0;
+                    }
+                    state.step += 1;
+                    let correction1 = 1.0 - beta1.powi(state.step as i32);
+                    let correction2 = 1.0 - beta2.powi(state.step as i32);
+                    let scale = T::from_f64(1.0 / batch_size as f64);
+                    let (beta1, beta2) = (T::from_f64(*beta1), T::from_f64(*beta2));
+                    let (correction1, correction2) = (T::from_f64(correction1), T::from_f64(correction2));
+                    let (learning_rate, epsilon) = (T::from_f64(learning_rate), T::from_f64(*epsilon));
+                    for (((param, grad), velocity), square) in params.iter_mut().zip(gradients).zip(state.velocity.iter_mut()).zip(state.square.iter_mut()) {
+                        let grad = *grad * scale;
+                        *velocity = beta1 * *velocity + (T::one() - beta1) * grad;
+                        *square = beta2 * *square + (T::one() - beta2) * grad * grad;
+                        *param -= learning_rate * (*velocity / correction1) / ((*square / correction2).sqrt() + epsilon);
+                    }
+                },
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use rayon::prelude::*;
+use serde_derive::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::{
+    conv_layer::{validate_window, window_output_shape},
+    conv_params::{ConvParams, PaddingType},
+    error::SproutError,
+    float::Float,
+    layer::{Layer, LayerCache},
+    network::Image,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+type PoolCells<T> = Vec<Vec<Vec<(T, [usize; 2])>>>; //channel > rows > cols of (max, [row, col])
+
+//max pooling, only the kernel and stride of params are used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolLayer<T = f64> {
+    pub params: ConvParams<T>,
+}
+
+//what backward needs from a forward batch
+pub struct PoolCache<T = f64> {
+    pub inputs: Vec<Image<T>>,
+    pub outputs: Vec<Image<T>>,
+}
+
+impl<T: Float> PoolLayer<T> {
+    pub fn new(kernel: usize, stride: usize) -> Self {
+        PoolLayer {
+            params: ConvParams::new(kernel, PaddingType::Valid, stride),
+        }
+    }
+
+    pub fn predict_image(&self, inputs: &[Vec<Vec<T>>]) -> Image<T> {
+        self.pool_sample(inputs).into_iter()
+            .map(|channel| channel.into_iter().map(|row| row.into_iter().map(|(max, _)| max).collect()).collect())
+            .collect()
+    }
+
+    //max value and its [row, col] position in the input for every output cell
+    fn pool_sample(&self, img: &[Vec<Vec<T>>]) -> PoolCells<T> {
+        let params = &self.params;
+        let output_dims = params.get_output_dims(img);
+
+        img.iter().map(|channel| { //each channel
+            (0..output_dims[1]).map(|j| { //each output img row
+                (0..output_dims[0]).map(|k| { //each output img column
+                    let mut max = T::neg_infinity();
+                    let mut max_indx = [j * params.stride, k * params.stride];
+                    for kern_row in 0..params.kernel { //Kernel rows
+                        let row_i = j * params.stride + kern_row;
+                        for kern_col in 0..params.kernel { //Kernel Columns
+                            let col_i = k * params.stride + kern_col;
+                            let val = channel[row_i][col_i];
+                            if val > max {
+                                max = val;
+                                max_indx = [row_i, col_i];
+                            }
+                        }
+                    }
+                    (max, max_indx)
+                }).collect()
+            }).collect()
+        }).collect()
+    }
+}
+
+impl<T: Float> Layer<T> for PoolLayer<T> {
+    fn type_name(&self) -> &'static str {
+        "pool"
+    }
+
+    fn output_shape(&self, input: Shape) -> Result<Shape, String> {
+        window_output_shape("pooling", &self.params, input)
+    }
+
+    fn forward(&self, inputs: Vec<Tensor<T>>) -> (Vec<Tensor<T>>, LayerCache) {
+        let inputs: Vec<Image<T>> = inputs.into_iter().map(Tensor::into_image).collect();
+        let outputs: Vec<Image<T>> = inputs.par_iter().map(|sample| self.predict_image(sample)).collect();
+        let tensors = outputs.iter().cloned().map(Tensor::Image).collect();
+        (tensors, Box::new(PoolCache { inputs, outputs }))
+    }
+
+    fn predict(&self, input: &Tensor<T>) -> Tensor<T> {
+        Tensor::Image(self.predict_image(input.as_image()))
+    }
+
+    //routes each delta back to the input cell that held the max
+    fn backward(&self, cache: &LayerCache, errors: Vec<Tensor<T>>) -> (Vec<Tensor<T>>, Vec<Vec<T>>) {
+        let cache: &PoolCache<T> = cache.downcast_ref().expect("pooling layer got another layer's cache");
+        let next_deltas = errors.into_par_iter().zip(&cache.inputs).map(|(delta_output, inputs)| {
+            let mut next_delta = vec![vec![vec![T::zero(); inputs[0][0].len()]; inputs[0].len()]; inputs.len()];
+            let pooled = self.pool_sample(inputs);
+
+            for ((delta_channel, pooled_channel), next_channel) in delta_output.as_image().iter().zip(&pooled).zip(next_delta.iter_mut()) {
+                for (delta_row, pooled_row) in delta_channel.iter().zip(pooled_channel) {
+                    for (delta, (_, max_indx)) in delta_row.iter().zip(pooled_row) {
+                        next_channel[max_indx[0]][max_indx[1]] += *delta;
+                    }
+                }
+            }
+            Tensor::Image(next_delta)
+        }).collect();
+        (next_deltas, vec![])
+    }
+
+    fn to_json(&self) -> Result<Value, SproutError> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn validate(&self) -> Result<(), SproutError> {
+        validate_window(&self.params)
+    }
+}
@@ -0,0 +1,291 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{error::SproutError, float::Float};
+
+//fit/transform preprocessing of flat inputs. fitted steps attached to a network (see Network::fit_preprocessing)
+//are saved with the model and applied to every input it trains on or predicts, so training and deployment can't drift apart.
+//statistics are kept as f64 whatever the network's precision
+
+//scales each feature to zero mean and unit variance
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StandardScaler {
+    pub mean: Vec<f64>,
+    pub std_dev: Vec<f64>, //population standard deviation, 1 for constant features so they only get centered
+}
+
+impl StandardScaler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fit<T: Float>(&mut self, inputs: &[Vec<T>]) -> Result<(), SproutError> {
+        let width = check_fit_data(inputs)?;
+        let count = inputs.len() as f64;
+        self.mean = (0..width).map(|j| inputs.iter().map(|input| input[j].as_f64()).sum::<f64>() / count).collect();
+        self.std_dev = (0..width).map(|j| {
+            let variance = inputs.iter().map(|input| (input[j].as_f64() - self.mean[j]).powi(2)).sum::<f64>() / count;
+            match variance.sqrt() {
+                std_dev if std_dev > 0.0 => std_dev,
+                _ => 1.0,
+            }
+        }).collect();
+        Ok(())
+    }
+
+    pub fn transform<T: Float>(&self, input: &[T]) -> Result<Vec<T>, SproutError> {
+        check_width("standard scaler", self.mean.len(), input.len())?;
+        Ok(input.iter().zip(self.mean.iter().zip(&self.std_dev))
+            .map(|(x, (mean, std_dev))| T::from_f64((x.as_f64() - mean) / std_dev))
+            .collect())
+    }
+
+    pub fn inverse_transform<T: Float>(&self, input: &[T]) -> Result<Vec<T>, SproutError> {
+        check_width("standard scaler", self.mean.len(), input.len())?;
+        Ok(input.iter().zip(self.mean.iter().zip(&self.std_dev))
+            .map(|(x, (mean, std_dev))| T::from_f64(x.as_f64() * std_dev + mean))
+            .collect())
+    }
+}
+
+//scales each feature linearly so the smallest value seen in fit maps to range[0] and the largest to range[1]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinMaxScaler {
+    pub range: [f64; 2],
+    pub min: Vec<f64>,
+    pub max: Vec<f64>,
+}
+
+impl Default for MinMaxScaler {
+    fn default() -> Self {
+        Self::new(0.0, 1.0)
+    }
+}
+
+impl MinMaxScaler {
+    pub fn new(low: f64, high: f64) -> Self {
+        MinMaxScaler { range: [low, high], min: vec![], max: vec![] }
+    }
+
+    pub fn fit<T: Float>(&mut self, inputs: &[Vec<T>]) -> Result<(), SproutError> {
+        let [low, high] = self.range;
+        if !(low.is_finite() && high.is_finite() && low < high) {
+            return Err(SproutError::InvalidConfig(format!("min-max range must go from low to high, got {:?}", self.range)));
+        }
+        let width = check_fit_data(inputs)?;
+        let column = |j: usize| inputs.iter().map(move |input| input[j].as_f64());
+        self.min = (0..width).map(|j| column(j).fold(f64::INFINITY, f64::min)).collect();
+        self.max = (0..width).map(|j| column(j).fold(f64::NEG_INFINITY, f64::max)).collect();
+        Ok(())
+    }
+
+    //a feature that was constant in fit maps to range[0]
+    pub fn transform<T: Float>(&self, input: &[T]) -> Result<Vec<T>, SproutError> {
+        check_width("min-max scaler", self.min.len(), input.len())?;
+        let [low, high] = self.range;
+        Ok(input.iter().zip(self.min.iter().zip(&self.max))
+            .map(|(x, (min, max))| T::from_f64(low + (x.as_f64() - min) / span(*min, *max) * (high - low)))
+            .collect())
+    }
+
+    pub fn inverse_transform<T: Float>(&self, input: &[T]) -> Result<Vec<T>, SproutError> {
+        check_width("min-max scaler", self.min.len(), input.len())?;
+        let [low, high] = self.range;
+        Ok(input.iter().zip(self.min.iter().zip(&self.max))
+            .map(|(x, (min, max))| T::from_f64(min + (x.as_f64() - low) / (high - low) * span(*min, *max)))
+            .collect())
+    }
+}
+
+//a constant feature is only shifted
+fn span(min: f64, max: f64) -> f64 {
+    match max - min {
+        span if span > 0.0 => span,
+        _ => 1.0,
+    }
+}
+
+//replaces each of the given columns, holding category codes, with one column per category seen in fit.
+//a value that wasn't seen in fit comes out as all zeros
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OneHotEncoder {
+    pub columns: Vec<usize>, //indices into the input, in increasing order
+    pub categories: Vec<Vec<f64>>, //column > its categories in increasing order
+    pub width: usize, //input length seen in fit
+}
+
+impl OneHotEncoder {
+    pub fn new(columns: impl IntoIterator<Item = usize>) -> Self {
+        let mut columns: Vec<usize> = columns.into_iter().collect();
+        columns.sort();
+        columns.dedup();
+        OneHotEncoder { columns, categories: vec![], width: 0 }
+    }
+
+    pub fn fit<T: Float>(&mut self, inputs: &[Vec<T>]) -> Result<(), SproutError> {
+        let width = check_fit_data(inputs)?;
+        if let Some(column) = self.columns.iter().find(|column| **column >= width) {
+            return Err(SproutError::ShapeMismatch(format!("one-hot column {} is out of range for inputs of {} values", column, width)));
+        }
+        self.categories = self.columns.iter().map(|&j| {
+            let mut categories: Vec<f64> = inputs.iter().map(|input| input[j].as_f64()).collect();
+            categories.sort_by(f64::total_cmp);
+            categories.dedup();
+            categories
+        }).collect();
+        self.width = width;
+        Ok(())
+    }
+
+    pub fn output_len(&self) -> usize {
+        self.width - self.columns.len() + self.categories.iter().map(Vec::len).sum::<usize>()
+    }
+
+    pub fn transform<T: Float>(&self, input: &[T]) -> Result<Vec<T>, SproutError> {
+        check_width("one-hot encoder", self.width, input.len())?;
+        let mut output = Vec::with_capacity(self.output_len());
+        let mut encoded = self.columns.iter().zip(&self.categories).peekable();
+        for (j, x) in input.iter().enumerate() {
+            match encoded.next_if(|(column, _)| **column == j) {
+                Some((_, categories)) => {
+                    let hot = categories.iter().position(|category| *category == x.as_f64());
+                    output.extend((0..categories.len()).map(|k| if Some(k) == hot { T::one() } else { T::zero() }));
+                },
+                None => output.push(*x),
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Preprocessor {
+    Standard(StandardScaler),
+    MinMax(MinMaxScaler),
+    OneHot(OneHotEncoder),
+}
+
+impl Preprocessor {
+    pub fn fit<T: Float>(&mut self, inputs: &[Vec<T>]) -> Result<(), SproutError> {
+        match self {
+            Preprocessor::Standard(scaler) => scaler.fit(inputs),
+            Preprocessor::MinMax(scaler) => scaler.fit(inputs),
+            Preprocessor::OneHot(encoder) => encoder.fit(inputs),
+        }
+    }
+
+    pub fn transform<T: Float>(&self, input: &[T]) -> Result<Vec<T>, SproutError> {
+        match self {
+            Preprocessor::Standard(scaler) => scaler.transform(input),
+            Preprocessor::MinMax(scaler) => scaler.transform(input),
+            Preprocessor::OneHot(encoder) => encoder.transform(input),
+        }
+    }
+}
+
+impl From<StandardScaler> for Preprocessor {
+    fn from(scaler: StandardScaler) -> Self {
+        Preprocessor::Standard(scaler)
+    }
+}
+
+impl From<MinMaxScaler> for Preprocessor {
+    fn from(scaler: MinMaxScaler) -> Self {
+        Preprocessor::MinMax(scaler)
+    }
+}
+
+impl From<OneHotEncoder> for Preprocessor {
+    fn from(encoder: OneHotEncoder) -> Self {
+        Preprocessor::OneHot(encoder)
+    }
+}
+
+//fits every step on what the steps before it output, returns the fully transformed inputs
+pub fn fit_all<T: Float>(steps: &mut [Preprocessor], inputs: &[Vec<T>]) -> Result<Vec<Vec<T>>, SproutError> {
+    let mut current = inputs.to_vec();
+    for (i, step) in steps.iter_mut().enumerate() {
+        let context = format!("preprocessing step {}", i);
+        step.fit(&current).map_err(|err| err.context(&context))?;
+        current = current.iter().map(|input| step.transform(input)).collect::<Result<_, _>>().map_err(|err| err.context(&context))?;
+    }
+    Ok(current)
+}
+
+pub fn transform_all<T: Float>(steps: &[Preprocessor], input: &[T]) -> Result<Vec<T>, SproutError> {
+    let mut current = input.to_vec();
+    for (i, step) in steps.iter().enumerate() {
+        current = step.transform(&current).map_err(|err| err.context(&format!("preprocessing step {}", i)))?;
+    }
+    Ok(current)
+}
+
+//maps class names to output indices (sorted by name) and back. a network with one names its predictions, see Network::predict_label
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LabelEncoder {
+    pub classes: Vec<String>,
+}
+
+impl LabelEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fit(&mut self, labels: &[impl AsRef<str>]) -> Result<(), SproutError> {
+        if labels.is_empty() {
+            return Err(SproutError::InvalidConfig("no labels to fit on".to_string()));
+        }
+        let mut classes: Vec<String> = labels.iter().map(|label| label.as_ref().to_string()).collect();
+        classes.sort();
+        classes.dedup();
+        self.classes = classes;
+        Ok(())
+    }
+
+    pub fn encode(&self, label: &str) -> Result<usize, SproutError> {
+        self.classes.binary_search_by(|class| class.as_str().cmp(label))
+            .map_err(|_| SproutError::InvalidConfig(format!("label {:?} was not seen when the encoder was fit", label)))
+    }
+
+    pub fn transform(&self, labels: &[impl AsRef<str>]) -> Result<Vec<usize>, SproutError> {
+        labels.iter().map(|label| self.encode(label.as_ref())).collect()
+    }
+
+    //the training target for a label
+    pub fn one_hot<T: Float>(&self, label: &str) -> Result<Vec<T>, SproutError> {
+        let mut target = vec![T::zero(); self.classes.len()];
+        target[self.encode(label)?] = T::one();
+        Ok(target)
+    }
+
+    pub fn decode(&self, index: usize) -> Option<&str> {
+        self.classes.get(index).map(String::as_str)
+    }
+
+    //the class of the highest output
+    pub fn decode_output<T: Float>(&self, output: &[T]) -> Option<&str> {
+        let index = output.iter().enumerate().max_by(|a, b| a.1.as_f64().total_cmp(&b.1.as_f64()))?.0;
+        self.decode(index)
+    }
+}
+
+//every row has to be the same length and finite
+fn check_fit_data<T: Float>(inputs: &[Vec<T>]) -> Result<usize, SproutError> {
+    let width = inputs.first().ok_or_else(|| SproutError::InvalidConfig("no inputs to fit on".to_string()))?.len();
+    for (i, input) in inputs.iter().enumerate() {
+        if input.len() != width {
+            return Err(SproutError::ShapeMismatch(format!("sample {} has {} values but sample 0 has {}", i, input.len(), width)));
+        }
+        if input.iter().any(|x| !x.is_finite()) {
+            return Err(SproutError::Numerical(format!("sample {} contains NaN or infinite values", i)));
+        }
+    }
+    Ok(width)
+}
+
+fn check_width(name: &str, fitted: usize, width: usize) -> Result<(), SproutError> {
+    match fitted == width {
+        true => Ok(()),
+        false if fitted == 0 => Err(SproutError::InvalidConfig(format!("{} has not been fit", name))),
+        false => Err(SproutError::ShapeMismatch(format!("{} was fit on {} values but got {}", name, fitted, width))),
+    }
+}
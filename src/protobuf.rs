@@ -0,0 +1,239 @@
+use crate::error::SproutError;
+
+//just enough of the protobuf wire format to read and write ONNX models
+
+const VARINT: u32 = 0;
+const FIXED64: u32 = 1;
+const LENGTH_DELIMITED: u32 = 2;
+const FIXED32: u32 = 5;
+
+#[derive(Default)]
+pub struct Message {
+    pub bytes: Vec<u8>,
+}
+
+impl Message {
+    pub fn new() -> Self {
+        Message::default()
+    }
+
+    fn key(&mut self, field: u32, wire_type: u32) {
+        write_varint(&mut self.bytes, ((field << 3) | wire_type) as u64);
+    }
+
+    pub fn int(&mut self, field: u32, value: i64) -> &mut Self {
+        self.key(field, VARINT);
+        write_varint(&mut self.bytes, value as u64);
+        self
+    }
+
+    pub fn float(&mut self, field: u32, value: f32) -> &mut Self {
+        self.key(field, FIXED32);
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn bytes(&mut self, field: u32, value: &[u8]) -> &mut Self {
+        self.key(field, LENGTH_DELIMITED);
+        write_varint(&mut self.bytes, value.len() as u64);
+        self.bytes.extend_from_slice(value);
+        self
+    }
+
+    pub fn string(&mut self, field: u32, value: &str) -> &mut Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    pub fn message(&mut self, field: u32, message: &Message) -> &mut Self {
+        self.bytes(field, &message.bytes)
+    }
+
+    //written unpacked, the way proto2 files like onnx.proto expect repeated scalars
+    pub fn ints(&mut self, field: u32, values: &[i64]) -> &mut Self {
+        for value in values {
+            self.int(field, *value);
+        }
+        self
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Field<'a> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Bytes(&'a [u8]),
+    Fixed32([u8; 4]),
+}
+
+impl<'a> Field<'a> {
+    pub fn as_int(&self) -> Result<i64, SproutError> {
+        match self {
+            Field::Varint(value) => Ok(*value as i64),
+            _ => Err(wire_error("expected an integer")),
+        }
+    }
+
+    pub fn as_f32(&self) -> Result<f32, SproutError> {
+        match self {
+            Field::Fixed32(bytes) => Ok(f32::from_le_bytes(*bytes)),
+            _ => Err(wire_error("expected a float")),
+        }
+    }
+
+    pub fn as_bytes(&self) -> Result<&'a [u8], SproutError> {
+        match self {
+            Field::Bytes(bytes) => Ok(bytes),
+            _ => Err(wire_error("expected a length-delimited field")),
+        }
+    }
+
+    pub fn as_string(&self) -> Result<String, SproutError> {
+        String::from_utf8(self.as_bytes()?.to_vec()).map_err(|_| wire_error("string is not valid UTF-8"))
+    }
+
+    //repeated integers come either packed into one field or as one field per value
+    pub fn push_ints(&self, values: &mut Vec<i64>) -> Result<(), SproutError> {
+        match self {
+            Field::Varint(value) => values.push(*value as i64),
+            Field::Bytes(bytes) => {
+                let mut position = 0;
+                while position < bytes.len() {
+                    values.push(read_varint(bytes, &mut position)? as i64);
+                }
+            },
+            _ => return Err(wire_error("expected integers")),
+        }
+        Ok(())
+    }
+
+    pub fn push_f32s(&self, values: &mut Vec<f64>) -> Result<(), SproutError> {
+        match self {
+            Field::Fixed32(bytes) => values.push(f32::from_le_bytes(*bytes) as f64),
+            Field::Bytes(bytes) if bytes.len() % 4 == 0 =>
+                values.extend(bytes.chunks_exact(4).map(|x| f32::from_le_bytes(x.try_into().unwrap()) as f64)),
+            _ => return Err(wire_error("expected floats")),
+        }
+        Ok(())
+    }
+
+    pub fn push_f64s(&self, values: &mut Vec<f64>) -> Result<(), SproutError> {
+        match self {
+            Field::Fixed64(bytes) => values.push(f64::from_le_bytes(*bytes)),
+            Field::Bytes(bytes) if bytes.len() % 8 == 0 =>
+                values.extend(bytes.chunks_exact(8).map(|x| f64::from_le_bytes(x.try_into().unwrap()))),
+            _ => return Err(wire_error("expected doubles")),
+        }
+        Ok(())
+    }
+}
+
+//every (field number, value) of a message in wire order
+pub fn read_fields(bytes: &[u8]) -> Result<Vec<(u32, Field<'_>)>, SproutError> {
+    let mut fields = vec![];
+    let mut position = 0;
+    while position < bytes.len() {
+        let key = read_varint(bytes, &mut position)?;
+        let field = (key >> 3) as u32;
+        let value = match (key & 7) as u32 {
+            VARINT => Field::Varint(read_varint(bytes, &mut position)?),
+            FIXED64 => Field::Fixed64(take(bytes, &mut position, 8)?.try_into().unwrap()),
+            LENGTH_DELIMITED =>
+                {
+                    let len = read_varint(bytes, &mut position)? as usize;
+                    Field::Bytes(take(bytes, &mut position, len)?)
+                },
+            FIXED32 => Field::Fixed32(take(bytes, &mut position, 4)?.try_into().unwrap()),
+            wire_type => return Err(wire_error(&format!("unsupported wire type {}", wire_type))),
+        };
+        fields.push((field, value));
+    }
+    Ok(fields)
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u64, SproutError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position).ok_or_else(|| wire_error("message ends inside a varint"))?;
+        *position += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+    Err(wire_error("varint is longer than 10 bytes"))
+}
+
+fn take<'a>(bytes: &'a [u8], position: &mut usize, len: usize) -> Result<&'a [u8], SproutError> {
+    let end = position.checked_add(len).filter(|end| *end <= bytes.len())
+        .ok_or_else(|| wire_error("message ends inside a field"))?;
+    let slice = &bytes[*position..end];
+    *position = end;
+    Ok(slice)
+}
+
+fn wire_error(message: &str) -> SproutError {
+    SproutError::Format(format!("malformed protobuf, {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //field 1 = 150, field 2 = "testing" (the examples from the protobuf encoding guide), field 3 = 1.5f,
+    //field 4 = packed [3, 270], field 5 = 2.0 as a double
+    const KNOWN: [u8; 31] = [
+        0x08, 0x96, 0x01,
+        0x12, 0x07, b't', b'e', b's', b't', b'i', b'n', b'g',
+        0x1d, 0x00, 0x00, 0xc0, 0x3f,
+        0x22, 0x03, 0x03, 0x8e, 0x02,
+        0x29, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x40,
+    ];
+
+    #[test]
+    fn decodes_known_bytes() {
+        let fields = read_fields(&KNOWN).unwrap();
+        let numbers: Vec<u32> = fields.iter().map(|(field, _)| *field).collect();
+        assert_eq!(numbers, [1, 2, 3, 4, 5]);
+        assert_eq!(fields[0].1.as_int().unwrap(), 150);
+        assert_eq!(fields[1].1.as_string().unwrap(), "testing");
+        assert_eq!(fields[2].1.as_f32().unwrap(), 1.5);
+        let mut ints = vec![];
+        fields[3].1.push_ints(&mut ints).unwrap();
+        assert_eq!(ints, [3, 270]);
+        let mut doubles = vec![];
+        fields[4].1.push_f64s(&mut doubles).unwrap();
+        assert_eq!(doubles, [2.0]);
+        assert!(fields[0].1.as_bytes().is_err());
+    }
+
+    #[test]
+    fn encodes_known_bytes() {
+        let mut message = Message::new();
+        message.int(1, 150).string(2, "testing").float(3, 1.5);
+        assert_eq!(message.bytes, KNOWN[..17]);
+        //repeated ints are written unpacked and read back the same as packed ones
+        let mut ints = vec![];
+        for (_, field) in read_fields(&Message::new().ints(4, &[3, 270]).bytes).unwrap() {
+            field.push_ints(&mut ints).unwrap();
+        }
+        assert_eq!(ints, [3, 270]);
+        assert_eq!(Message::new().int(1, -1).bytes.len(), 11);
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        for end in [1, 2, 5, 15, 30] {
+            assert!(read_fields(&KNOWN[..end]).is_err(), "{} bytes", end);
+        }
+        assert!(read_fields(&[0x0b]).is_err()); //wire type 3, groups aren't supported
+        assert!(read_fields(&[0xff; 11]).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{error::SproutError, float::Float};
+
+//penalties and constraints on a layer's weights, biases are left alone. everything is off by default
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Regularizer {
+    #[serde(default)]
+    pub l1: f64, //adds l1 * sum |w| to the cost
+    #[serde(default)]
+    pub l2: f64, //adds l2 * sum w^2 to the cost
+    #[serde(default)]
+    pub weight_decay: f64, //decoupled, after each update w -= learning_rate * weight_decay * w, outside the optimizer (as in AdamW)
+    #[serde(default)]
+    pub constraint: Option<Constraint>, //applied after each update
+}
+
+//per unit means each output unit's incoming weights, laid out as Layer::weight_units says
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Constraint {
+    MaxNorm(f64), //each unit's L2 norm is scaled down to at most this
+    NonNegative, //negative weights are set to 0
+    UnitNorm, //each unit's L2 norm is scaled to 1
+}
+
+//where each unit's weights sit in a layer's flat weights
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Units {
+    Columns(usize), //unit j is every n-th weight starting at j, the columns of a row-major in x out matrix
+    Blocks(usize), //n runs of equal length one after the other, e.g. a kernel per channel
+}
+
+impl Regularizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn l1(mut self, l1: f64) -> Self {
+        self.l1 = l1;
+        self
+    }
+
+    pub fn l2(mut self, l2: f64) -> Self {
+        self.l2 = l2;
+        self
+    }
+
+    pub fn weight_decay(mut self, weight_decay: f64) -> Self {
+        self.weight_decay = weight_decay;
+        self
+    }
+
+    pub fn constraint(mut self, constraint: Constraint) -> Self {
+        self.constraint = Some(constraint);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), SproutError> {
+        for (name, value) in [("l1", self.l1), ("l2", self.l2), ("weight decay", self.weight_decay)] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(SproutError::InvalidConfig(format!("{} must be finite and not negative, got {}", name, value)));
+            }
+        }
+        match self.constraint {
+            Some(Constraint::MaxNorm(max)) if !(max.is_finite() && max > 0.0) =>
+                Err(SproutError::InvalidConfig(format!("max-norm must be positive, got {}", max))),
+            _ => Ok(()),
+        }
+    }
+
+    //l1 * sum |w| + l2 * sum w^2
+    pub fn penalty<T: Float>(&self, weights: &[T]) -> f64 {
+        if self.l1 == 0.0 && self.l2 == 0.0 {
+            return 0.0;
+        }
+        weights.iter().map(|w| {
+            let w = w.as_f64();
+            self.l1 * w.abs() + self.l2 * w * w
+        }).sum()
+    }
+
+    //adds the penalty's gradient to gradients summed over batch_size samples, so it counts once per sample like the loss
+    pub fn add_gradients<T: Float>(&self, weights: &[T], gradients: &mut [T], batch_size: usize) {
+        if self.l1 == 0.0 && self.l2 == 0.0 {
+            return;
+        }
+        let scale = batch_size as f64;
+        for (w, gradient) in weights.iter().zip(gradients.iter_mut()) {
+            let w = w.as_f64();
+            //the subgradient of |w| at 0 is taken as 0
+            let sign = if w > 0.0 { 1.0 } else if w < 0.0 { -1.0 } else { 0.0 };
+            *gradient += T::from_f64(scale * (self.l1 * sign + 2.0 * self.l2 * w));
+        }
+    }
+
+    //weight decay and then the constraint, run after the optimizer has stepped the weights
+    pub fn after_update<T: Float>(&self, weights: &mut [T], units: Units, learning_rate: f64) {
+        if self.weight_decay > 0.0 {
+            let keep = T::from_f64(1.0 - learning_rate * self.weight_decay);
+            for w in weights.iter_mut() {
+                *w *= keep;
+            }
+        }
+        if let Some(constraint) = self.constraint {
+            constraint.apply(weights, units);
+        }
+    }
+}
+
+impl Constraint {
+    pub fn apply<T: Float>(&self, weights: &mut [T], units: Units) {
+        match *self {
+            Constraint::NonNegative => {
+                for w in weights.iter_mut() {
+                    if *w < T::zero() {
+                        *w = T::zero();
+                    }
+                }
+            },
+            Constraint::MaxNorm(max) => scale_units(weights, units, |norm| if norm > max { max / norm } else { 1.0 }),
+            //a unit that is all zeros has no direction and stays as it is
+            Constraint::UnitNorm => scale_units(weights, units, |norm| if norm > 0.0 { 1.0 / norm } else { 1.0 }),
+        }
+    }
+}
+
+fn scale_units<T: Float>(weights: &mut [T], units: Units, scale: impl Fn(f64) -> f64) {
+    match units {
+        Units::Columns(units) =>
+            {
+                let units = units.max(1);
+                for unit in 0..units {
+                    scale_unit(weights.iter_mut().skip(unit).step_by(units).collect(), &scale);
+                }
+            },
+        Units::Blocks(units) =>
+            {
+                let len = weights.len() / units.max(1);
+                for block in weights.chunks_mut(len.max(1)) {
+                    scale_unit(block.iter_mut().collect(), &scale);
+                }
+            },
+    }
+}
+
+fn scale_unit<T: Float>(unit: Vec<&mut T>, scale: &impl Fn(f64) -> f64) {
+    let norm = unit.iter().map(|w| w.as_f64().powi(2)).sum::<f64>().sqrt();
+    let scale = scale(norm);
+    if scale != 1.0 {
+        for w in unit {
+            *w = T::from_f64(w.as_f64() * scale);
+        }
+    }
+}
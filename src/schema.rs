@@ -0,0 +1,141 @@
+use serde_json::{Map, Value};
+
+use crate::{error::SproutError, float::Float, network::Network};
+
+//version of the saved JSON layout, bump it and add a migration whenever a saved field changes
+//  0: no version field, layers saved as the old Layer struct (layer_type + conv_params/dense_params)
+//     or already as tagged layers
+//  1: "schema_version" field, every layer tagged with its "type"
+pub const SCHEMA_VERSION: u32 = 1;
+
+const VERSION_KEY: &str = "schema_version";
+
+type Migration = fn(&mut Map<String, Value>) -> Result<(), SproutError>;
+
+//MIGRATIONS[v] upgrades a version v model to version v + 1
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [tag_layers];
+
+pub fn to_value<T: Float>(network: &Network<T>) -> Result<Value, SproutError> {
+    let mut value = serde_json::to_value(network)?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert(VERSION_KEY.to_string(), Value::from(SCHEMA_VERSION));
+    }
+    Ok(value)
+}
+
+//upgrades a saved model of any older version and deserializes it into the current Network
+pub fn from_value<T: Float>(mut value: Value) -> Result<Network<T>, SproutError> {
+    migrate(&mut value)?;
+    let mut network: Network<T> = serde_json::from_value(value)?;
+    network.precision = T::PRECISION;
+    //the rng's state isn't saved, a loaded model starts it again from its seed
+    network.set_seed(network.seed);
+    Ok(network)
+}
+
+//rewrites a saved model in place to the current layout
+pub fn migrate(value: &mut Value) -> Result<(), SproutError> {
+    let object = value.as_object_mut().ok_or_else(|| SproutError::Format("saved model is not a JSON object".to_string()))?;
+    let version = match object.get(VERSION_KEY) {
+        None => 0,
+        Some(version) => version.as_u64()
+            .ok_or_else(|| SproutError::Format(format!("schema version {} is not a number", version)))?,
+    };
+    if version > SCHEMA_VERSION as u64 {
+        return Err(SproutError::Format(format!(
+            "model was saved with schema version {} but this version of Sprout reads up to {}, update Sprout to load it",
+            version, SCHEMA_VERSION
+        )));
+    }
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(object).map_err(|err| err.context(&format!("upgrading schema version {}", from)))?;
+    }
+    object.insert(VERSION_KEY.to_string(), Value::from(SCHEMA_VERSION));
+    Ok(())
+}
+
+//0 -> 1: layers saved as the old Layer struct become tagged layers, dropping the caches and optimizer state
+//they used to carry and flattening nested weights (conv channel > rows > cols, dense in > out) to row-major
+fn tag_layers(network: &mut Map<String, Value>) -> Result<(), SproutError> {
+    let Some(Value::Array(layers)) = network.get_mut("layers") else {
+        return Err(SproutError::Format("model has no layer list".to_string()));
+    };
+    for (i, layer) in layers.iter_mut().enumerate() {
+        let Some(object) = layer.as_object_mut() else {
+            return Err(SproutError::Format(format!("layer {} is not a JSON object", i)));
+        };
+        if object.contains_key("type") {
+            continue;
+        }
+        *layer = untagged_layer(object).map_err(|err| err.context(&format!("layer {}", i)))?;
+    }
+    Ok(())
+}
+
+fn untagged_layer(layer: &mut Map<String, Value>) -> Result<Value, SproutError> {
+    let layer_type = layer.get("layer_type").and_then(Value::as_str)
+        .ok_or_else(|| SproutError::Format("layer has neither a \"type\" tag nor a \"layer_type\"".to_string()))?
+        .to_string();
+    let mut tagged = Map::new();
+    match layer_type.as_str() {
+        "Dense" =>
+            {
+                let mut params = take_params(layer, "dense_params")?;
+                let mut dense = Map::new();
+                for field in ["nodes_in", "nodes_out"] {
+                    dense.insert(field.to_string(), take_field(&mut params, field)?);
+                }
+                dense.insert("weights".to_string(), flatten(take_field(&mut params, "weights")?));
+                dense.insert("biases".to_string(), take_field(&mut params, "biases")?);
+                tagged.insert("type".to_string(), Value::from("dense"));
+                tagged.insert("activation".to_string(), take_field(layer, "activation")?);
+                tagged.insert("params".to_string(), Value::Object(dense));
+            },
+        "Convolutional" | "Pooling" =>
+            {
+                let mut params = take_params(layer, "conv_params")?;
+                let mut conv = Map::new();
+                for field in ["kernel", "padding_type", "padding", "stride"] {
+                    conv.insert(field.to_string(), take_field(&mut params, field)?);
+                }
+                if layer_type == "Pooling" {
+                    //pooling never used its weights, and the current layer doesn't keep any
+                    conv.insert("weights".to_string(), Value::Array(vec![]));
+                    conv.insert("bias".to_string(), Value::from(0.0));
+                    tagged.insert("type".to_string(), Value::from("pool"));
+                } else {
+                    conv.insert("weights".to_string(), flatten(take_field(&mut params, "weights")?));
+                    conv.insert("bias".to_string(), take_field(&mut params, "bias")?);
+                    tagged.insert("type".to_string(), Value::from("conv"));
+                    tagged.insert("activation".to_string(), take_field(layer, "activation")?);
+                }
+                tagged.insert("params".to_string(), Value::Object(conv));
+            },
+        _ => return Err(SproutError::Format(format!("unknown layer_type '{}'", layer_type))),
+    }
+    Ok(Value::Object(tagged))
+}
+
+fn take_params(layer: &mut Map<String, Value>, field: &str) -> Result<Map<String, Value>, SproutError> {
+    match layer.remove(field) {
+        Some(Value::Object(params)) => Ok(params),
+        _ => Err(SproutError::Format(format!("layer is missing its {}", field))),
+    }
+}
+
+fn take_field(object: &mut Map<String, Value>, field: &str) -> Result<Value, SproutError> {
+    object.remove(field).ok_or_else(|| SproutError::Format(format!("missing field \"{}\"", field)))
+}
+
+//nested arrays of any depth to one row-major array
+fn flatten(value: Value) -> Value {
+    fn push(value: Value, out: &mut Vec<Value>) {
+        match value {
+            Value::Array(values) => values.into_iter().for_each(|value| push(value, out)),
+            value => out.push(value),
+        }
+    }
+    let mut out = vec![];
+    push(value, &mut out);
+    Value::Array(out)
+}
@@ -0,0 +1,413 @@
+use std::{collections::{BTreeMap, BTreeSet}, fmt, fs, path::Path, time::Instant};
+
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{dataset::{DataLoader, Dataset}, error::SproutError, float::Float, network::Network, validation::Metrics};
+
+//hyperparameter search. the space names every parameter and where its values come from, each trial hands a Config
+//(name > value) to a build function that makes the network, which is trained on the training loader and scored on the
+//validation data. trials run in parallel and are seeded from the search's seed, so a search can be repeated exactly
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Param {
+    Choice(Vec<f64>), //one of these
+    Int { low: usize, high: usize }, //any whole number from low to high, inclusive
+    Uniform { low: f64, high: f64 }, //random search only
+    LogUniform { low: f64, high: f64 }, //spread evenly over orders of magnitude, e.g. learning rates, random search only
+}
+
+impl Param {
+    fn validate(&self, name: &str) -> Result<(), SproutError> {
+        let valid = match self {
+            Param::Choice(values) => !values.is_empty() && values.iter().all(|value| value.is_finite()),
+            Param::Int { low, high } => low <= high,
+            Param::Uniform { low, high } => low.is_finite() && high.is_finite() && low <= high,
+            Param::LogUniform { low, high } => low.is_finite() && high.is_finite() && *low > 0.0 && low <= high,
+        };
+        match valid {
+            true => Ok(()),
+            false => Err(SproutError::InvalidConfig(format!("search parameter {} has an empty or invalid range: {:?}", name, self))),
+        }
+    }
+
+    //every value for grid search
+    fn grid(&self, name: &str) -> Result<Vec<f64>, SproutError> {
+        match self {
+            Param::Choice(values) => Ok(values.clone()),
+            Param::Int { low, high } => Ok((*low..=*high).map(|value| value as f64).collect()),
+            _ => Err(SproutError::InvalidConfig(format!("grid search needs discrete values but {} is continuous", name))),
+        }
+    }
+
+    fn sample(&self, rng: &mut dyn RngCore) -> f64 {
+        match self {
+            Param::Choice(values) => values[rng.gen_range(0..values.len())],
+            Param::Int { low, high } => rng.gen_range(*low..=*high) as f64,
+            Param::Uniform { low, high } => rng.gen_range(*low..=*high),
+            Param::LogUniform { low, high } => rng.gen_range(low.ln()..=high.ln()).exp(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Space {
+    pub params: BTreeMap<String, Param>,
+}
+
+impl Space {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn param(mut self, name: &str, param: Param) -> Self {
+        self.params.insert(name.to_string(), param);
+        self
+    }
+
+    pub fn choice(self, name: &str, values: impl IntoIterator<Item = f64>) -> Self {
+        self.param(name, Param::Choice(values.into_iter().collect()))
+    }
+
+    pub fn int(self, name: &str, low: usize, high: usize) -> Self {
+        self.param(name, Param::Int { low, high })
+    }
+
+    pub fn uniform(self, name: &str, low: f64, high: f64) -> Self {
+        self.param(name, Param::Uniform { low, high })
+    }
+
+    pub fn log_uniform(self, name: &str, low: f64, high: f64) -> Self {
+        self.param(name, Param::LogUniform { low, high })
+    }
+
+    pub fn validate(&self) -> Result<(), SproutError> {
+        if self.params.is_empty() {
+            return Err(SproutError::InvalidConfig("search space has no parameters".to_string()));
+        }
+        self.params.iter().try_for_each(|(name, param)| param.validate(name))
+    }
+
+    //every combination, the last parameter by name changes fastest
+    pub fn grid(&self) -> Result<Vec<Config>, SproutError> {
+        self.validate()?;
+        let mut configs = vec![Config::default()];
+        for (name, param) in &self.params {
+            let values = param.grid(name)?;
+            configs = configs.into_iter()
+                .flat_map(|config| values.iter().map(move |value| config.clone().with(name, *value)))
+                .collect();
+        }
+        Ok(configs)
+    }
+
+    pub fn sample(&self, rng: &mut dyn RngCore) -> Config {
+        Config(self.params.iter().map(|(name, param)| (name.clone(), param.sample(rng))).collect())
+    }
+}
+
+//one point of the space
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Config(pub BTreeMap<String, f64>);
+
+impl Config {
+    pub fn with(mut self, name: &str, value: f64) -> Self {
+        self.0.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.0.get(name).copied()
+    }
+
+    pub fn float(&self, name: &str) -> Result<f64, SproutError> {
+        self.get(name).ok_or_else(|| SproutError::InvalidConfig(format!("search config has no {}", name)))
+    }
+
+    //for sizes and counts, the value is rounded
+    pub fn int(&self, name: &str) -> Result<usize, SproutError> {
+        match self.float(name)?.round() {
+            value if value >= 0.0 => Ok(value as usize),
+            value => Err(SproutError::InvalidConfig(format!("search config {} = {} is not a size", name, value))),
+        }
+    }
+
+    //sets learning_rate, batch_size and grad_threshold on the network when the config has them,
+    //anything else (e.g. layer sizes) is up to the build function. values are checked like Network::try_new does,
+    //an infinite grad_threshold turns clipping off
+    pub fn apply<T: Float>(&self, network: &mut Network<T>) -> Result<(), SproutError> {
+        if let Some(learning_rate) = self.get("learning_rate") {
+            if !learning_rate.is_finite() || learning_rate <= 0.0 {
+                return Err(SproutError::InvalidConfig(format!("learning rate must be positive, got {}", learning_rate)));
+            }
+            network.learning_rate = learning_rate;
+        }
+        if self.get("batch_size").is_some() {
+            match self.int("batch_size")? {
+                0 => return Err(SproutError::InvalidConfig("batch size must be at least 1".to_string())),
+                batch_size => network.batch_size = batch_size,
+            }
+        }
+        if let Some(grad_threshold) = self.get("grad_threshold") {
+            if grad_threshold.is_nan() || grad_threshold <= 0.0 {
+                return Err(SproutError::InvalidConfig(format!("gradient threshold must be positive, got {}", grad_threshold)));
+            }
+            network.grad_threshold = grad_threshold;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self.0.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        write!(f, "{}", values.join(", "))
+    }
+}
+
+//which validation metric ranks the trials
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Objective {
+    #[default]
+    Loss, //lowest
+    Accuracy, //highest
+    MeanAbsoluteError, //lowest
+}
+
+impl Objective {
+    //lower is better
+    pub fn score(&self, metrics: &Metrics) -> f64 {
+        match self {
+            Objective::Loss => metrics.loss,
+            Objective::Accuracy => -metrics.accuracy,
+            Objective::MeanAbsoluteError => metrics.mean_absolute_error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Strategy {
+    Grid, //every combination, for spaces of Choice and Int parameters
+    Random { trials: usize },
+    //random configs trained for min_epochs, then the best 1/factor of them trained on for factor times the epochs
+    //and so on until one is left or the search's epochs are reached
+    SuccessiveHalving { trials: usize, min_epochs: usize, factor: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Search {
+    pub space: Space,
+    pub strategy: Strategy,
+    pub objective: Objective,
+    pub epochs: usize, //every trial's epochs, the most any trial gets with successive halving
+    pub seed: u64, //draws the random configs, trial i's network is seeded with seed + i
+}
+
+impl Search {
+    pub fn new(space: Space, strategy: Strategy, epochs: usize) -> Self {
+        Search { space, strategy, objective: Objective::Loss, epochs, seed: rand::random() }
+    }
+
+    pub fn grid(space: Space, epochs: usize) -> Self {
+        Self::new(space, Strategy::Grid, epochs)
+    }
+
+    pub fn random(space: Space, trials: usize, epochs: usize) -> Self {
+        Self::new(space, Strategy::Random { trials }, epochs)
+    }
+
+    pub fn successive_halving(space: Space, trials: usize, min_epochs: usize, factor: usize, max_epochs: usize) -> Self {
+        Self::new(space, Strategy::SuccessiveHalving { trials, min_epochs, factor }, max_epochs)
+    }
+
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.objective = objective;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), SproutError> {
+        self.space.validate()?;
+        if self.epochs == 0 {
+            return Err(SproutError::InvalidConfig("search needs at least 1 epoch per trial".to_string()));
+        }
+        match self.strategy {
+            Strategy::Random { trials: 0 } | Strategy::SuccessiveHalving { trials: 0, .. } =>
+                Err(SproutError::InvalidConfig("search needs at least 1 trial".to_string())),
+            Strategy::SuccessiveHalving { min_epochs, factor, .. } if min_epochs == 0 || min_epochs > self.epochs || factor < 2 =>
+                Err(SproutError::InvalidConfig(format!(
+                    "successive halving needs 1 to {} min_epochs and a factor of at least 2, got {} and {}", self.epochs, min_epochs, factor
+                ))),
+            _ => Ok(()),
+        }
+    }
+
+    //the configs the search starts from
+    pub fn configs(&self) -> Result<Vec<Config>, SproutError> {
+        self.validate()?;
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        match self.strategy {
+            Strategy::Grid => self.space.grid(),
+            Strategy::Random { trials } | Strategy::SuccessiveHalving { trials, .. } => Ok((0..trials).map(|_| self.space.sample(&mut rng)).collect()),
+        }
+    }
+
+    //trials train with the loader's settings but their network's batch size. a trial that fails (the build function errors,
+    //training diverges, ...) is recorded with its error and left out of the ranking
+    pub fn run<T: Float>(
+        &self,
+        build: impl Fn(&Config) -> Result<Network<T>, SproutError> + Sync,
+        train: &DataLoader<T>,
+        validation: &dyn Dataset<T>,
+    ) -> Result<SearchResults, SproutError> {
+        let configs = self.configs()?;
+        let built: Vec<Result<Network<T>, SproutError>> = configs.par_iter().enumerate()
+            .map(|(id, config)| build(config).and_then(|network| network.try_fresh(self.seed.wrapping_add(id as u64))))
+            .collect();
+        let mut trials: Vec<Trial> = vec![];
+        let mut rung: Vec<(usize, Network<T>)> = vec![];
+        for (id, network) in built.into_iter().enumerate() {
+            match network {
+                Ok(network) => rung.push((id, network)),
+                Err(err) => trials.push(Trial::failed(id, &configs[id], 0, 0.0, err)),
+            }
+        }
+
+        let (mut epochs, factor) = match self.strategy {
+            Strategy::SuccessiveHalving { min_epochs, factor, .. } => (min_epochs, factor),
+            _ => (self.epochs, 1),
+        };
+        let mut trained = 0;
+        loop {
+            let results: Vec<(Trial, Network<T>)> = rung.into_par_iter()
+                .map(|(id, mut network)| {
+                    let mut loader = train.clone();
+                    loader.batch_size = network.batch_size;
+                    let start = Instant::now();
+                    let metrics = network.try_train(&loader, epochs - trained).and_then(|_| network.evaluate(validation));
+                    let seconds = start.elapsed().as_secs_f64();
+                    let trial = match metrics {
+                        Ok(metrics) => Trial { id, config: configs[id].clone(), epochs, metrics: Some(metrics), seconds, error: None },
+                        Err(err) => Trial::failed(id, &configs[id], epochs, seconds, err),
+                    };
+                    (trial, network)
+                })
+                .collect();
+            trained = epochs;
+            let mut survivors: Vec<(Trial, Network<T>)> = vec![];
+            for (trial, network) in results {
+                trials.push(trial.clone());
+                if trial.metrics.is_some() {
+                    survivors.push((trial, network));
+                }
+            }
+            survivors.sort_by(|a, b| a.0.score(self.objective).total_cmp(&b.0.score(self.objective)).then(a.0.id.cmp(&b.0.id)));
+            if factor == 1 || survivors.len() <= 1 || epochs >= self.epochs {
+                break;
+            }
+            survivors.truncate((survivors.len() / factor).max(1));
+            rung = survivors.into_iter().map(|(trial, network)| (trial.id, network)).collect();
+            rung.sort_by_key(|(id, _)| *id);
+            epochs = (epochs * factor).min(self.epochs);
+        }
+        Ok(SearchResults { objective: self.objective, trials })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trial {
+    pub id: usize, //index of the config, successive halving has one trial per config per round
+    pub config: Config,
+    pub epochs: usize, //trained in total when it was evaluated
+    pub metrics: Option<Metrics>, //on the validation data, None if the trial failed
+    pub seconds: f64, //building is not counted, for successive halving only this round's training is
+    pub error: Option<String>,
+}
+
+impl Trial {
+    fn failed(id: usize, config: &Config, epochs: usize, seconds: f64, err: SproutError) -> Self {
+        Trial { id, config: config.clone(), epochs, metrics: None, seconds, error: Some(err.to_string()) }
+    }
+
+    //failed trials rank last
+    pub fn score(&self, objective: Objective) -> f64 {
+        self.metrics.as_ref().map_or(f64::INFINITY, |metrics| objective.score(metrics))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub objective: Objective,
+    pub trials: Vec<Trial>, //in the order they finished rounds, by id within a round
+}
+
+impl SearchResults {
+    //best first, ties keep the earlier trial first. each config is ranked by its last (longest trained) trial only,
+    //so successive halving's early rounds don't compete with the configs that went on, and a config that failed
+    //in its last round isn't ranked on an earlier score
+    pub fn ranked(&self) -> Vec<&Trial> {
+        let mut last: BTreeMap<usize, &Trial> = BTreeMap::new();
+        for trial in &self.trials {
+            let entry = last.entry(trial.id).or_insert(trial);
+            if trial.epochs > entry.epochs {
+                *entry = trial;
+            }
+        }
+        let mut trials: Vec<&Trial> = self.trials.iter()
+            .filter(|trial| trial.metrics.is_some() && std::ptr::eq(*trial, last[&trial.id]))
+            .collect();
+        trials.sort_by(|a, b| a.score(self.objective).total_cmp(&b.score(self.objective)));
+        trials
+    }
+
+    pub fn best(&self) -> Option<&Trial> {
+        self.ranked().into_iter().next()
+    }
+
+    //one row per trial: id, every parameter, epochs, metrics, seconds and error
+    pub fn to_csv(&self) -> String {
+        let names: Vec<&String> = self.trials.iter().flat_map(|trial| trial.config.0.keys()).collect::<BTreeSet<_>>().into_iter().collect();
+        let mut header = vec!["id".to_string()];
+        header.extend(names.iter().map(|name| csv_field(name)));
+        header.extend(["epochs", "loss", "accuracy", "mean_absolute_error", "seconds", "error"].map(String::from));
+        let mut lines = vec![header.join(",")];
+        for trial in &self.trials {
+            let mut row = vec![trial.id.to_string()];
+            row.extend(names.iter().map(|name| trial.config.get(name).map_or(String::new(), |value| value.to_string())));
+            row.push(trial.epochs.to_string());
+            match &trial.metrics {
+                Some(metrics) => row.extend([metrics.loss, metrics.accuracy, metrics.mean_absolute_error].map(|value| value.to_string())),
+                None => row.extend([String::new(), String::new(), String::new()]),
+            }
+            row.push(trial.seconds.to_string());
+            row.push(trial.error.as_deref().map_or(String::new(), csv_field));
+            lines.push(row.join(","));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> Result<(), SproutError> {
+        let path = path.as_ref();
+        fs::write(path, self.to_csv()).map_err(|err| SproutError::from(err).context(&path.display().to_string()))
+    }
+
+    pub fn save_json(&self, path: impl AsRef<Path>) -> Result<(), SproutError> {
+        let path = path.as_ref();
+        fs::write(path, serde_json::to_string_pretty(self)?).map_err(|err| SproutError::from(err).context(&path.display().to_string()))
+    }
+}
+
+//quoted when it holds a comma, quote or line break
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
@@ -0,0 +1,194 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    activation::ActivationFunction,
+    conv_layer::ConvLayer,
+    conv_params::PaddingType,
+    dense_layer::DenseLayer,
+    error::SproutError,
+    float::Float,
+    initializer::Initializer,
+    layer::{self, Layer},
+    loss_function::LossType,
+    network::Network,
+    optimizer::{Optimizer, Schedule},
+    regularizer::Regularizer,
+    shape::Shape,
+};
+
+enum Step {
+    Conv { kernel: usize, padding: PaddingType, stride: usize, activation: ActivationFunction, init: Option<[Initializer; 2]>, regularizer: Regularizer },
+    Pool { kernel: usize, stride: usize },
+    Flatten,
+    Dense { nodes: usize, activation: ActivationFunction, init: Option<[Initializer; 2]>, regularizer: Regularizer },
+    Init, //init() that didn't follow a conv or dense layer
+    Regularize, //regularize() that didn't follow a conv or dense layer
+}
+
+//chainable model definition, layer sizes are worked out from the input shape when built
+pub struct Sequential {
+    input_shape: Shape,
+    steps: Vec<Step>,
+    loss_type: LossType,
+    optimizer: Optimizer,
+    schedule: Schedule,
+    learning_rate: f64,
+    batch_size: usize,
+    seed: Option<u64>,
+}
+
+impl Sequential {
+    pub fn new(input_shape: Shape) -> Self {
+        Sequential {
+            input_shape,
+            steps: vec![],
+            loss_type: LossType::MSE,
+            optimizer: Optimizer::SGD,
+            schedule: Schedule::Constant,
+            learning_rate: 0.01,
+            batch_size: 32,
+            seed: None,
+        }
+    }
+
+    pub fn conv(mut self, kernel: usize, padding: PaddingType, stride: usize, activation: ActivationFunction) -> Self {
+        self.steps.push(Step::Conv { kernel, padding, stride, activation, init: None, regularizer: Regularizer::default() });
+        self
+    }
+
+    pub fn pool(mut self, kernel: usize, stride: usize) -> Self {
+        self.steps.push(Step::Pool { kernel, stride });
+        self
+    }
+
+    pub fn flatten(mut self) -> Self {
+        self.steps.push(Step::Flatten);
+        self
+    }
+
+    pub fn dense(mut self, nodes: usize, activation: ActivationFunction) -> Self {
+        self.steps.push(Step::Dense { nodes, activation, init: None, regularizer: Regularizer::default() });
+        self
+    }
+
+    //initializers for the weights and biases of the conv or dense layer just added
+    pub fn init(mut self, weights: Initializer, biases: Initializer) -> Self {
+        match self.steps.last_mut() {
+            Some(Step::Conv { init, .. } | Step::Dense { init, .. }) => *init = Some([weights, biases]),
+            _ => self.steps.push(Step::Init),
+        }
+        self
+    }
+
+    //weight penalties and constraints for the conv or dense layer just added
+    pub fn regularize(mut self, regularizer: Regularizer) -> Self {
+        match self.steps.last_mut() {
+            Some(Step::Conv { regularizer: current, .. } | Step::Dense { regularizer: current, .. }) => *current = regularizer,
+            _ => self.steps.push(Step::Regularize),
+        }
+        self
+    }
+
+    pub fn loss(mut self, loss_type: LossType) -> Self {
+        self.loss_type = loss_type;
+        self
+    }
+
+    pub fn optimizer(mut self, optimizer: Optimizer) -> Self {
+        self.optimizer = optimizer;
+        self
+    }
+
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn learning_rate(mut self, learning_rate: f64) -> Self {
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build<T: Float>(self) -> Result<Network<T>, SproutError> {
+        if self.input_shape.size() == 0 {
+            return Err(SproutError::InvalidConfig(format!("input shape {} is empty", self.input_shape)));
+        }
+        //layers are initialized here, drawing from the rng the network then carries on with
+        let seed = self.seed.unwrap_or_else(rand::random);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut layers: Vec<Box<dyn Layer<T>>> = vec![];
+        let mut shape = self.input_shape;
+        for step in self.steps {
+            let i = layers.len();
+            let flat = matches!(shape, Shape::Flat(_));
+            let mut layer = match step {
+                Step::Conv { .. } | Step::Pool { .. } if flat =>
+                    {
+                        return Err(SproutError::InvalidConfig(format!("layer {}: conv and pooling layers can't come after flatten() or a dense layer", i)));
+                    },
+                Step::Conv { kernel, stride, .. } | Step::Pool { kernel, stride } if kernel == 0 || stride == 0 =>
+                    {
+                        return Err(SproutError::InvalidConfig(format!("layer {}: kernel ({}) and stride ({}) must be at least 1", i, kernel, stride)));
+                    },
+                Step::Conv { kernel, padding, stride, activation, init, regularizer } =>
+                    {
+                        regularizer.validate().map_err(|err| err.context(&format!("layer {}", i)))?;
+                        let mut layer = ConvLayer::new(kernel, padding, stride, activation).regularize(regularizer);
+                        if let Some([weights, biases]) = init {
+                            layer = layer.weight_init(weights).bias_init(biases);
+                        }
+                        Box::new(layer)
+                    },
+                Step::Pool { kernel, stride } => layer::pool(kernel, stride),
+                Step::Flatten =>
+                    {
+                        if let Shape::Flat(_) = shape {
+                            return Err(SproutError::InvalidConfig(format!("flatten before layer {} has nothing to flatten", i)));
+                        }
+                        shape = Shape::Flat(shape.size());
+                        continue;
+                    },
+                Step::Init => return Err(SproutError::InvalidConfig(format!("init() before layer {} has to follow a conv or dense layer", i))),
+                Step::Regularize => return Err(SproutError::InvalidConfig(format!("regularize() before layer {} has to follow a conv or dense layer", i))),
+                Step::Dense { nodes, activation, init, regularizer } =>
+                    {
+                        if nodes == 0 {
+                            return Err(SproutError::InvalidConfig(format!("layer {}: dense layer needs at least one node", i)));
+                        }
+                        if let Shape::Image(_) = shape {
+                            return Err(SproutError::InvalidConfig(format!("layer {}: call flatten() before a dense layer that follows an image", i)));
+                        }
+                        regularizer.validate().map_err(|err| err.context(&format!("layer {}", i)))?;
+                        let mut layer = DenseLayer::new([shape.size(), nodes], activation).regularize(regularizer);
+                        if let Some([weights, biases]) = init {
+                            layer = layer.weight_init(weights).bias_init(biases);
+                        }
+                        Box::new(layer)
+                    },
+            };
+            shape = layer.build(shape, &mut rng).map_err(|message| SproutError::ShapeMismatch(format!("layer {}: {}", i, message)))?;
+            layers.push(layer);
+        }
+
+        self.optimizer.validate()?;
+        self.schedule.validate()?;
+        let mut network = Network::try_new(layers, self.learning_rate, self.batch_size, self.loss_type)?;
+        network.optimizer = self.optimizer;
+        network.schedule = self.schedule;
+        network.seed = seed;
+        network.rng = rng;
+        network.build(self.input_shape)?;
+        Ok(network)
+    }
+}
@@ -0,0 +1,28 @@
+use std::fmt;
+
+use serde_derive::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Shape {
+    Flat(usize), //nodes
+    Image([usize; 3]), //channels, rows, cols
+}
+
+impl Shape {
+    //number of values once flattened
+    pub fn size(&self) -> usize {
+        match self {
+            Shape::Flat(nodes) => *nodes,
+            Shape::Image([channels, rows, cols]) => channels * rows * cols,
+        }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Shape::Flat(nodes) => write!(f, "[{}]", nodes),
+            Shape::Image([channels, rows, cols]) => write!(f, "[{} x {} x {}]", channels, rows, cols),
+        }
+    }
+}
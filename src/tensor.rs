@@ -0,0 +1,61 @@
+use crate::{float::Float, network::Image, shape::Shape};
+
+//one sample as it flows between layers
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tensor<T = f64> {
+    Flat(Vec<T>),
+    Image(Image<T>), //channel > rows > cols
+}
+
+impl<T: Float> Tensor<T> {
+    pub fn shape(&self) -> Shape {
+        match self {
+            Tensor::Flat(values) => Shape::Flat(values.len()),
+            Tensor::Image(img) => Shape::Image([
+                img.len(),
+                img.first().map_or(0, |channel| channel.len()),
+                img.first().and_then(|channel| channel.first()).map_or(0, |row| row.len()),
+            ]),
+        }
+    }
+
+    //images are flattened channel by channel, row by row
+    pub fn into_flat(self) -> Vec<T> {
+        match self {
+            Tensor::Flat(values) => values,
+            Tensor::Image(img) => img.into_iter().flatten().flatten().collect(),
+        }
+    }
+
+    pub fn to_flat(&self) -> Vec<T> {
+        self.clone().into_flat()
+    }
+
+    pub fn into_image(self) -> Image<T> {
+        match self {
+            Tensor::Image(img) => img,
+            Tensor::Flat(values) => panic!("expected an image but got {} flat values", values.len()),
+        }
+    }
+
+    pub fn as_image(&self) -> &Image<T> {
+        match self {
+            Tensor::Image(img) => img,
+            Tensor::Flat(values) => panic!("expected an image but got {} flat values", values.len()),
+        }
+    }
+
+    //inverse of into_flat
+    pub fn from_flat(values: Vec<T>, shape: Shape) -> Self {
+        match shape {
+            Shape::Flat(_) => Tensor::Flat(values),
+            Shape::Image([channels, rows, cols]) =>
+                {
+                    let mut values = values.into_iter();
+                    Tensor::Image((0..channels).map(|_| {
+                        (0..rows).map(|_| values.by_ref().take(cols).collect()).collect()
+                    }).collect())
+                },
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use rand::{seq::SliceRandom, RngCore};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{dataset::{DataLoader, Dataset, Subset}, error::SproutError, float::Float, network::Network};
+
+//splits are lists of sample indices, so they work the same for in-memory data and lazily loaded datasets
+//(see Partition::select and DataLoader::subset). shuffling is drawn from the rng passed in, a seeded rng splits the same way every run
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Partition {
+    pub train: Vec<usize>,
+    pub validation: Vec<usize>,
+    pub test: Vec<usize>,
+}
+
+impl Partition {
+    //the train, validation and test samples of data
+    pub fn select<S: Clone>(&self, data: &[S]) -> [Vec<S>; 3] {
+        [&self.train, &self.validation, &self.test].map(|indices| indices.iter().map(|&i| data[i].clone()).collect())
+    }
+}
+
+//shuffles len samples into train/validation/test, the validation and test fractions are rounded to whole samples
+pub fn split(len: usize, validation: f64, test: f64, rng: &mut dyn RngCore) -> Result<Partition, SproutError> {
+    check_fractions(validation, test)?;
+    let mut order: Vec<usize> = (0..len).collect();
+    order.shuffle(rng);
+    let partition = take_partition(order, validation, test);
+    check_partition(&partition)?;
+    Ok(partition)
+}
+
+//like split, but every class (labels[i] is the class of sample i) is split in the same proportions
+pub fn stratified_split(labels: &[usize], validation: f64, test: f64, rng: &mut dyn RngCore) -> Result<Partition, SproutError> {
+    check_fractions(validation, test)?;
+    let mut partition = Partition::default();
+    for members in classes(labels, rng).into_values() {
+        let class = take_partition(members, validation, test);
+        partition.train.extend(class.train);
+        partition.validation.extend(class.validation);
+        partition.test.extend(class.test);
+    }
+    //mixes the classes back together
+    for indices in [&mut partition.train, &mut partition.validation, &mut partition.test] {
+        indices.shuffle(rng);
+    }
+    check_partition(&partition)?;
+    Ok(partition)
+}
+
+fn check_fractions(validation: f64, test: f64) -> Result<(), SproutError> {
+    if !((0.0..1.0).contains(&validation) && (0.0..1.0).contains(&test) && validation + test < 1.0) {
+        return Err(SproutError::InvalidConfig(format!(
+            "validation ({}) and test ({}) fractions must be from 0 to 1 and leave something to train on", validation, test
+        )));
+    }
+    Ok(())
+}
+
+fn take_partition(order: Vec<usize>, validation: f64, test: f64) -> Partition {
+    let len = order.len() as f64;
+    let (validation, test) = ((len * validation).round() as usize, (len * test).round() as usize);
+    let test = test.min(order.len());
+    let validation = validation.min(order.len() - test);
+    Partition {
+        test: order[..test].to_vec(),
+        validation: order[test..test + validation].to_vec(),
+        train: order[test + validation..].to_vec(),
+    }
+}
+
+fn check_partition(partition: &Partition) -> Result<(), SproutError> {
+    match partition.train.is_empty() {
+        true => Err(SproutError::InvalidConfig("split leaves no training samples".to_string())),
+        false => Ok(()),
+    }
+}
+
+//class > its samples, shuffled
+fn classes(labels: &[usize], rng: &mut dyn RngCore) -> BTreeMap<usize, Vec<usize>> {
+    let mut classes: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (i, label) in labels.iter().enumerate() {
+        classes.entry(*label).or_default().push(i);
+    }
+    for members in classes.values_mut() {
+        members.shuffle(rng);
+    }
+    classes
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fold {
+    pub train: Vec<usize>,
+    pub validation: Vec<usize>,
+}
+
+//k folds whose validation sets are a shuffled partition of the samples, with sizes at most one apart
+pub fn k_fold(len: usize, k: usize, rng: &mut dyn RngCore) -> Result<Vec<Fold>, SproutError> {
+    check_folds(len, k)?;
+    let mut order: Vec<usize> = (0..len).collect();
+    order.shuffle(rng);
+    let mut start = 0;
+    let parts: Vec<Vec<usize>> = (0..k).map(|i| {
+        let size = len / k + usize::from(i < len % k);
+        start += size;
+        order[start - size..start].to_vec()
+    }).collect();
+    Ok(folds(parts))
+}
+
+//k folds where each class is spread over the validation sets as evenly as it can be
+pub fn stratified_k_fold(labels: &[usize], k: usize, rng: &mut dyn RngCore) -> Result<Vec<Fold>, SproutError> {
+    check_folds(labels.len(), k)?;
+    //dealing class after class round robin keeps both the classes and the fold sizes even
+    let mut parts = vec![vec![]; k];
+    for (i, index) in classes(labels, rng).into_values().flatten().enumerate() {
+        parts[i % k].push(index);
+    }
+    Ok(folds(parts))
+}
+
+fn check_folds(len: usize, k: usize) -> Result<(), SproutError> {
+    if k < 2 || k > len {
+        return Err(SproutError::InvalidConfig(format!("k-fold needs from 2 to {} folds for {} samples, got {}", len, len, k)));
+    }
+    Ok(())
+}
+
+fn folds(parts: Vec<Vec<usize>>) -> Vec<Fold> {
+    (0..parts.len()).map(|i| Fold {
+        train: parts.iter().enumerate().filter(|(j, _)| *j != i).flat_map(|(_, part)| part.iter().copied()).collect(),
+        validation: parts[i].clone(),
+    }).collect()
+}
+
+//the class a target stands for: its highest value, or for a single output whether it is at least 0.5
+pub fn class_of<T: Float>(target: &[T]) -> usize {
+    match target {
+        [value] => usize::from(value.as_f64() >= 0.5),
+        _ => target.iter().enumerate().max_by(|a, b| a.1.as_f64().total_cmp(&b.1.as_f64())).map_or(0, |(i, _)| i),
+    }
+}
+
+//the class of every sample for stratifying, loads the whole dataset
+pub fn labels<T: Float>(dataset: &dyn Dataset<T>) -> Result<Vec<usize>, SproutError> {
+    (0..dataset.len())
+        .map(|i| dataset.get(i).map(|sample| class_of(&sample.target)).map_err(|err| err.context(&format!("sample {}", i))))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    pub loss: f64, //mean per sample, as training reports it in cost
+    pub accuracy: f64, //share of samples predicted as their target's class, see class_of
+    pub mean_absolute_error: f64, //over every output
+}
+
+impl Metrics {
+    fn map(metrics: &[Metrics], f: impl Fn(&[f64]) -> f64) -> Metrics {
+        let column = |field: fn(&Metrics) -> f64| f(&metrics.iter().map(field).collect::<Vec<f64>>());
+        Metrics {
+            loss: column(|m| m.loss),
+            accuracy: column(|m| m.accuracy),
+            mean_absolute_error: column(|m| m.mean_absolute_error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossValidation {
+    pub folds: Vec<Metrics>, //each fold's model on its validation samples
+    pub mean: Metrics,
+    pub std_dev: Metrics, //population standard deviation across the folds
+}
+
+impl CrossValidation {
+    pub fn new(folds: Vec<Metrics>) -> Self {
+        let mean = Metrics::map(&folds, |values| values.iter().sum::<f64>() / values.len() as f64);
+        let std_dev = Metrics::map(&folds, |values| {
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / values.len() as f64).sqrt()
+        });
+        CrossValidation { folds, mean, std_dev }
+    }
+}
+
+impl fmt::Display for CrossValidation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<6} {:>12} {:>10} {:>12}", "fold", "loss", "accuracy", "mae")?;
+        let rows = self.folds.iter().enumerate().map(|(i, metrics)| (i.to_string(), metrics))
+            .chain([("mean".to_string(), &self.mean), ("std".to_string(), &self.std_dev)]);
+        for (name, metrics) in rows {
+            writeln!(f, "{:<6} {:>12.6} {:>9.2}% {:>12.6}", name, metrics.loss, 100.0 * metrics.accuracy, metrics.mean_absolute_error)?;
+        }
+        Ok(())
+    }
+}
+
+//trains a fresh copy of network (see Network::fresh, fold i is seeded with network.seed + i) on each fold's training samples
+//and evaluates it on the fold's validation samples. the loader's settings (batch size, shuffling, augmentation, ...) are used
+//for training, validation samples are not augmented. the network's preprocessing steps are fitted again on each fold's
+//(unaugmented) training samples, so nothing about the validation samples leaks into the scaling
+pub fn cross_validate<T: Float>(network: &Network<T>, loader: &DataLoader<T>, folds: &[Fold], epochs: usize) -> Result<CrossValidation, SproutError> {
+    if folds.is_empty() {
+        return Err(SproutError::InvalidConfig("cross-validation needs at least one fold".to_string()));
+    }
+    let mut metrics = Vec::with_capacity(folds.len());
+    for (i, fold) in folds.iter().enumerate() {
+        let context = format!("fold {}", i);
+        let mut model = network.try_fresh(network.seed.wrapping_add(i as u64))?;
+        if !model.preprocessing.is_empty() {
+            let inputs = fold.train.iter()
+                .map(|&j| loader.dataset().get(j).map(|sample| sample.input.into_flat()).map_err(|err| err.context(&format!("sample {}", j))))
+                .collect::<Result<Vec<Vec<T>>, SproutError>>()
+                .map_err(|err| err.context(&context))?;
+            let steps = std::mem::take(&mut model.preprocessing);
+            model.fit_preprocessing(steps, &inputs).map_err(|err| err.context(&context))?;
+        }
+        model.try_train(&loader.subset(fold.train.clone()), epochs).map_err(|err| err.context(&context))?;
+        let validation = Subset::new(Arc::clone(loader.dataset()), fold.validation.clone());
+        metrics.push(model.evaluate(&validation).map_err(|err| err.context(&context))?);
+    }
+    Ok(CrossValidation::new(metrics))
+}
@@ -0,0 +1,127 @@
+use std::{collections::{BTreeMap, HashSet}, fmt};
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::{
+    error::SproutError,
+    float::{Float, Precision},
+    network::Network,
+};
+
+//weights-only files: every parameter tensor keyed "<layer name>.<parameter name>", e.g. "dense_1.weights",
+//where layers are named by type and their position among layers of that type
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightTensor<T = f64> {
+    pub shape: Vec<usize>,
+    pub values: Vec<T>, //row-major
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Weights<T = f64> {
+    #[serde(default)]
+    pub precision: Precision,
+    pub tensors: BTreeMap<String, WeightTensor<T>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MismatchedWeight {
+    pub key: String,
+    pub expected: Vec<usize>,
+    pub found: Vec<usize>,
+}
+
+//what a load matched, keys are sorted within each list
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    pub loaded: Vec<String>,
+    pub missing: Vec<String>, //in the network but not in the weights
+    pub unexpected: Vec<String>, //in the weights but not in the network
+    pub mismatched: Vec<MismatchedWeight>,
+}
+
+impl LoadReport {
+    //every parameter of the network was loaded and nothing was left over
+    pub fn is_exact(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} loaded", self.loaded.len())?;
+        if !self.missing.is_empty() {
+            write!(f, ", missing: {}", self.missing.join(", "))?;
+        }
+        if !self.unexpected.is_empty() {
+            write!(f, ", unexpected: {}", self.unexpected.join(", "))?;
+        }
+        if !self.mismatched.is_empty() {
+            let mismatched: Vec<String> = self.mismatched.iter()
+                .map(|weight| format!("{} (expected {:?}, found {:?})", weight.key, weight.expected, weight.found))
+                .collect();
+            write!(f, ", shape mismatch: {}", mismatched.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn layer_names<T: Float>(network: &Network<T>) -> Vec<String> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    network.layers.iter().map(|layer| {
+        let count = counts.entry(layer.type_name()).or_insert(0);
+        *count += 1;
+        format!("{}_{}", layer.type_name(), *count - 1)
+    }).collect()
+}
+
+pub fn collect<T: Float>(network: &Network<T>) -> Weights<T> {
+    let mut tensors = BTreeMap::new();
+    for (layer, layer_name) in network.layers.iter().zip(layer_names(network)) {
+        for ((params, name), shape) in layer.parameters().into_iter().zip(layer.parameter_names()).zip(layer.parameter_shapes()) {
+            tensors.insert(format!("{}.{}", layer_name, name), WeightTensor { shape, values: params.to_vec() });
+        }
+    }
+    Weights { precision: T::PRECISION, tensors }
+}
+
+//strict loads fail without touching the network unless the weights match it exactly,
+//otherwise every key with a matching shape is loaded and the rest is reported
+pub fn apply<T: Float>(network: &mut Network<T>, weights: &Weights<T>, strict: bool) -> Result<LoadReport, SproutError> {
+    let mut report = LoadReport::default();
+    let mut known = HashSet::new();
+    let mut matched = vec![]; //layer, parameter, key
+    for (i, (layer, layer_name)) in network.layers.iter().zip(layer_names(network)).enumerate() {
+        let shapes = layer.parameter_shapes();
+        if layer.parameters().iter().zip(&shapes).any(|(params, shape)| params.len() != shape.iter().product::<usize>()) {
+            return Err(SproutError::ShapeMismatch(format!("{} has no parameters yet, build the network before loading weights", layer_name)));
+        }
+        for (p, (name, shape)) in layer.parameter_names().into_iter().zip(shapes).enumerate() {
+            let key = format!("{}.{}", layer_name, name);
+            known.insert(key.clone());
+            match weights.tensors.get(&key) {
+                None => report.missing.push(key),
+                Some(tensor) if tensor.shape != shape || tensor.values.len() != shape.iter().product::<usize>() =>
+                    report.mismatched.push(MismatchedWeight { key, expected: shape, found: tensor.shape.clone() }),
+                Some(_) => matched.push((i, p, key)),
+            }
+        }
+    }
+    report.unexpected = weights.tensors.keys().filter(|key| !known.contains(*key)).cloned().collect();
+    report.missing.sort();
+    report.mismatched.sort_by(|a, b| a.key.cmp(&b.key));
+    if strict && !report.is_exact() {
+        return Err(SproutError::ShapeMismatch(format!("weights don't match the network: {}", report)));
+    }
+
+    for (i, p, key) in matched {
+        network.layers[i].parameters_mut()[p].copy_from_slice(&weights.tensors[&key].values);
+        report.loaded.push(key);
+    }
+    report.loaded.sort();
+    if !report.loaded.is_empty() {
+        //optimizer moments belong to the old weights
+        network.optimizer_states.clear();
+    }
+    Ok(report)
+}
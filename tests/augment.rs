@@ -0,0 +1,164 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sprout::{
+    augment::{Augmentation, Transform},
+    dataset::{DataLoader, Sample},
+    error::SproutError,
+    network::Image,
+    tensor::Tensor,
+};
+
+fn rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+//3 channels of 7 x 5, not square so rows and cols can't be mixed up
+fn image(seed: usize) -> Image<f64> {
+    (0..3).map(|c| (0..7).map(|r| (0..5).map(|k| ((seed + c * 11 + r * 5 + k) % 13) as f64 / 13.0).collect()).collect()).collect()
+}
+
+fn shape(image: &Image<f64>) -> Vec<Vec<usize>> {
+    image.iter().map(|channel| channel.iter().map(Vec::len).collect()).collect()
+}
+
+fn transforms() -> Vec<Transform> {
+    vec![
+        Transform::Crop { padding: 2 },
+        Transform::HorizontalFlip(1.0),
+        Transform::VerticalFlip(0.5),
+        Transform::Rotation(30.0),
+        Transform::Translation(0.2),
+        Transform::Scale { min: 0.5, max: 1.5 },
+        Transform::Brightness(0.1),
+        Transform::Contrast(0.3),
+        Transform::Noise(0.05),
+        Transform::Cutout(9),
+        Transform::Erasing { probability: 1.0, area: [0.1, 0.4] },
+    ]
+}
+
+#[test]
+fn transforms_keep_the_shape() {
+    for transform in transforms() {
+        for seed in 0..5 {
+            let mut augmented = image(seed as usize);
+            transform.apply(&mut augmented, &mut rng(seed));
+            assert_eq!(shape(&augmented), shape(&image(0)), "{:?}", transform);
+            assert!(augmented.iter().flatten().flatten().all(|value| value.is_finite()));
+        }
+    }
+    //a certain flip mirrors each row
+    let mut flipped = image(1);
+    Transform::HorizontalFlip(1.0).apply(&mut flipped, &mut rng(0));
+    assert_eq!(flipped[2][3], image(1)[2][3].iter().rev().copied().collect::<Vec<f64>>());
+}
+
+#[test]
+fn same_seed_same_batches() {
+    let augmentation = transforms().into_iter().fold(Augmentation::new(), Augmentation::then).mixup(0.4);
+    let samples: Vec<(Image<f64>, Vec<f64>)> = (0..10).map(|i| (image(i), vec![(i % 2) as f64, 1.0 - (i % 2) as f64])).collect();
+    let loader = DataLoader::new(samples, 4).augment(augmentation);
+    let epoch = |loader: &DataLoader<f64>, seed: u64| loader.batches(&mut rng(seed)).collect::<Result<Vec<Vec<Sample<f64>>>, SproutError>>().unwrap();
+    let first = epoch(&loader, 3);
+    assert_eq!(first.len(), 3);
+    assert_eq!(epoch(&loader, 3), first);
+    //loading on another thread draws the same
+    assert_eq!(epoch(&loader.clone().prefetch(2), 3), first);
+    assert_ne!(epoch(&loader, 4), first);
+}
+
+//one-hot targets where sample i is class i, so a mixed target shows both the partner and how much was kept
+fn batch(flat: bool) -> Vec<Sample<f64>> {
+    (0..6).map(|i| {
+        let value = (i + 1) as f64;
+        let input = match flat {
+            true => Tensor::Flat(vec![value; 4]),
+            false => Tensor::Image(vec![vec![vec![value; 8]; 6]; 2]),
+        };
+        let mut target = vec![0.0; 6];
+        target[i] = 1.0;
+        Sample { input, target }
+    }).collect()
+}
+
+//the kept share of each sample and its partner, None for a sample paired with itself
+fn mixed(sample: &Sample<f64>, i: usize) -> Option<(f64, usize)> {
+    assert!((sample.target.iter().sum::<f64>() - 1.0).abs() < 1e-12, "{:?}", sample.target);
+    let partner = (0..6).filter(|j| *j != i).find(|j| sample.target[*j] > 0.0)?;
+    assert!((sample.target[i] + sample.target[partner] - 1.0).abs() < 1e-12);
+    Some((sample.target[i], partner))
+}
+
+#[test]
+fn mixup_blends_inputs_and_targets_alike() {
+    for flat in [true, false] {
+        for seed in 0..10 {
+            let mut samples = batch(flat);
+            Augmentation::new().mixup(0.4).apply_batch(&mut samples, &mut rng(seed)).unwrap();
+            let kept: Vec<f64> = samples.iter().enumerate().filter_map(|(i, sample)| {
+                let Some((keep, partner)) = mixed(sample, i) else {
+                    assert_eq!(sample, &batch(flat)[i]);
+                    return None;
+                };
+                let expected = keep * (i + 1) as f64 + (1.0 - keep) * (partner + 1) as f64;
+                assert!(sample.input.to_flat().iter().all(|value| (value - expected).abs() < 1e-12));
+                Some(keep)
+            }).collect();
+            //one lambda for the whole batch
+            assert!(kept.windows(2).all(|pair| (pair[0] - pair[1]).abs() < 1e-12), "{:?}", kept);
+        }
+    }
+}
+
+#[test]
+fn cutmix_targets_follow_the_pasted_area() {
+    for seed in 0..10 {
+        let mut samples = batch(false);
+        Augmentation::new().cutmix(1.0).apply_batch(&mut samples, &mut rng(seed)).unwrap();
+        for (i, sample) in samples.iter().enumerate() {
+            let values = sample.input.to_flat();
+            let own = values.iter().filter(|value| **value == (i + 1) as f64).count();
+            match mixed(sample, i) {
+                Some((keep, partner)) => {
+                    let pasted = values.iter().filter(|value| **value == (partner + 1) as f64).count();
+                    assert_eq!(own + pasted, values.len());
+                    assert!((keep - own as f64 / values.len() as f64).abs() < 1e-12);
+                },
+                None => assert_eq!(own, values.len()),
+            }
+        }
+    }
+    let mut flat = batch(true);
+    assert!(matches!(Augmentation::new().cutmix(1.0).apply_batch(&mut flat, &mut rng(0)), Err(SproutError::ShapeMismatch(_))));
+}
+
+#[test]
+fn out_of_range_parameters_are_rejected() {
+    let invalid = [
+        Augmentation::new().flip_horizontal(1.5),
+        Augmentation::new().flip_vertical(-0.1),
+        Augmentation::new().rotate(f64::NAN),
+        Augmentation::new().translate(2.0),
+        Augmentation::new().scale(0.0, 1.0),
+        Augmentation::new().scale(1.5, 1.0),
+        Augmentation::new().brightness(-0.1),
+        Augmentation::new().contrast(1.5),
+        Augmentation::new().noise(f64::INFINITY),
+        Augmentation::new().cutout(0),
+        Augmentation::new().erase(1.2, 0.1, 0.2),
+        Augmentation::new().erase(0.5, 0.3, 0.2),
+        Augmentation::new().erase(0.5, 0.0, 0.2),
+        Augmentation::new().erase(0.5, 0.1, 1.2),
+        Augmentation::new().mixup(0.0),
+        Augmentation::new().mixup(-1.0),
+        Augmentation::new().cutmix(f64::NAN),
+    ];
+    for augmentation in invalid {
+        assert!(matches!(augmentation.validate(), Err(SproutError::InvalidConfig(_))), "{:?}", augmentation);
+    }
+    transforms().into_iter().fold(Augmentation::new(), Augmentation::then).cutmix(0.2).validate().unwrap();
+
+    //the loader checks before handing out batches
+    let loader = DataLoader::new(vec![(image(0), vec![1.0])], 1).augment(Augmentation::new().mixup(0.0));
+    assert!(loader.validate().is_err());
+}
@@ -0,0 +1,129 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use serde_json::Value;
+use sprout::{
+    activation::ActivationFunction::*,
+    checkpoint::{self, Checkpointing},
+    conv_params::PaddingType::*,
+    layer,
+    loss_function::LossType::*,
+    network::Network,
+    optimizer::{Optimizer, Schedule},
+    shape::Shape,
+};
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("sprout-checkpoint").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn data() -> Vec<[Vec<f64>; 2]> {
+    (0..37).map(|i| {
+        let (a, b) = ((i % 7) as f64 / 7.0, (i % 5) as f64 / 5.0);
+        [vec![a, b, a * b], if a + b > 0.8 { vec![1.0, 0.0] } else { vec![0.0, 1.0] }]
+    }).collect()
+}
+
+fn names(dir: &Path) -> Vec<String> {
+    checkpoint::list(dir).unwrap().iter().map(|path| path.file_name().unwrap().to_str().unwrap().to_string()).collect()
+}
+
+//the shuffle rng and sample order as a checkpoint records them
+fn training_state(network: &Network, dir: &Path) -> Value {
+    let path = dir.join("state.json");
+    network.try_save_checkpoint(path.to_str().unwrap()).unwrap();
+    let mut value: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    let mut state = value["training_state"].take();
+    state.as_object_mut().unwrap().remove("checkpointing");
+    state
+}
+
+type Image = Vec<Vec<Vec<f64>>>;
+
+fn network() -> Network {
+    let mut nn = Network::new(vec![layer::dense([3, 8], TanH), layer::dense([8, 2], SoftMax)], 0.05, 4, MSE);
+    nn.optimizer = Optimizer::adam();
+    nn.schedule = Schedule::Step { every: 2, gamma: 0.5 };
+    nn.set_seed(9);
+    nn.build(Shape::Flat(3)).unwrap();
+    nn
+}
+
+//k + m epochs in one run end bit for bit where k epochs, a checkpoint and m more after resuming do
+#[test]
+fn resume_is_bit_for_bit() {
+    let (k, m) = (4, 3);
+    let dir = dir("resume");
+    let mut full = network();
+    full.try_dense_train(data(), k + m).unwrap();
+
+    let mut part = network();
+    part.set_checkpointing(Checkpointing::new(&dir).every(1).keep_last(0));
+    part.try_dense_train(data(), k).unwrap();
+    drop(part);
+    let latest = checkpoint::latest(&dir).unwrap().unwrap();
+    let mut resumed = Network::<f64>::try_resume_from(latest.to_str().unwrap()).unwrap();
+    assert_eq!(resumed.epoch, k);
+    resumed.try_dense_train(data(), m).unwrap();
+
+    assert_eq!(resumed.epoch, full.epoch);
+    assert_eq!(resumed.named_weights().tensors, full.named_weights().tensors);
+    assert_eq!(resumed.cost.to_bits(), full.cost.to_bits());
+    assert_eq!(training_state(&resumed, &dir), training_state(&full, &dir));
+}
+
+#[test]
+fn conv_resume_is_bit_for_bit() {
+    let images: Vec<(Image, Vec<f64>)> = (0..9).map(|i| {
+        let image = vec![(0..6).map(|r| (0..6).map(|k| ((i + r * 7 + k * 3) % 11) as f64 / 11.0).collect()).collect()];
+        (image, if i % 2 == 0 { vec![1.0, 0.0] } else { vec![0.0, 1.0] })
+    }).collect();
+    let dir = dir("conv");
+    let mut base: Network = Network::new(vec![layer::conv(3, Same, 1, ReLU), layer::pool(2, 2), layer::dense([9, 2], Sigmoid)], 0.05, 2, MSE);
+    base.optimizer = Optimizer::momentum();
+    base.set_seed(4);
+    base.build(Shape::Image([1, 6, 6])).unwrap();
+
+    let mut full = base.clone();
+    full.try_conv_train(images.clone(), 5).unwrap();
+    let mut part = base.clone();
+    part.try_conv_train(images.clone(), 2).unwrap();
+    let path = dir.join("conv.json");
+    part.try_save_checkpoint(path.to_str().unwrap()).unwrap();
+    let mut resumed = Network::<f64>::try_resume_from(path.to_str().unwrap()).unwrap();
+    resumed.try_conv_train(images, 3).unwrap();
+    assert_eq!(resumed.named_weights().tensors, full.named_weights().tensors);
+    assert_eq!(training_state(&resumed, &dir), training_state(&full, &dir));
+}
+
+#[test]
+fn old_checkpoints_are_rotated_away() {
+    let dir = dir("rotate");
+    let mut nn = network();
+    nn.set_checkpointing(Checkpointing::new(&dir).every(2).keep_last(2));
+    nn.try_dense_train(data(), 7).unwrap();
+    assert_eq!(names(&dir), ["checkpoint-000004.json", "checkpoint-000006.json"]);
+
+    //resuming carries the settings on and keeps rotating
+    let mut resumed = Network::<f64>::try_resume_from(checkpoint::latest(&dir).unwrap().unwrap().to_str().unwrap()).unwrap();
+    resumed.try_dense_train(data(), 4).unwrap();
+    assert_eq!(names(&dir), ["checkpoint-000008.json", "checkpoint-000010.json"]);
+
+    //keep_last(0) keeps everything
+    let dir = self::dir("keep-all");
+    let mut nn = network();
+    nn.set_checkpointing(Checkpointing::new(&dir).keep_last(0));
+    nn.try_dense_train(data(), 3).unwrap();
+    assert_eq!(names(&dir).len(), 3);
+}
+
+#[test]
+fn saved_model_is_not_a_checkpoint() {
+    let dir = dir("model");
+    let name = dir.join("model");
+    network().try_save_model(name.to_str().unwrap()).unwrap();
+    assert!(Network::<f64>::try_resume_from(&format!("{}.json", name.to_str().unwrap())).is_err());
+}
@@ -0,0 +1,99 @@
+use sprout::{
+    datasets::csv::{CsvLoader, Encoding, Missing, Tabular},
+    error::SproutError,
+};
+
+#[test]
+fn quoted_fields() {
+    let text = "name,size,price\n\
+                \"Smith, John\",1,10\n\
+                \"say \"\"hi\"\"\",2,20\n\
+                \"two\nlines\",3,30\r\n\
+                \n\
+                plain,4,40";
+    let data = CsvLoader::new("quoted.csv").target("price").categorical("name", Encoding::Label).parse::<f64>(text).unwrap();
+    assert_eq!(data.categories["name"], ["Smith, John", "plain", "say \"hi\"", "two\nlines"]);
+    assert_eq!(data.features, [vec![0.0, 1.0], vec![2.0, 2.0], vec![3.0, 3.0], vec![1.0, 4.0]]);
+    assert_eq!(data.targets, [vec![10.0], vec![20.0], vec![30.0], vec![40.0]]);
+    assert_eq!(data.feature_names, ["name", "size"]);
+
+    let err = CsvLoader::new("open.csv").target(1).header(false).parse::<f64>("a,1\n\"b,2\nc,3").unwrap_err();
+    assert!(matches!(err, SproutError::Format(ref message) if message.contains("line 2")), "{}", err);
+}
+
+//errors name the line in the file, a record spanning two lines moves the count on by two
+#[test]
+fn field_count_mismatch() {
+    let err = CsvLoader::new("short.csv").target("c").parse::<f64>("a,b,c\n1,2,3\n4,5\n").unwrap_err();
+    assert!(matches!(err, SproutError::Format(ref message) if message.contains("line 3 has 2 fields but the CSV has 3 columns")), "{}", err);
+
+    let err = CsvLoader::new("long.csv").target("c").parse::<f64>("a,b,c\n\"1\n1\",2,3\n4,5,6,7\n").unwrap_err();
+    assert!(matches!(err, SproutError::Format(ref message) if message.contains("line 4 has 4 fields")), "{}", err);
+
+    let err = CsvLoader::new("name.csv").target("d").parse::<f64>("a,b,c\n1,2,3\n").unwrap_err();
+    assert!(matches!(err, SproutError::InvalidConfig(ref message) if message.contains("no column named 'd'")), "{}", err);
+    let err = CsvLoader::new("index.csv").target(3).parse::<f64>("a,b,c\n1,2,3\n").unwrap_err();
+    assert!(matches!(err, SproutError::InvalidConfig(_)), "{}", err);
+}
+
+const MISSING: &str = "x,y,color,t\n\
+                       1,10,red,1\n\
+                       NA,20,blue,2\n\
+                       3,,red,3\n\
+                       8,40,?,4\n\
+                       5,50,blue,\n";
+
+fn load(missing: Missing) -> Result<Tabular, SproutError> {
+    CsvLoader::new("missing.csv").target("t").categorical("color", Encoding::Label).missing(missing).parse(MISSING)
+}
+
+#[test]
+fn missing_policies() {
+    //the row missing its target goes whatever the policy
+    //categories come from the rows that are kept, red is the only one left
+    let data = load(Missing::Drop).unwrap();
+    assert_eq!(data.features, [vec![1.0, 10.0, 0.0]]);
+    assert_eq!(data.categories["color"], ["red"]);
+    assert_eq!(data.dropped, 4);
+
+    //x is 1, ?, 3, 8 and y is 10, 20, ?, 40 over the kept rows, color imputes to red (2 of 3)
+    let data = load(Missing::Mean).unwrap();
+    assert_eq!(data.dropped, 1);
+    assert_eq!(data.features, [vec![1.0, 10.0, 1.0], vec![4.0, 20.0, 0.0], vec![3.0, 70.0 / 3.0, 1.0], vec![8.0, 40.0, 1.0]]);
+    assert_eq!(data.targets, [vec![1.0], vec![2.0], vec![3.0], vec![4.0]]);
+
+    let data = load(Missing::Median).unwrap();
+    assert_eq!(data.features, [vec![1.0, 10.0, 1.0], vec![3.0, 20.0, 0.0], vec![3.0, 20.0, 1.0], vec![8.0, 40.0, 1.0]]);
+
+    let data = load(Missing::Constant(-1.0)).unwrap();
+    assert_eq!(data.features, [vec![1.0, 10.0, 1.0], vec![-1.0, 20.0, 0.0], vec![3.0, -1.0, 1.0], vec![8.0, 40.0, 1.0]]);
+
+    //an even count of present values takes the middle two
+    let data = CsvLoader::new("even.csv").target("t").missing(Missing::Median).parse::<f64>("x,t\n1,0\n2,0\n,0\n4,0\n10,0\n").unwrap();
+    assert_eq!(data.features[2], [3.0]);
+
+    let err = CsvLoader::new("empty.csv").target("t").missing(Missing::Mean).parse::<f64>("x,t\n,1\nNA,2\n").unwrap_err();
+    assert!(matches!(err, SproutError::InvalidConfig(ref message) if message.contains("column 'x'")), "{}", err);
+    let data = CsvLoader::new("empty.csv").target("t").missing(Missing::Constant(0.5)).parse::<f64>("x,t\n,1\nNA,2\n").unwrap();
+    assert_eq!(data.features, [vec![0.5], vec![0.5]]);
+}
+
+//an imputed categorical column with no values at all would encode to zero one-hot columns
+#[test]
+fn all_missing_categorical_column_is_rejected() {
+    for encoding in [Encoding::OneHot, Encoding::Label] {
+        for missing in [Missing::Mean, Missing::Median, Missing::Constant(0.0)] {
+            let result = CsvLoader::new("colors.csv").target("t").categorical("color", encoding).missing(missing)
+                .parse::<f64>("x,color,t\n1,,0\n2,NA,1\n3,?,0\n");
+            match result {
+                Err(SproutError::InvalidConfig(message)) => assert!(message.contains("column 'color'") && message.contains("no categories"), "{}", message),
+                Err(err) => panic!("expected an invalid configuration, got {}", err),
+                Ok(data) => panic!("loaded {:?}", data.features),
+            }
+        }
+    }
+    //one value is enough to impute the rest
+    let data = CsvLoader::new("colors.csv").target("t").categorical("color", Encoding::OneHot).missing(Missing::Mean)
+        .parse::<f64>("x,color,t\n1,,0\n2,green,1\n3,?,0\n").unwrap();
+    assert_eq!(data.features, [vec![1.0, 1.0], vec![2.0, 1.0], vec![3.0, 1.0]]);
+}
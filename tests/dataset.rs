@@ -0,0 +1,113 @@
+use std::{fs, path::Path, sync::Arc};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sprout::{
+    dataset::{DataLoader, Dataset, FileDataset, Sample, Subset},
+    error::SproutError,
+    tensor::Tensor,
+};
+
+fn rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+//sample i has input [i], so a batch shows which samples it holds
+fn samples(n: usize) -> Vec<[Vec<f64>; 2]> {
+    (0..n).map(|i| [vec![i as f64], vec![1.0]]).collect()
+}
+
+fn epoch(loader: &DataLoader<f64>, seed: u64) -> Vec<Vec<Sample<f64>>> {
+    loader.batches(&mut rng(seed)).collect::<Result<Vec<Vec<Sample<f64>>>, SproutError>>().unwrap()
+}
+
+fn inputs(batches: &[Vec<Sample<f64>>]) -> Vec<Vec<f64>> {
+    batches.iter().map(|batch| batch.iter().flat_map(|sample| sample.input.to_flat()).collect()).collect()
+}
+
+fn out_of_range(result: Result<Sample<f64>, SproutError>, expected: &str) {
+    match result {
+        Err(SproutError::InvalidConfig(message)) => assert!(message.contains(expected), "{}", message),
+        other => panic!("expected an out of range error, got {:?}", other),
+    }
+}
+
+#[test]
+fn batch_count_with_and_without_drop_last() {
+    let loader = DataLoader::new(samples(10), 3).shuffle(false);
+    assert_eq!(inputs(&epoch(&loader, 0)), vec![vec![0.0, 1.0, 2.0], vec![3.0, 4.0, 5.0], vec![6.0, 7.0, 8.0], vec![9.0]]);
+    assert_eq!(loader.samples_per_epoch(), 10);
+
+    let dropping = loader.clone().drop_last(true);
+    assert_eq!(epoch(&dropping, 0).len(), 3);
+    assert_eq!(dropping.samples_per_epoch(), 9);
+
+    //nothing is dropped when the batches come out even
+    let even = DataLoader::new(samples(9), 3).drop_last(true);
+    assert_eq!(epoch(&even, 0).len(), 3);
+    assert_eq!(even.samples_per_epoch(), 9);
+
+    //a shuffled epoch still visits every sample once
+    let mut seen: Vec<f64> = inputs(&epoch(&DataLoader::new(samples(10), 4), 7)).concat();
+    seen.sort_by(f64::total_cmp);
+    assert_eq!(seen, (0..10).map(|i| i as f64).collect::<Vec<f64>>());
+
+    assert!(DataLoader::new(samples(2), 3).drop_last(true).validate().is_err());
+    assert!(DataLoader::new(samples(2), 0).validate().is_err());
+}
+
+#[test]
+fn prefetch_loads_the_same_batches() {
+    for drop_last in [false, true] {
+        let loader = DataLoader::new(samples(11), 4).drop_last(drop_last);
+        for seed in 0..3 {
+            let batches = epoch(&loader, seed);
+            assert_eq!(epoch(&loader.clone().prefetch(1), seed), batches);
+            assert_eq!(epoch(&loader.clone().prefetch(8), seed), batches);
+        }
+    }
+}
+
+#[test]
+fn indexing_past_the_end_is_an_error() {
+    out_of_range(samples(3).get(3), "sample 3 is out of range for a dataset of 3");
+    let flat: Vec<Sample<f64>> = samples(2).into_iter().map(Sample::from).collect();
+    out_of_range(flat.get(5), "sample 5");
+
+    let dir = std::env::temp_dir().join("sprout-dataset");
+    fs::create_dir_all(&dir).unwrap();
+    let paths: Vec<_> = (0..3).map(|i| {
+        let path = dir.join(format!("{}.txt", i));
+        fs::write(&path, i.to_string()).unwrap();
+        path
+    }).collect();
+    let read = |path: &Path| -> Result<Sample<f64>, SproutError> {
+        let value = fs::read_to_string(path)?.parse::<f64>().map_err(|err| SproutError::Format(err.to_string()))?;
+        Ok(Sample { input: Tensor::Flat(vec![value]), target: vec![1.0] })
+    };
+    let files = FileDataset::new(paths.clone(), read);
+    assert_eq!(files.get(2).unwrap().input, Tensor::Flat(vec![2.0]));
+    out_of_range(files.get(3), "sample 3 is out of range for a dataset of 3");
+
+    //a file that can't be read names its path
+    let missing = FileDataset::new(vec![dir.join("missing.txt")], read);
+    let err = missing.get(0).unwrap_err();
+    assert!(err.to_string().contains("missing.txt"), "{}", err);
+
+    let subset = Subset::new(Arc::new(files), vec![2, 0]);
+    assert_eq!(subset.get(0).unwrap().input, Tensor::Flat(vec![2.0]));
+    out_of_range(subset.get(2), "sample 2 is out of range for a dataset of 2");
+    //indices past the inner dataset fail when they are read
+    let dangling = Subset::new(Arc::new(samples(3)), vec![0, 4]);
+    out_of_range(dangling.get(1), "sample 4 is out of range for a dataset of 3");
+
+    //the loader passes the error on, with or without prefetching
+    let loader = DataLoader::new(samples(3), 2);
+    for loader in [loader.clone(), loader.prefetch(2)] {
+        let mut batches = loader.batches_in(&[0, 1, 5], &mut rng(0));
+        assert!(batches.next().unwrap().is_ok());
+        let err = batches.next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("sample 5"), "{}", err);
+        assert!(batches.next().is_none());
+    }
+}
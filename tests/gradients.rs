@@ -0,0 +1,151 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_params::PaddingType::*,
+    layer::{self, Layer},
+    shape::Shape,
+    tensor::Tensor,
+};
+
+//the loss is sum(coefficients . outputs) per sample, so the output errors handed to backward are the coefficients
+
+fn build(mut layer: Box<dyn Layer<f64>>, input: Shape) -> Box<dyn Layer<f64>> {
+    layer.build(input, &mut ChaCha8Rng::seed_from_u64(3)).unwrap();
+    layer
+}
+
+fn sample(shape: Shape, seed: usize) -> Tensor<f64> {
+    let values = (0..shape.size()).map(|i| ((i * 7 + seed * 13) % 17) as f64 / 17.0 - 0.5).collect();
+    Tensor::from_flat(values, shape)
+}
+
+fn coefficients(len: usize, seed: usize) -> Vec<f64> {
+    (0..len).map(|i| ((i * 5 + seed * 3) % 11) as f64 / 11.0 - 0.4).collect()
+}
+
+fn loss(layer: &dyn Layer<f64>, inputs: &[Tensor<f64>], errors: &[Tensor<f64>]) -> f64 {
+    inputs.iter().zip(errors)
+        .map(|(input, error)| layer.predict(input).to_flat().iter().zip(error.to_flat()).map(|(o, c)| o * c).sum::<f64>())
+        .sum()
+}
+
+fn errors_for(layer: &dyn Layer<f64>, inputs: &[Tensor<f64>]) -> Vec<Tensor<f64>> {
+    inputs.iter().enumerate().map(|(s, input)| {
+        let output = layer.predict(input);
+        Tensor::from_flat(coefficients(output.to_flat().len(), s), output.shape())
+    }).collect()
+}
+
+fn check_gradients(mut layer: Box<dyn Layer<f64>>, inputs: Vec<Tensor<f64>>) {
+    let eps = 1e-6;
+    let errors = errors_for(layer.as_ref(), &inputs);
+    let (_, cache) = layer.forward(inputs.clone());
+    let (input_gradients, parameter_gradients) = layer.backward(&cache, errors.clone());
+
+    for (p, analytic) in parameter_gradients.iter().enumerate() {
+        assert_eq!(analytic.len(), layer.parameters()[p].len());
+        for (i, analytic) in analytic.iter().enumerate() {
+            let original = layer.parameters()[p][i];
+            layer.parameters_mut()[p][i] = original + eps;
+            let plus = loss(layer.as_ref(), &inputs, &errors);
+            layer.parameters_mut()[p][i] = original - eps;
+            let minus = loss(layer.as_ref(), &inputs, &errors);
+            layer.parameters_mut()[p][i] = original;
+            let numeric = (plus - minus) / (2.0 * eps);
+            assert!((numeric - analytic).abs() < 1e-6, "parameter {} [{}]: numeric {} analytic {}", p, i, numeric, analytic);
+        }
+    }
+
+    for (s, input) in inputs.iter().enumerate() {
+        let analytic = input_gradients[s].to_flat();
+        let values = input.to_flat();
+        for i in 0..values.len() {
+            let nudged = |delta: f64| {
+                let mut values = values.clone();
+                values[i] += delta;
+                Tensor::from_flat(values, input.shape())
+            };
+            let plus = loss(layer.as_ref(), &[nudged(eps)], &errors[s..=s]);
+            let minus = loss(layer.as_ref(), &[nudged(-eps)], &errors[s..=s]);
+            let numeric = (plus - minus) / (2.0 * eps);
+            assert!((numeric - analytic[i]).abs() < 1e-6, "sample {} input [{}]: numeric {} analytic {}", s, i, numeric, analytic[i]);
+        }
+    }
+}
+
+fn inputs(shape: Shape, count: usize) -> Vec<Tensor<f64>> {
+    (0..count).map(|s| sample(shape, s)).collect()
+}
+
+//softmax isn't checked: its layers take the error as the gradient of the pre-activations, which is what
+//cross-entropy hands them
+#[test]
+fn dense_gradients() {
+    for activation in [Sigmoid, TanH, ReLU] {
+        let shape = Shape::Flat(5);
+        check_gradients(build(layer::dense([5, 3], activation), shape), inputs(shape, 3));
+    }
+}
+
+#[test]
+fn dense_gradients_flatten_images() {
+    let shape = Shape::Image([2, 3, 3]);
+    check_gradients(build(layer::dense([18, 4], TanH), shape), inputs(shape, 2));
+}
+
+#[test]
+fn conv_gradients() {
+    let shape = Shape::Image([2, 6, 6]);
+    for padding in [Valid, Same, Full] {
+        for stride in [1, 2] {
+            for activation in [Sigmoid, TanH] {
+                check_gradients(build(layer::conv(3, padding.clone(), stride, activation), shape), inputs(shape, 2));
+            }
+        }
+    }
+}
+
+#[test]
+fn pool_gradients() {
+    let shape = Shape::Image([2, 6, 6]);
+    for (kernel, stride) in [(2, 2), (3, 1), (2, 1)] {
+        check_gradients(build(layer::pool(kernel, stride), shape), inputs(shape, 2));
+    }
+}
+
+//a batch of N sums what N single-sample passes give, and hands back the same input gradients
+fn check_batch(layer: Box<dyn Layer<f64>>, shape: Shape) {
+    let inputs = inputs(shape, 5);
+    let errors = errors_for(layer.as_ref(), &inputs);
+    let (_, cache) = layer.forward(inputs.clone());
+    let (batch_inputs, batch_parameters) = layer.backward(&cache, errors.clone());
+
+    let mut summed: Vec<Vec<f64>> = layer.parameters().iter().map(|params| vec![0.0; params.len()]).collect();
+    for (s, input) in inputs.iter().enumerate() {
+        let (_, cache) = layer.forward(vec![input.clone()]);
+        let (single_inputs, single_parameters) = layer.backward(&cache, vec![errors[s].clone()]);
+        assert_eq!(single_inputs[0], batch_inputs[s]);
+        for (sum, gradients) in summed.iter_mut().zip(single_parameters) {
+            for (sum, gradient) in sum.iter_mut().zip(gradients) {
+                *sum += gradient;
+            }
+        }
+    }
+    for (summed, batch) in summed.iter().zip(&batch_parameters) {
+        for (a, b) in summed.iter().zip(batch) {
+            assert!((a - b).abs() < 1e-12, "summed {} batch {}", a, b);
+        }
+    }
+}
+
+#[test]
+fn batch_sums_single_samples() {
+    for activation in [TanH, SoftMax] {
+        check_batch(build(layer::dense([6, 4], activation), Shape::Flat(6)), Shape::Flat(6));
+    }
+    for padding in [Valid, Same] {
+        check_batch(build(layer::conv(3, padding, 1, Sigmoid), Shape::Image([3, 5, 5])), Shape::Image([3, 5, 5]));
+    }
+    check_batch(build(layer::pool(2, 2), Shape::Image([3, 6, 6])), Shape::Image([3, 6, 6]));
+}
@@ -0,0 +1,126 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use image::{Rgb, RgbImage};
+use sprout::{
+    dataset::{DataLoader, Dataset},
+    datasets::image_folder::{ColorMode, ImageFolder},
+    error::SproutError,
+    shape::Shape,
+};
+
+//root/<class>/<image>, every image of a class is filled with one color
+fn root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join("sprout-image-folder").join(name);
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    root
+}
+
+fn png(path: &Path, rows: u32, cols: u32, color: [u8; 3]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    RgbImage::from_pixel(cols, rows, Rgb(color)).save(path).unwrap();
+}
+
+fn dims(image: &[Vec<Vec<f64>>]) -> [usize; 3] {
+    [image.len(), image[0].len(), image[0][0].len()]
+}
+
+fn classes(root: &Path) {
+    png(&root.join("zebra/b.png"), 4, 6, [255, 0, 0]);
+    png(&root.join("zebra/a.png"), 4, 6, [255, 0, 0]);
+    png(&root.join("ant/0.png"), 4, 6, [0, 255, 0]);
+    png(&root.join("mouse/x.PNG"), 2, 3, [0, 0, 255]);
+}
+
+#[test]
+fn classes_are_sorted_directory_names() {
+    let root = root("classes");
+    classes(&root);
+    //hidden directories and files next to the classes aren't classes
+    png(&root.join(".thumbnails/0.png"), 4, 6, [0, 0, 0]);
+    fs::write(root.join("labels.txt"), "zebra ant mouse").unwrap();
+
+    let folder = ImageFolder::<f64>::new(&root).unwrap();
+    assert_eq!(folder.classes, vec!["ant", "mouse", "zebra"]);
+    assert_eq!(folder.class_index("zebra"), Some(2));
+    assert_eq!(folder.class_to_index().into_iter().collect::<Vec<(String, usize)>>(), vec![
+        ("ant".to_string(), 0), ("mouse".to_string(), 1), ("zebra".to_string(), 2),
+    ]);
+    //samples go class by class, sorted by file name within each
+    let names: Vec<(&str, usize)> = folder.samples.iter()
+        .map(|(path, class)| (path.file_name().unwrap().to_str().unwrap(), *class))
+        .collect();
+    assert_eq!(names, vec![("0.png", 0), ("x.PNG", 1), ("a.png", 2), ("b.png", 2)]);
+    assert_eq!(folder.get(3).unwrap().target, vec![0.0, 0.0, 1.0]);
+    assert!(matches!(folder.get(4), Err(SproutError::InvalidConfig(_))));
+}
+
+#[test]
+fn gray_and_rgb_channels() {
+    let root = root("channels");
+    classes(&root);
+    let folder = ImageFolder::<f64>::new(&root).unwrap();
+
+    //gray is the default, one plane
+    let gray = folder.image(0).unwrap();
+    assert_eq!(dims(&gray), [1, 4, 6]);
+    let green = gray[0][0][0];
+    assert!(green > 0.0 && green < 1.0, "{}", green);
+    assert!(gray.iter().flatten().flatten().all(|value| *value == green));
+
+    //rgb keeps each channel as its own plane
+    let rgb = folder.clone().color(ColorMode::Rgb);
+    let image = rgb.image(2).unwrap();
+    assert_eq!(dims(&image), [3, 4, 6]);
+    let planes: Vec<f64> = image.iter().map(|plane| plane[3][5]).collect();
+    assert_eq!(planes, vec![1.0, 0.0, 0.0]);
+    assert_eq!(rgb.image(1).unwrap().iter().map(|plane| plane[1][2]).collect::<Vec<f64>>(), vec![0.0, 0.0, 1.0]);
+    assert_eq!(rgb.shape(), None);
+}
+
+#[test]
+fn resizes_every_image() {
+    let root = root("resize");
+    classes(&root);
+    let folder = ImageFolder::<f64>::new(&root).unwrap().color(ColorMode::Rgb).resize(3, 5);
+    assert_eq!(folder.shape(), Some(Shape::Image([3, 3, 5])));
+    for i in 0..folder.len() {
+        assert_eq!(dims(&folder.image(i).unwrap()), [3, 3, 5]);
+    }
+    //a flat color stays flat when it is resized
+    assert!(folder.image(1).unwrap()[2].iter().flatten().all(|value| (value - 1.0).abs() < 1e-12));
+
+    //with a fixed size the images batch together
+    let loader = DataLoader::new(folder, 4);
+    loader.validate().unwrap();
+    let batch = loader.batches(&mut rand::thread_rng()).next().unwrap().unwrap();
+    assert_eq!(batch.len(), 4);
+}
+
+#[test]
+fn non_images_are_skipped_or_rejected() {
+    let root = root("non-images");
+    classes(&root);
+    fs::write(root.join("ant/notes.txt"), "not an image").unwrap();
+    fs::write(root.join("ant/no-extension"), "not an image").unwrap();
+    fs::create_dir_all(root.join("ant/nested.png")).unwrap();
+    let folder = ImageFolder::<f64>::new(&root).unwrap();
+    assert_eq!(folder.len(), 4);
+
+    //a file with an image extension is only read when its sample is
+    fs::write(root.join("ant/1.png"), "not an image").unwrap();
+    let folder = ImageFolder::<f64>::new(&root).unwrap();
+    assert_eq!(folder.len(), 5);
+    match folder.get(1) {
+        Err(SproutError::Format(message)) => assert!(message.contains("1.png"), "{}", message),
+        other => panic!("expected a format error, got {:?}", other),
+    }
+
+    //no class directories, or classes without images
+    let empty = self::root("empty");
+    assert!(matches!(ImageFolder::<f64>::new(&empty), Err(SproutError::InvalidConfig(_))));
+    fs::create_dir_all(empty.join("cat")).unwrap();
+    fs::write(empty.join("cat/notes.txt"), "").unwrap();
+    assert!(matches!(ImageFolder::<f64>::new(&empty), Err(SproutError::InvalidConfig(_))));
+    assert!(matches!(ImageFolder::<f64>::new(empty.join("missing")), Err(SproutError::Io(_))));
+}
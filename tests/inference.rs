@@ -0,0 +1,100 @@
+use std::{sync::Arc, thread};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_params::PaddingType::*,
+    dense_layer::DenseLayer,
+    layer::Layer,
+    network::{Image, Network},
+    sequential::Sequential,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+fn dense_network() -> Network {
+    Sequential::new(Shape::Flat(6)).dense(9, ReLU).dense(7, TanH).dense(3, SoftMax).seed(5).build().unwrap()
+}
+
+fn conv_network() -> Network {
+    Sequential::new(Shape::Image([2, 6, 6])).conv(3, Same, 1, ReLU).pool(2, 2).flatten().dense(4, Sigmoid).seed(5).build().unwrap()
+}
+
+//more samples than a batch splits evenly into, whatever the thread count
+fn inputs(count: usize, width: usize) -> Vec<Vec<f64>> {
+    (0..count).map(|i| (0..width).map(|j| ((i * 7 + j * 3) % 13) as f64 / 6.5 - 1.0).collect()).collect()
+}
+
+fn images(count: usize) -> Vec<Image<f64>> {
+    inputs(count, 72).into_iter().map(|values| Tensor::from_flat(values, Shape::Image([2, 6, 6])).into_image()).collect()
+}
+
+fn assert_close(batch: &[Vec<f64>], single: &[Vec<f64>]) {
+    assert_eq!(batch.len(), single.len());
+    for (a, b) in batch.iter().zip(single) {
+        assert_eq!(a.len(), b.len());
+        assert!(a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-12), "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn network_batch_matches_single_predictions() {
+    let nn = dense_network();
+    let inputs = inputs(37, 6);
+    let single: Vec<Vec<f64>> = inputs.iter().map(|input| nn.predict(&Tensor::Flat(input.clone())).into_flat()).collect();
+    assert_close(&nn.predict_batch(&inputs), &single);
+    assert_close(&nn.try_predict_batch(&inputs).unwrap(), &single);
+    assert_close(&nn.dense_forward_batch(inputs.clone()), &single);
+
+    let nn = conv_network();
+    let images = images(19);
+    let single: Vec<Vec<f64>> = images.iter().map(|image| nn.conv_forward(image.clone())).collect();
+    assert_close(&nn.conv_predict_batch(&images), &single);
+    assert_close(&nn.try_conv_predict_batch(&images).unwrap(), &single);
+
+    //a bad sample in the batch is named
+    let mut bad = inputs.clone();
+    bad[4].pop();
+    let err = dense_network().try_predict_batch(&bad).unwrap_err();
+    assert!(err.to_string().contains("sample 4"), "{}", err);
+}
+
+#[test]
+fn dense_layer_batch_matches_single_predictions() {
+    let mut dense = DenseLayer::<f64>::new([6, 5], TanH);
+    dense.build(Shape::Flat(6), &mut ChaCha8Rng::seed_from_u64(2)).unwrap();
+    let inputs = inputs(41, 6);
+    let single: Vec<Vec<f64>> = inputs.iter().map(|input| dense.predict(&Tensor::Flat(input.clone())).into_flat()).collect();
+    assert_close(&dense.predict_batch(&inputs), &single);
+
+    //the training forward pass gives the same outputs
+    let (outputs, _) = dense.forward(inputs.iter().cloned().map(Tensor::Flat).collect());
+    assert_close(&outputs.into_iter().map(Tensor::into_flat).collect::<Vec<Vec<f64>>>(), &single);
+    assert!(dense.predict_batch(&[]).is_empty());
+}
+
+#[test]
+fn shared_network_predicts_from_many_threads() {
+    let nn = Arc::new(conv_network());
+    let images = Arc::new(images(12));
+    let expected: Vec<Vec<f64>> = images.iter().map(|image| nn.conv_forward(image.clone())).collect();
+
+    //each thread walks the samples from a different start, with a failing input mixed in
+    let handles: Vec<_> = (0..4).map(|t| {
+        let (nn, images) = (nn.clone(), images.clone());
+        thread::spawn(move || {
+            let mut outputs = vec![vec![]; images.len()];
+            for step in 0..images.len() {
+                let i = (t * 5 + step) % images.len();
+                outputs[i] = nn.predict(&Tensor::Image(images[i].clone())).into_flat();
+                assert!(nn.try_predict(&Tensor::Flat(vec![1.0; 3])).is_err());
+            }
+            outputs
+        })
+    }).collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), expected);
+    }
+    assert_eq!(Arc::strong_count(&nn), 1);
+}
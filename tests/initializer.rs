@@ -0,0 +1,50 @@
+use std::fs;
+
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_params::PaddingType::*,
+    error::SproutError,
+    initializer::{register_initializer, Initializer::*},
+    network::Network,
+    sequential::Sequential,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+//a model saved by a process that registered "halves" and loaded by one that never did
+fn loaded_elsewhere(name: &str) -> Network {
+    register_initializer("halves", |values, _fans, _rng| values.fill(0.5));
+    let nn: Network = Sequential::new(Shape::Image([1, 4, 4]))
+        .conv(3, Same, 1, TanH).init(Custom("halves".to_string()), Zeros)
+        .flatten()
+        .dense(2, Sigmoid).init(XavierUniform, Custom("halves".to_string()))
+        .build()
+        .unwrap();
+    let dir = std::env::temp_dir().join("sprout-initializer");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    nn.try_save_model(path.to_str().unwrap()).unwrap();
+    let json = format!("{}.json", path.to_str().unwrap());
+    fs::write(&json, fs::read_to_string(&json).unwrap().replace("halves", "not-registered")).unwrap();
+    Network::try_from_load(path.to_str().unwrap()).unwrap()
+}
+
+#[test]
+fn unregistered_custom_initializer_is_an_error_not_a_panic() {
+    let mut nn = loaded_elsewhere("unregistered");
+    let input = Tensor::from_flat((0..16).map(|i| i as f64 / 16.0).collect(), Shape::Image([1, 4, 4]));
+    let before = nn.named_weights().tensors;
+    let output = nn.try_predict(&input).unwrap();
+
+    let Err(err) = nn.try_fresh(1) else { panic!("fresh drew weights from an unregistered initializer") };
+    assert!(matches!(err, SproutError::InvalidConfig(ref message) if message.contains("layer 0") && message.contains("not-registered")), "{}", err);
+    assert!(matches!(nn.try_reset(), Err(SproutError::InvalidConfig(_))));
+    //the failed reset left the loaded weights as they were
+    assert_eq!(nn.named_weights().tensors, before);
+    assert_eq!(nn.try_predict(&input).unwrap(), output);
+    assert!(nn.try_convert::<f32>().is_ok());
+
+    register_initializer("not-registered", |values, _fans, _rng| values.fill(0.25));
+    let fresh = nn.try_fresh(1).unwrap();
+    assert_ne!(fresh.named_weights().tensors, before);
+}
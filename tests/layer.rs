@@ -0,0 +1,108 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sprout::{
+    activation::ActivationFunction::*,
+    error::SproutError,
+    layer::{self, register_layer, Layer, LayerCache},
+    loss_function::LossType,
+    network::Network,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+//multiplies every input by one learned factor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Scale {
+    factor: Vec<f64>,
+}
+
+impl Layer<f64> for Scale {
+    fn type_name(&self) -> &'static str {
+        "scale"
+    }
+
+    fn output_shape(&self, input: Shape) -> Result<Shape, String> {
+        Ok(input)
+    }
+
+    fn forward(&self, inputs: Vec<Tensor<f64>>) -> (Vec<Tensor<f64>>, LayerCache) {
+        let outputs = inputs.iter().map(|input| self.predict(input)).collect();
+        (outputs, Box::new(inputs))
+    }
+
+    fn predict(&self, input: &Tensor<f64>) -> Tensor<f64> {
+        Tensor::from_flat(input.to_flat().iter().map(|value| value * self.factor[0]).collect(), input.shape())
+    }
+
+    fn backward(&self, cache: &LayerCache, errors: Vec<Tensor<f64>>) -> (Vec<Tensor<f64>>, Vec<Vec<f64>>) {
+        let inputs: &Vec<Tensor<f64>> = cache.downcast_ref().unwrap();
+        let gradient = inputs.iter().zip(&errors)
+            .map(|(input, error)| input.to_flat().iter().zip(error.to_flat()).map(|(x, e)| x * e).sum::<f64>())
+            .sum();
+        let errors = errors.iter().map(|error| self.predict(error)).collect();
+        (errors, vec![vec![gradient]])
+    }
+
+    fn parameters(&self) -> Vec<&[f64]> {
+        vec![&self.factor]
+    }
+
+    fn parameters_mut(&mut self) -> Vec<&mut [f64]> {
+        vec![&mut self.factor]
+    }
+
+    fn to_json(&self) -> Result<Value, SproutError> {
+        Ok(serde_json::to_value(self)?)
+    }
+}
+
+fn network() -> Network {
+    let layers = vec![layer::dense([3, 4], TanH), Box::new(Scale { factor: vec![2.5] }) as Box<dyn Layer<f64>>, layer::dense([4, 2], Sigmoid)];
+    let mut nn = Network::new(layers, 0.01, 2, LossType::MSE);
+    nn.set_seed(3);
+    nn.build(Shape::Flat(3)).unwrap();
+    nn
+}
+
+fn invalid_config<T>(result: Result<T, SproutError>, expected: &str) {
+    match result {
+        Err(SproutError::InvalidConfig(message)) => assert!(message.contains(expected), "{:?} doesn't mention {:?}", message, expected),
+        Err(err) => panic!("expected an invalid configuration, got {}", err),
+        Ok(_) => panic!("expected an invalid configuration mentioning {:?}", expected),
+    }
+}
+
+#[test]
+fn registered_layers_save_and_load() {
+    register_layer::<f64, Scale>("scale").unwrap();
+    let nn = network();
+    assert_eq!(nn.layer_names(), ["dense_0", "scale_0", "dense_1"]);
+    let input = Tensor::Flat(vec![0.3, -0.2, 0.9]);
+
+    let name = std::env::temp_dir().join("sprout-custom-layer").display().to_string();
+    nn.try_save_model(&name).unwrap();
+    let loaded = Network::<f64>::try_from_load(&name).unwrap();
+    assert_eq!(loaded.layers[1].downcast_ref::<Scale>().unwrap().factor, vec![2.5]);
+    assert_eq!(loaded.predict(&input), nn.predict(&input));
+
+    //custom layers without parameter_pointers keep their parameters in the binary format's JSON
+    nn.try_save_binary(&name).unwrap();
+    assert_eq!(Network::<f64>::try_from_binary(&name).unwrap().predict(&input), nn.predict(&input));
+
+    //registration is per precision
+    let err = Network::<f32>::try_from_load(&name).err().unwrap();
+    assert!(err.to_string().contains("unknown layer type 'scale', register it with register_layer"), "{}", err);
+}
+
+#[test]
+fn built_in_names_are_taken() {
+    for name in ["dense", "conv", "pool"] {
+        invalid_config(register_layer::<f64, Scale>(name), &format!("'{}' is a built-in layer type", name));
+    }
+}
+
+#[test]
+fn unregistered_layers_dont_load() {
+    invalid_config(layer::layer_from_json::<f64>(json!({"type": "unregistered", "factor": [1.0]})), "unknown layer type 'unregistered', register it with register_layer");
+    invalid_config(layer::layer_from_json::<f64>(json!({"factor": [1.0]})), "no \"type\" tag");
+}
@@ -0,0 +1,127 @@
+use std::{fs, io::Write, path::PathBuf};
+
+use flate2::{write::GzEncoder, Compression};
+use sprout::{
+    dataset::Dataset,
+    datasets::mnist::{parse_idx, read_idx, Mnist, Normalization, Split},
+    error::SproutError,
+    tensor::Tensor,
+};
+
+//an unsigned byte IDX file
+fn idx(dims: &[u32], data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0, 0, 0x08, dims.len() as u8];
+    for dim in dims {
+        bytes.extend(dim.to_be_bytes());
+    }
+    bytes.extend(data);
+    bytes
+}
+
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("sprout-mnist").join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+//three 2 x 3 images with labels 7, 0, 9
+fn images() -> Vec<u8> {
+    idx(&[3, 2, 3], &[0, 51, 102, 153, 204, 255, 255, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6])
+}
+
+fn labels() -> Vec<u8> {
+    idx(&[3], &[7, 0, 9])
+}
+
+fn format_error(result: Result<impl std::fmt::Debug, SproutError>, expected: &str) {
+    match result {
+        Err(SproutError::Format(message)) => assert!(message.contains(expected), "{}", message),
+        other => panic!("expected a format error, got {:?}", other),
+    }
+}
+
+#[test]
+fn parses_idx_headers() {
+    let parsed = parse_idx(&images()).unwrap();
+    assert_eq!(parsed.dims, vec![3, 2, 3]);
+    assert_eq!(parsed.data.len(), 18);
+
+    format_error(parse_idx(&[0, 0]), "shorter than its header");
+    format_error(parse_idx(&[1, 0, 0x08, 1, 0, 0, 0, 0]), "magic number");
+    format_error(parse_idx(&[0, 0, 0x0d, 1, 0, 0, 0, 0]), "0x0d is not supported");
+    format_error(parse_idx(&[0, 0, 0x08, 3, 0, 0, 0, 1]), "ends inside its dimensions");
+    //data has to match the dimensions exactly, short or long
+    format_error(parse_idx(&idx(&[2, 2], &[1, 2, 3])), "need 4 bytes but the file has 3");
+    format_error(parse_idx(&idx(&[2, 2], &[1, 2, 3, 4, 5])), "need 4 bytes but the file has 5");
+    let truncated = images();
+    format_error(parse_idx(&truncated[..truncated.len() - 1]), "need 18 bytes but the file has 17");
+}
+
+#[test]
+fn gzipped_files_read_the_same() {
+    let dir = dir("gzip");
+    fs::write(dir.join("plain"), images()).unwrap();
+    fs::write(dir.join("zipped"), gzip(&images())).unwrap();
+    assert_eq!(read_idx(dir.join("zipped")).unwrap(), read_idx(dir.join("plain")).unwrap());
+
+    //the gzip magic followed by garbage
+    fs::write(dir.join("broken"), [0x1f, 0x8b, 1, 2, 3]).unwrap();
+    format_error(read_idx(dir.join("broken")), "is not valid gzip");
+
+    //load_dir picks up either name
+    let [image_name, label_name] = Split::Test.file_names();
+    fs::write(dir.join(format!("{}.gz", image_name)), gzip(&images())).unwrap();
+    fs::write(dir.join(label_name), labels()).unwrap();
+    let mnist = Mnist::<f64>::load_dir(&dir, Split::Test, Normalization::Raw).unwrap();
+    assert_eq!(mnist.len(), 3);
+    assert_eq!((mnist.rows, mnist.cols), (2, 3));
+    assert!(matches!(Mnist::<f64>::load_dir(&dir, Split::Train, Normalization::Raw), Err(SproutError::InvalidConfig(_))));
+}
+
+#[test]
+fn normalizes_pixels() {
+    let dir = dir("normalization");
+    fs::write(dir.join("images"), images()).unwrap();
+    fs::write(dir.join("labels"), labels()).unwrap();
+    let load = |normalization| Mnist::<f64>::load(dir.join("images"), dir.join("labels"), normalization).unwrap();
+
+    assert_eq!(load(Normalization::Raw).image(0), vec![vec![vec![0.0, 51.0, 102.0], vec![153.0, 204.0, 255.0]]]);
+    assert_eq!(load(Normalization::Unit).image(0), vec![vec![vec![0.0, 0.2, 0.4], vec![0.6, 0.8, 1.0]]]);
+    let symmetric = load(Normalization::Symmetric).image(0).concat().concat();
+    let expected = [-1.0, -0.6, -0.2, 0.2, 0.6, 1.0];
+    assert!(symmetric.iter().zip(expected).all(|(value, expected)| (value - expected).abs() < 1e-12), "{:?}", symmetric);
+    let standard = load(Normalization::Standard { mean: 0.5, std_dev: 0.25 }).image(0).concat().concat();
+    assert!((standard[0] + 2.0).abs() < 1e-12 && (standard[5] - 2.0).abs() < 1e-12, "{:?}", standard);
+
+    //samples are one-hot single channel images, flattened row by row for dense networks
+    let mnist = Mnist::<f32>::load(dir.join("images"), dir.join("labels"), Normalization::Raw).unwrap();
+    let sample = mnist.get(2).unwrap();
+    assert_eq!(sample.input, Tensor::Image(vec![vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]]));
+    assert_eq!(sample.target, vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(mnist.dense_samples()[1][0], vec![255.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    assert_eq!(mnist.dense_samples()[1][1][0], 1.0);
+    assert!(matches!(mnist.get(3), Err(SproutError::InvalidConfig(_))));
+}
+
+#[test]
+fn labels_have_to_fit_the_images() {
+    let dir = dir("labels");
+    fs::write(dir.join("images"), images()).unwrap();
+    let load = |labels: Vec<u8>| {
+        fs::write(dir.join("labels"), labels).unwrap();
+        Mnist::<f64>::load(dir.join("images"), dir.join("labels"), Normalization::Unit)
+    };
+
+    format_error(load(idx(&[3], &[7, 10, 9])), "label 10 of sample 1 is not one of the 10 classes");
+    assert!(matches!(load(idx(&[2], &[7, 0])), Err(SproutError::ShapeMismatch(_))));
+    format_error(load(idx(&[3, 1], &[7, 0, 9])), "expected [labels]");
+    fs::write(dir.join("images"), idx(&[3, 6], &[0; 18])).unwrap();
+    format_error(load(labels()), "expected [images, rows, cols]");
+}
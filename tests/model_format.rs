@@ -0,0 +1,149 @@
+use std::fs;
+
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_params::PaddingType::*,
+    error::SproutError,
+    float::Float,
+    initializer::Initializer,
+    layer,
+    loss_function::LossType::*,
+    model_format,
+    network::Network,
+    sequential::Sequential,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+fn path(name: &str) -> String {
+    let dir = std::env::temp_dir().join("sprout-model-format");
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name).to_str().unwrap().to_string()
+}
+
+fn image() -> Tensor<f64> {
+    Tensor::Image((0..2).map(|c| (0..6).map(|r| (0..6).map(|k| ((c * 5 + r * 3 + k) % 7) as f64 / 7.0).collect()).collect()).collect())
+}
+
+fn cast<T: Float>(tensor: &Tensor<f64>) -> Tensor<T> {
+    Tensor::from_flat(tensor.to_flat().into_iter().map(T::from_f64).collect(), tensor.shape())
+}
+
+//the conv bias comes out of its Constant(0.25) initializer untouched, so the same number shows up in bias_init as well
+fn network<T: Float>() -> Network<T> {
+    Sequential::new(Shape::Image([2, 6, 6]))
+        .conv(3, Same, 1, TanH).init(Initializer::Constant(0.25), Initializer::Constant(0.25))
+        .pool(2, 2)
+        .flatten()
+        .dense(4, Sigmoid).init(Initializer::XavierNormal, Initializer::Constant(0.0))
+        .seed(11)
+        .build()
+        .unwrap()
+}
+
+fn parameters<T: Float>(network: &Network<T>) -> Vec<Vec<T>> {
+    network.layers.iter().flat_map(|layer| layer.parameters().into_iter().map(<[T]>::to_vec)).collect()
+}
+
+fn round_trip<T: Float>(name: &str) {
+    let mut nn = network::<T>();
+    //one epoch moves the weights off their initial values, the conv bias is pinned back to its initializer's constant
+    nn.try_conv_train(vec![(cast::<T>(&image()).into_image(), vec![T::one(); 4])], 1).unwrap();
+    nn.layers[0].parameters_mut()[1][0] = T::from_f64(0.25);
+
+    let name = path(name);
+    nn.try_save_binary(&name).unwrap();
+    let binary = Network::<T>::try_from_binary(&name).unwrap();
+    assert_eq!(parameters(&binary), parameters(&nn));
+    assert_eq!(binary.predict(&cast(&image())), nn.predict(&cast(&image())));
+
+    //binary -> JSON -> binary gives back the same model and the same bytes
+    model_format::binary_to_json(&name).unwrap();
+    let json = Network::<T>::try_from_load(&name).unwrap();
+    assert_eq!(parameters(&json), parameters(&nn));
+    let bytes = fs::read(format!("{}.sprout", name)).unwrap();
+    model_format::json_to_binary(&name).unwrap();
+    assert_eq!(fs::read(format!("{}.sprout", name)).unwrap(), bytes);
+
+    //JSON -> binary from a model saved as JSON
+    nn.try_save_model(&name).unwrap();
+    model_format::json_to_binary(&name).unwrap();
+    assert_eq!(parameters(&Network::<T>::try_from_binary(&name).unwrap()), parameters(&nn));
+}
+
+#[test]
+fn f64_round_trip() {
+    round_trip::<f64>("f64");
+}
+
+#[test]
+fn f32_round_trip() {
+    round_trip::<f32>("f32");
+}
+
+//blobs go back by position, a bias equal to its initializer's constant (or to 0) stays the bias
+#[test]
+fn bias_equal_to_initializer_constant() {
+    for bias in [0.25, 0.0] {
+        let mut nn = network::<f64>();
+        nn.layers[0].parameters_mut()[1][0] = bias;
+        nn.layers[0].parameters_mut()[0].fill(bias);
+        let name = path(&format!("constant-{}", bias));
+        nn.try_save_binary(&name).unwrap();
+        let loaded = Network::<f64>::try_from_binary(&name).unwrap();
+        assert_eq!(parameters(&loaded), parameters(&nn));
+    }
+}
+
+//the architecture in the file holds no parameter values, only where each blob goes back to
+#[test]
+fn dense_and_conv_parameters_are_blobs() {
+    let nn = network::<f64>();
+    let name = path("blobs");
+    nn.try_save_binary(&name).unwrap();
+    let bytes = fs::read(format!("{}.sprout", name)).unwrap();
+    let len = u64::from_le_bytes(bytes[9..17].try_into().unwrap()) as usize;
+    let architecture: serde_json::Value = serde_json::from_slice(&bytes[17..17 + len]).unwrap();
+    for (layer, key) in [(0, "weights"), (0, "bias"), (2, "weights"), (2, "biases")] {
+        assert!(architecture["layers"][layer]["params"][key].is_null());
+    }
+    assert_eq!(architecture["blobs"].as_array().unwrap().len(), 4);
+    assert_eq!(architecture["layers"][0]["bias_init"], serde_json::json!({ "Constant": 0.25 }));
+
+    let (_, value) = model_format::read(&format!("{}.sprout", name)).unwrap();
+    assert_eq!(value["layers"][0]["params"]["bias"], 0.25);
+    assert_eq!(value["layers"][2]["params"]["weights"].as_array().unwrap().len(), 18 * 4);
+
+    let unbuilt: Network = Network::new(vec![layer::dense([3, 2], TanH)], 0.1, 1, MSE);
+    assert!(unbuilt.try_save_binary(&path("unbuilt")).is_ok());
+}
+
+#[test]
+fn header_and_corruption_checks() {
+    let name = path("header");
+    network::<f32>().try_save_binary(&name).unwrap();
+    let file = format!("{}.sprout", name);
+    let bytes = fs::read(&file).unwrap();
+    assert_eq!(bytes[..4], model_format::MAGIC);
+    assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 1);
+    assert_eq!(bytes[8], 0);
+
+    let rejected = |bytes: &[u8], expected: &str| match model_format::decode(bytes) {
+        Err(SproutError::Format(message)) => assert!(message.contains(expected), "{:?} doesn't mention {:?}", message, expected),
+        other => panic!("expected a format error, got {:?}", other.map(|(precision, _)| precision)),
+    };
+    let mut newer = bytes.clone();
+    newer[4..8].copy_from_slice(&(model_format::FORMAT_VERSION + 1).to_le_bytes());
+    rejected(&newer, "binary format version 2");
+    let mut flipped = bytes.clone();
+    flipped[40] ^= 1;
+    rejected(&flipped, "checksum mismatch");
+    rejected(&bytes[..bytes.len() - 5], "checksum mismatch");
+    rejected(b"SPR", "not a Sprout binary model");
+
+    //read goes through the same checks whether or not the file can be mapped
+    fs::write(&file, &flipped).unwrap();
+    assert!(matches!(model_format::read(&file), Err(SproutError::Format(_))));
+    fs::write(&file, []).unwrap();
+    assert!(matches!(model_format::read(&file), Err(SproutError::Format(_))));
+}
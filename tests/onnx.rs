@@ -0,0 +1,65 @@
+use sprout::{
+    activation::ActivationFunction::{self, *},
+    conv_params::PaddingType::*,
+    float::Float,
+    network::Network,
+    onnx,
+    sequential::Sequential,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+const ACTIVATIONS: [ActivationFunction; 4] = [Sigmoid, ReLU, TanH, SoftMax];
+
+fn image<T: Float>(seed: usize) -> Tensor<T> {
+    Tensor::Image((0..3).map(|c| (0..8).map(|r| (0..8).map(|k| T::from_f64(((seed + c * 5 + r * 7 + k * 3) % 13) as f64 / 13.0 - 0.4)).collect()).collect()).collect())
+}
+
+fn flat<T: Float>(seed: usize) -> Tensor<T> {
+    Tensor::Flat((0..6).map(|i| T::from_f64(((seed + i * 5) % 7) as f64 / 7.0 - 0.3)).collect())
+}
+
+fn round_trip<T: Float>(network: &Network<T>, inputs: &[Tensor<T>]) {
+    let imported: Network<T> = onnx::import(&onnx::export(network).unwrap()).unwrap();
+    assert_eq!(imported.layer_names(), network.layer_names());
+    for input in inputs {
+        assert_eq!(imported.predict(input), network.predict(input));
+    }
+}
+
+//Gemm + each activation
+#[test]
+fn dense_round_trip() {
+    for activation in ACTIVATIONS {
+        let network: Network = Sequential::new(Shape::Flat(6)).dense(5, TanH).dense(3, activation.clone()).seed(2).build().unwrap();
+        round_trip(&network, &[flat(0), flat(1)]);
+        let network: Network<f32> = Sequential::new(Shape::Flat(6)).dense(3, activation).seed(2).build().unwrap();
+        round_trip(&network, &[flat(0), flat(1)]);
+    }
+}
+
+//depthwise Conv (with and without padding, strided) + each activation, MaxPool and the Flatten in front of Gemm
+#[test]
+fn conv_pool_round_trip() {
+    for activation in ACTIVATIONS {
+        for (padding, stride) in [(Valid, 1), (Same, 1), (Full, 2)] {
+            let network: Network = Sequential::new(Shape::Image([3, 8, 8]))
+                .conv(3, padding.clone(), stride, activation.clone())
+                .pool(2, 2)
+                .flatten()
+                .dense(4, Sigmoid)
+                .seed(3)
+                .build()
+                .unwrap();
+            round_trip(&network, &[image(0), image(1)]);
+        }
+    }
+    let network: Network<f32> = Sequential::new(Shape::Image([3, 8, 8])).conv(3, Same, 1, ReLU).pool(3, 1).flatten().dense(2, SoftMax).seed(4).build().unwrap();
+    round_trip(&network, &[image(0), image(2)]);
+}
+
+#[test]
+fn import_rejects_garbage() {
+    assert!(onnx::import::<f64>(&[0x0a, 0x05, 0x01]).is_err());
+    assert!(onnx::import::<f64>(b"not an onnx model").is_err());
+}
@@ -0,0 +1,80 @@
+use std::fs;
+
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_params::PaddingType::*,
+    float::{Float, Precision},
+    network::Network,
+    optimizer::Optimizer,
+    sequential::Sequential,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+fn xor<T: Float>() -> Vec<[Vec<T>; 2]> {
+    [[0.0, 0.0, 0.0], [0.0, 1.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 0.0]].iter()
+        .map(|[a, b, y]| [vec![T::from_f64(*a), T::from_f64(*b)], vec![T::from_f64(*y)]])
+        .collect()
+}
+
+fn loss<T: Float>(nn: &Network<T>) -> f64 {
+    xor::<T>().iter().map(|[input, target]| {
+        let output = nn.predict(&Tensor::Flat(input.clone())).into_flat();
+        (output[0] - target[0]).as_f64().powi(2)
+    }).sum()
+}
+
+fn image() -> Tensor<f64> {
+    Tensor::Image((0..2).map(|c| (0..6).map(|r| (0..6).map(|k| ((c * 5 + r * 3 + k) % 7) as f64 / 7.0 - 0.4).collect()).collect()).collect())
+}
+
+#[test]
+fn f32_networks_train() {
+    let mut nn: Network<f32> = Sequential::new(Shape::Flat(2)).dense(8, TanH).dense(1, Sigmoid)
+        .optimizer(Optimizer::adam()).learning_rate(0.05).batch_size(4).seed(3).build().unwrap();
+    let before = loss(&nn);
+    nn.try_dense_train(xor(), 300).unwrap();
+    let after = loss(&nn);
+    assert!(after < before / 4.0, "loss went from {} to {}", before, after);
+    assert!(nn.layers.iter().flat_map(|layer| layer.parameters().concat()).all(|value| value.is_finite()));
+    assert_eq!(nn.precision, Precision::F32);
+}
+
+#[test]
+fn convert_keeps_predictions() {
+    let nn: Network = Sequential::new(Shape::Image([2, 6, 6])).conv(3, Same, 1, ReLU).pool(2, 2).flatten().dense(5, TanH).dense(3, SoftMax)
+        .seed(9).build().unwrap();
+    let expected = nn.predict(&image()).into_flat();
+
+    let single = nn.try_convert::<f32>().unwrap();
+    assert_eq!(single.precision, Precision::F32);
+    let input = Tensor::from_flat(image().to_flat().into_iter().map(|value| value as f32).collect(), image().shape());
+    let output = single.predict(&input).into_flat();
+    assert!(output.iter().zip(&expected).all(|(a, b)| (*a as f64 - b).abs() < 1e-5), "{:?} != {:?}", output, expected);
+
+    //f32 weights are exact in f64, only the arithmetic differs
+    let double = single.convert::<f64>();
+    assert_eq!(double.precision, Precision::F64);
+    let output = double.predict(&image()).into_flat();
+    assert!(output.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-5), "{:?} != {:?}", output, expected);
+    assert_eq!(double.layers[3].parameters()[0], single.layers[3].parameters()[0].iter().map(|value| *value as f64).collect::<Vec<f64>>());
+}
+
+#[test]
+fn precision_is_saved() {
+    let dir = std::env::temp_dir().join("sprout-precision");
+    fs::create_dir_all(&dir).unwrap();
+    for (name, precision, saved) in [("f32", Precision::F32, "F32"), ("f64", Precision::F64, "F64")] {
+        let name = dir.join(name).display().to_string();
+        let nn: Network = Sequential::new(Shape::Flat(2)).dense(2, Sigmoid).seed(1).build().unwrap();
+        match precision {
+            Precision::F32 => nn.convert::<f32>().try_save_model(&name).unwrap(),
+            Precision::F64 => nn.try_save_model(&name).unwrap(),
+        }
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(format!("{}.json", name)).unwrap()).unwrap();
+        assert_eq!(json["precision"], saved);
+        //either precision loads either file, and keeps its own
+        assert_eq!(Network::<f32>::try_from_load(&name).unwrap().precision, Precision::F32);
+        assert_eq!(Network::<f64>::try_from_load(&name).unwrap().precision, Precision::F64);
+    }
+}
@@ -0,0 +1,118 @@
+use sprout::{
+    activation::ActivationFunction::*,
+    error::SproutError,
+    network::Network,
+    preprocessing::{transform_all, LabelEncoder, MinMaxScaler, OneHotEncoder, StandardScaler},
+    sequential::Sequential,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+fn inputs() -> Vec<Vec<f64>> {
+    vec![vec![1.0, 2.0, 10.0], vec![3.0, 0.0, 10.0], vec![2.0, 1.0, 10.0], vec![6.0, 1.0, 10.0]]
+}
+
+fn close(a: &[f64], b: &[f64]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-12)
+}
+
+#[test]
+fn standard_scaler() {
+    let mut scaler = StandardScaler::new();
+    scaler.fit(&inputs()).unwrap();
+    assert_eq!(scaler.mean, [3.0, 1.0, 10.0]);
+    //population deviation, the constant column is only centered
+    assert_eq!(scaler.std_dev, [3.5f64.sqrt(), 0.5f64.sqrt(), 1.0]);
+    let transformed: Vec<Vec<f64>> = inputs().iter().map(|input| scaler.transform(input).unwrap()).collect();
+    for j in 0..2 {
+        let column: Vec<f64> = transformed.iter().map(|row| row[j]).collect();
+        assert!(column.iter().sum::<f64>().abs() < 1e-12);
+        assert!((column.iter().map(|x| x * x).sum::<f64>() / 4.0 - 1.0).abs() < 1e-12);
+    }
+    assert!(transformed.iter().all(|row| row[2] == 0.0));
+    for (input, output) in inputs().iter().zip(&transformed) {
+        assert!(close(&scaler.inverse_transform(output).unwrap(), input));
+    }
+    assert!(matches!(scaler.transform(&[1.0, 2.0]), Err(SproutError::ShapeMismatch(_))));
+    assert!(matches!(StandardScaler::new().transform(&[1.0]), Err(SproutError::InvalidConfig(_))));
+}
+
+#[test]
+fn min_max_scaler() {
+    let mut scaler = MinMaxScaler::new(-1.0, 1.0);
+    scaler.fit(&inputs()).unwrap();
+    assert_eq!((scaler.min.clone(), scaler.max.clone()), (vec![1.0, 0.0, 10.0], vec![6.0, 2.0, 10.0]));
+    assert!(close(&scaler.transform(&[1.0, 2.0, 10.0]).unwrap(), &[-1.0, 1.0, -1.0]));
+    assert!(close(&scaler.transform(&[3.5, 1.0, 10.0]).unwrap(), &[0.0, 0.0, -1.0]));
+    for input in inputs() {
+        assert!(close(&scaler.inverse_transform(&scaler.transform(&input).unwrap()).unwrap(), &input));
+    }
+    assert!(matches!(MinMaxScaler::new(1.0, 1.0).fit(&inputs()), Err(SproutError::InvalidConfig(_))));
+}
+
+#[test]
+fn one_hot_encoder() {
+    let mut encoder = OneHotEncoder::new([1]);
+    encoder.fit(&inputs()).unwrap();
+    assert_eq!(encoder.categories, [vec![0.0, 1.0, 2.0]]);
+    assert_eq!(encoder.output_len(), 5);
+    assert_eq!(encoder.transform(&[1.0, 2.0, 10.0]).unwrap(), [1.0, 0.0, 0.0, 1.0, 10.0]);
+    assert_eq!(encoder.transform(&[4.0, 0.0, 10.0]).unwrap(), [4.0, 1.0, 0.0, 0.0, 10.0]);
+    //a category that wasn't seen in fit is all zeros
+    assert_eq!(encoder.transform(&[4.0, 7.0, 10.0]).unwrap(), [4.0, 0.0, 0.0, 0.0, 10.0]);
+    assert!(matches!(OneHotEncoder::new([3]).fit(&inputs()), Err(SproutError::ShapeMismatch(_))));
+
+    let mut labels = LabelEncoder::new();
+    labels.fit(&["dog", "cat", "dog", "bird"]).unwrap();
+    assert_eq!(labels.transform(&["bird", "cat", "dog"]).unwrap(), [0, 1, 2]);
+    assert_eq!(labels.one_hot::<f64>("cat").unwrap(), [0.0, 1.0, 0.0]);
+    assert_eq!(labels.decode_output(&[0.1, 0.2, 0.7]), Some("dog"));
+    assert!(labels.encode("fish").is_err());
+}
+
+fn network() -> Network {
+    let mut nn: Network = Sequential::new(Shape::Flat(5)).dense(3, TanH).dense(3, SoftMax).seed(2).build().unwrap();
+    nn.fit_preprocessing(vec![OneHotEncoder::new([1]).into(), StandardScaler::new().into(), MinMaxScaler::default().into()], &inputs()).unwrap();
+    let mut labels = LabelEncoder::new();
+    labels.fit(&["a", "b", "c"]).unwrap();
+    nn.label_encoder = Some(labels);
+    nn
+}
+
+#[test]
+fn saved_model_keeps_its_preprocessing() {
+    let nn = network();
+    let samples = [vec![1.0, 2.0, 10.0], vec![4.0, 1.0, 9.0], vec![-3.0, 5.0, 10.0]];
+    let expected: Vec<Vec<f64>> = samples.iter().map(|input| nn.predict(&Tensor::Flat(input.clone())).into_flat()).collect();
+    //the layers see what the steps make of the raw input
+    let direct = nn.layers.iter().fold(Tensor::Flat(transform_all(&nn.preprocessing, &samples[1]).unwrap()), |x, layer| layer.predict(&x));
+    assert_eq!(direct.into_flat(), expected[1]);
+
+    let dir = std::env::temp_dir().join("sprout-preprocessing");
+    std::fs::create_dir_all(&dir).unwrap();
+    let name = dir.join("model");
+    let name = name.to_str().unwrap();
+    nn.try_save_model(name).unwrap();
+    nn.try_save_binary(name).unwrap();
+    for loaded in [Network::<f64>::try_from_load(name).unwrap(), Network::<f64>::try_from_binary(name).unwrap()] {
+        assert_eq!(loaded.preprocessing, nn.preprocessing);
+        assert_eq!(loaded.label_encoder, nn.label_encoder);
+        for (input, expected) in samples.iter().zip(&expected) {
+            assert_eq!(&loaded.predict(&Tensor::Flat(input.clone())).into_flat(), expected);
+            assert_eq!(loaded.predict_label(&Tensor::Flat(input.clone())).unwrap(), nn.predict_label(&Tensor::Flat(input.clone())).unwrap());
+        }
+    }
+}
+
+#[test]
+fn wrong_width_is_an_error_in_try_predict() {
+    let nn = network();
+    let err = nn.try_predict(&Tensor::Flat(vec![1.0, 2.0])).unwrap_err();
+    assert!(matches!(err, SproutError::ShapeMismatch(ref message) if message.contains("preprocessing step 0")), "{}", err);
+}
+
+#[test]
+#[should_panic(expected = "input of shape Flat(2) doesn't fit the network's preprocessing")]
+fn wrong_width_panics_in_predict() {
+    network().predict(&Tensor::Flat(vec![1.0, 2.0]));
+}
@@ -0,0 +1,152 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_layer::ConvLayer,
+    conv_params::PaddingType::*,
+    dense_layer::DenseLayer,
+    layer::Layer,
+    network::{ConvSample, Network},
+    optimizer::Optimizer,
+    regularizer::{Constraint::*, Regularizer, Units},
+    sequential::Sequential,
+    shape::Shape,
+};
+
+fn norms(weights: &[f64], units: Units) -> Vec<f64> {
+    match units {
+        Units::Columns(n) => (0..n).map(|j| weights.iter().skip(j).step_by(n).map(|w| w * w).sum::<f64>().sqrt()).collect(),
+        Units::Blocks(n) => weights.chunks(weights.len() / n).map(|block| block.iter().map(|w| w * w).sum::<f64>().sqrt()).collect(),
+    }
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+    }
+}
+
+fn built(mut layer: Box<dyn Layer<f64>>, shape: Shape) -> Box<dyn Layer<f64>> {
+    layer.build(shape, &mut ChaCha8Rng::seed_from_u64(1)).unwrap();
+    layer
+}
+
+#[test]
+fn conv_units_are_channel_kernels() {
+    let conv = built(Box::new(ConvLayer::new(2, Valid, 1, TanH)), Shape::Image([3, 4, 4]));
+    assert_eq!(conv.weight_units(), Units::Blocks(3));
+    //channel 0 has norm 5, channel 1 norm 1, channel 2 norm 0.5
+    let weights = [3.0, 4.0, 0.0, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, 0.0, 0.0, -0.5];
+
+    let mut capped = weights.to_vec();
+    Regularizer::new().constraint(MaxNorm(2.0)).after_update(&mut capped, conv.weight_units(), 0.1);
+    assert_close(&capped, &[1.2, 1.6, 0.0, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, 0.0, 0.0, -0.5]);
+
+    let mut unit = weights.to_vec();
+    Regularizer::new().constraint(UnitNorm).after_update(&mut unit, conv.weight_units(), 0.1);
+    assert_close(&unit, &[0.6, 0.8, 0.0, 0.0, 0.5, 0.5, 0.5, 0.5, 0.0, 0.0, 0.0, -1.0]);
+
+    let mut zero = vec![0.0; 12];
+    zero[0] = 2.0;
+    Regularizer::new().constraint(UnitNorm).after_update(&mut zero, conv.weight_units(), 0.1);
+    assert_eq!(norms(&zero, Units::Blocks(3)), [1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn dense_units_are_columns() {
+    let dense = built(Box::new(DenseLayer::new([2, 3], TanH)), Shape::Flat(2));
+    assert_eq!(dense.weight_units(), Units::Columns(3));
+    //2 x 3 row-major, the columns are (3, 4), (1, 0) and (0, 0.1)
+    let mut weights = vec![3.0, 1.0, 0.0, 4.0, 0.0, 0.1];
+    Regularizer::new().constraint(MaxNorm(1.0)).after_update(&mut weights, dense.weight_units(), 0.1);
+    assert_close(&weights, &[0.6, 1.0, 0.0, 0.8, 0.0, 0.1]);
+    //decay comes first, then the constraint
+    let mut weights = vec![-1.0, 2.0];
+    Regularizer::new().weight_decay(0.5).constraint(NonNegative).after_update(&mut weights, dense.weight_units(), 0.1);
+    assert_eq!(weights, [0.0, 1.9]);
+}
+
+#[test]
+fn penalty_and_gradient() {
+    let weights = [0.5, -2.0, 0.0, 1.5];
+    let regularizer = Regularizer::new().l1(0.1).l2(0.01);
+    //0.1 * (0.5 + 2 + 0 + 1.5) + 0.01 * (0.25 + 4 + 0 + 2.25)
+    assert!((regularizer.penalty(&weights) - 0.465).abs() < 1e-15);
+    assert_eq!(Regularizer::new().weight_decay(0.1).penalty(&weights), 0.0);
+
+    //per sample 0.1 * sign(w) + 0.02 * w, times the 4 samples the gradients are summed over
+    let mut gradients = [1.0f64; 4];
+    regularizer.add_gradients(&weights, &mut gradients, 4);
+    let expected = [1.0 + 4.0 * (0.1 + 0.01), 1.0 + 4.0 * (-0.1 - 0.04), 1.0, 1.0 + 4.0 * (0.1 + 0.03)];
+    for (gradient, expected) in gradients.iter().zip(expected) {
+        assert!((gradient - expected).abs() < 1e-15, "{} {}", gradient, expected);
+    }
+}
+
+//the penalty shows up in the cost and its gradient in the update, against the same network without it
+#[test]
+fn penalty_in_training() {
+    let data = vec![[vec![0.2, -0.4], vec![1.0]], [vec![0.7, 0.1], vec![0.0]]];
+    let network = |regularizer: Regularizer| -> Network {
+        Sequential::new(Shape::Flat(2)).dense(1, Sigmoid).regularize(regularizer)
+            .optimizer(Optimizer::SGD).learning_rate(0.5).batch_size(2).seed(4).build().unwrap()
+    };
+    let regularizer = Regularizer::new().l1(0.1).l2(0.2);
+    let (mut plain, mut penalized) = (network(Regularizer::new()), network(regularizer.clone()));
+    let initial = plain.layers[0].parameters()[0].to_vec();
+    plain.dense_train(data.clone(), 1);
+    penalized.dense_train(data, 1);
+
+    assert!((penalized.cost - plain.cost - regularizer.penalty(&initial)).abs() < 1e-12);
+    //the summed gradient is averaged over the batch, so each weight moves by a further 0.5 * (l1 sign + 2 l2 w)
+    for ((plain, penalized), w) in plain.layers[0].parameters()[0].iter().zip(penalized.layers[0].parameters()[0]).zip(&initial) {
+        let step = 0.5 * (0.1 * w.signum() + 0.4 * w);
+        assert!((plain - step - penalized).abs() < 1e-12);
+    }
+    assert_eq!(plain.layers[0].parameters()[1], penalized.layers[0].parameters()[1]);
+}
+
+//one update on the whole batch: decayed weights are the plain ones shrunk by learning_rate * weight_decay, biases are untouched
+#[test]
+fn weight_decay_leaves_biases_alone() {
+    let images: Vec<ConvSample> = (0..3).map(|i| {
+        let image = (0..2).map(|c| (0..4).map(|r| (0..4).map(|k| ((i + c * 5 + r * 3 + k) % 7) as f64 / 7.0).collect()).collect()).collect();
+        (image, vec![(i % 2) as f64])
+    }).collect();
+    let network = |regularizer: Regularizer| -> Network {
+        Sequential::new(Shape::Image([2, 4, 4]))
+            .conv(3, Same, 1, TanH).regularize(regularizer.clone())
+            .flatten()
+            .dense(1, Sigmoid).regularize(regularizer)
+            .optimizer(Optimizer::SGD).learning_rate(0.2).batch_size(3).seed(8).build().unwrap()
+    };
+    let (mut plain, mut decayed) = (network(Regularizer::new()), network(Regularizer::new().weight_decay(0.5)));
+    plain.conv_train(images.clone(), 1);
+    decayed.conv_train(images, 1);
+    assert_eq!(plain.cost, decayed.cost);
+    //the flatten step isn't a layer, so these are the conv and the dense layer
+    for (plain, decayed) in plain.layers.iter().zip(&decayed.layers) {
+        let (plain, decayed) = (plain.parameters(), decayed.parameters());
+        assert_close(decayed[0], &plain[0].iter().map(|w| w * 0.9).collect::<Vec<f64>>());
+        assert_eq!(plain[1], decayed[1]);
+    }
+}
+
+//the conv constraint holds per channel through training
+#[test]
+fn conv_unit_norm_in_training() {
+    let images: Vec<ConvSample> = (0..4).map(|i| {
+        let image = (0..3).map(|c| (0..5).map(|r| (0..5).map(|k| ((i * 2 + c + r * k) % 9) as f64 / 9.0).collect()).collect()).collect();
+        (image, vec![(i % 2) as f64])
+    }).collect();
+    let mut nn: Network = Sequential::new(Shape::Image([3, 5, 5]))
+        .conv(3, Valid, 1, ReLU).regularize(Regularizer::new().constraint(UnitNorm))
+        .flatten()
+        .dense(1, Sigmoid)
+        .batch_size(2).seed(3).build().unwrap();
+    nn.conv_train(images, 2);
+    for norm in norms(nn.layers[0].parameters()[0], Units::Blocks(3)) {
+        assert!((norm - 1.0).abs() < 1e-12, "{}", norm);
+    }
+}
@@ -0,0 +1,56 @@
+use serde_json::Value;
+use sprout::{network::Network, schema, tensor::Tensor};
+
+//models saved by Sprout before layers were tagged (schema version 0), with the outputs that version predicted for them.
+//that version added the conv bias once per kernel weight, so the conv fixture was saved with a zero bias
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn expected<I: serde::de::DeserializeOwned>(name: &str) -> (Vec<I>, Vec<Vec<f64>>) {
+    serde_json::from_str(&std::fs::read_to_string(fixture(&format!("{}_outputs.json", name))).unwrap()).unwrap()
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-12, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn baseline_dense_model_migrates() {
+    let mut value: Value = serde_json::from_str(&std::fs::read_to_string(fixture("baseline_dense.json")).unwrap()).unwrap();
+    assert!(value.get("schema_version").is_none());
+    schema::migrate(&mut value).unwrap();
+    assert_eq!(value["schema_version"], schema::SCHEMA_VERSION);
+    for layer in value["layers"].as_array().unwrap() {
+        assert_eq!(layer["type"], "dense");
+        assert!(layer.get("dense_params").is_none());
+    }
+
+    let nn = Network::<f64>::try_from_load(&fixture("baseline_dense")).unwrap();
+    let (inputs, outputs) = expected::<Vec<f64>>("baseline_dense");
+    for (input, output) in inputs.into_iter().zip(outputs) {
+        assert_close(&nn.try_dense_forward(input).unwrap(), &output);
+    }
+}
+
+#[test]
+fn baseline_conv_model_migrates() {
+    let mut value: Value = serde_json::from_str(&std::fs::read_to_string(fixture("baseline_conv.json")).unwrap()).unwrap();
+    schema::migrate(&mut value).unwrap();
+    let types: Vec<&str> = value["layers"].as_array().unwrap().iter().map(|layer| layer["type"].as_str().unwrap()).collect();
+    assert_eq!(types, ["conv", "pool", "dense"]);
+
+    let nn = Network::<f64>::try_from_load(&fixture("baseline_conv")).unwrap();
+    let (images, outputs) = expected::<Vec<Vec<Vec<f64>>>>("baseline_conv");
+    for (image, output) in images.into_iter().zip(outputs) {
+        assert_close(&nn.try_conv_forward(image.clone()).unwrap(), &output);
+        assert_close(&nn.try_predict(&Tensor::Image(image)).unwrap().into_flat(), &output);
+    }
+    //f32 loads cast the weights
+    let nn = Network::<f32>::try_from_load(&fixture("baseline_conv")).unwrap();
+    assert_eq!(nn.layers.len(), 3);
+}
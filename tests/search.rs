@@ -0,0 +1,143 @@
+use sprout::{
+    activation::ActivationFunction::*,
+    dataset::DataLoader,
+    error::SproutError,
+    network::Network,
+    search::{Config, Objective, Search, SearchResults, Space, Trial},
+    sequential::Sequential,
+    shape::Shape,
+    validation::Metrics,
+};
+
+#[test]
+fn grid_order_and_count() {
+    let space = Space::new().choice("b", [0.1, 0.2]).int("a", 1, 3).choice("c", [5.0]);
+    let configs = space.grid().unwrap();
+    assert_eq!(configs.len(), 6);
+    //parameters are taken by name, the last one changes fastest
+    let values: Vec<[f64; 3]> = configs.iter().map(|config| ["a", "b", "c"].map(|name| config.get(name).unwrap())).collect();
+    assert_eq!(values, [
+        [1.0, 0.1, 5.0], [1.0, 0.2, 5.0],
+        [2.0, 0.1, 5.0], [2.0, 0.2, 5.0],
+        [3.0, 0.1, 5.0], [3.0, 0.2, 5.0],
+    ]);
+    assert!(Space::new().int("a", 1, 3).uniform("b", 0.0, 1.0).grid().is_err());
+    assert!(Space::new().int("a", 3, 1).grid().is_err());
+}
+
+fn network() -> Network {
+    Sequential::new(Shape::Flat(2)).dense(3, TanH).dense(1, Sigmoid).build().unwrap()
+}
+
+#[test]
+fn apply_rejects_what_try_new_rejects() {
+    let mut nn = network();
+    Config::default().with("learning_rate", 0.05).with("batch_size", 7.6).with("grad_threshold", 1.5).apply(&mut nn).unwrap();
+    assert_eq!((nn.learning_rate, nn.batch_size, nn.grad_threshold), (0.05, 8, 1.5));
+    Config::default().with("grad_threshold", f64::INFINITY).apply(&mut nn).unwrap();
+
+    for (name, value) in [
+        ("batch_size", 0.0), ("batch_size", 0.4), ("batch_size", -2.0),
+        ("learning_rate", 0.0), ("learning_rate", -0.1), ("learning_rate", f64::NAN), ("learning_rate", f64::INFINITY),
+        ("grad_threshold", 0.0), ("grad_threshold", -1.0), ("grad_threshold", f64::NAN),
+    ] {
+        let mut nn = network();
+        let result = Config::default().with(name, value).apply(&mut nn);
+        assert!(matches!(result, Err(SproutError::InvalidConfig(_))), "{} = {}", name, value);
+        assert_eq!((nn.learning_rate, nn.batch_size, nn.grad_threshold), (network().learning_rate, network().batch_size, network().grad_threshold));
+    }
+}
+
+fn data() -> Vec<[Vec<f64>; 2]> {
+    (0..16).map(|i| {
+        let (a, b) = ((i % 4) as f64 / 4.0, (i / 4) as f64 / 4.0);
+        [vec![a, b], vec![if a > b { 1.0 } else { 0.0 }]]
+    }).collect()
+}
+
+fn run(search: &Search) -> SearchResults {
+    let build = |config: &Config| -> Result<Network, SproutError> {
+        let mut nn: Network = Sequential::new(Shape::Flat(2)).dense(config.int("hidden")?, TanH).dense(1, Sigmoid).build()?;
+        config.apply(&mut nn)?;
+        Ok(nn)
+    };
+    search.run(build, &DataLoader::new(data(), 4), &data()).unwrap()
+}
+
+//everything but the timings
+fn outcomes(results: &SearchResults) -> Vec<Trial> {
+    results.trials.iter().cloned().map(|trial| Trial { seconds: 0.0, ..trial }).collect()
+}
+
+fn space() -> Space {
+    Space::new().log_uniform("learning_rate", 1e-3, 1.0).choice("batch_size", [2.0, 4.0]).int("hidden", 2, 6)
+}
+
+#[test]
+fn seeded_random_search_repeats() {
+    let search = Search::random(space(), 6, 3).seed(12);
+    let configs = search.configs().unwrap();
+    assert_eq!(configs.len(), 6);
+    assert_eq!(configs, search.configs().unwrap());
+    assert_ne!(configs, Search::random(space(), 6, 3).seed(13).configs().unwrap());
+
+    let first = run(&search);
+    assert_eq!(first.trials.iter().map(|trial| trial.config.clone()).collect::<Vec<_>>(), configs);
+    assert!(first.trials.iter().all(|trial| trial.epochs == 3 && trial.error.is_none()));
+    assert_eq!(outcomes(&run(&search)), outcomes(&first));
+}
+
+#[test]
+fn seeded_successive_halving_repeats() {
+    let search = Search::successive_halving(space(), 8, 1, 2, 4).seed(3);
+    let first = run(&search);
+    let per_round = |results: &SearchResults| [1, 2, 4].map(|epochs| results.trials.iter().filter(|trial| trial.epochs == epochs).count());
+    assert_eq!(per_round(&first), [8, 4, 2]);
+    assert_eq!(first.trials.len(), 14);
+    //each round's survivors are the best of the round before
+    for (round, next) in [(1, 2), (2, 4)] {
+        let mut ranked: Vec<&Trial> = first.trials.iter().filter(|trial| trial.epochs == round).collect();
+        ranked.sort_by(|a, b| a.score(search.objective).total_cmp(&b.score(search.objective)).then(a.id.cmp(&b.id)));
+        let mut best: Vec<usize> = ranked.iter().take(ranked.len() / 2).map(|trial| trial.id).collect();
+        best.sort();
+        let survivors: Vec<usize> = first.trials.iter().filter(|trial| trial.epochs == next).map(|trial| trial.id).collect();
+        assert_eq!(survivors, best);
+    }
+    for _ in 0..2 {
+        let again = run(&search);
+        assert_eq!(per_round(&again), [8, 4, 2]);
+        assert_eq!(outcomes(&again), outcomes(&first));
+    }
+}
+
+fn trial(id: usize, epochs: usize, loss: Option<f64>) -> Trial {
+    Trial {
+        id,
+        config: Config::default().with("id", id as f64),
+        epochs,
+        metrics: loss.map(|loss| Metrics { loss, accuracy: 0.0, mean_absolute_error: 0.0 }),
+        seconds: 0.0,
+        error: loss.is_none().then(|| "failed".to_string()),
+    }
+}
+
+//a config's early rounds don't count once it has trained for longer
+#[test]
+fn configs_rank_by_their_last_round() {
+    let results = SearchResults {
+        objective: Objective::Loss,
+        trials: vec![
+            trial(0, 1, Some(0.1)), trial(1, 1, Some(0.3)), trial(2, 1, Some(0.4)), trial(3, 1, Some(0.05)),
+            trial(0, 2, Some(0.5)), trial(1, 2, Some(0.2)), trial(3, 2, None),
+        ],
+    };
+    let ranked: Vec<(usize, usize)> = results.ranked().iter().map(|trial| (trial.id, trial.epochs)).collect();
+    assert_eq!(ranked, [(1, 2), (2, 1), (0, 2)]);
+    assert_eq!(results.best().map(|trial| trial.id), Some(1));
+
+    //a real run ranks every config once
+    let results = run(&Search::successive_halving(space(), 8, 1, 2, 4).seed(3));
+    let mut ids: Vec<usize> = results.ranked().iter().map(|trial| trial.id).collect();
+    ids.sort();
+    assert_eq!(ids, (0..8).collect::<Vec<usize>>());
+}
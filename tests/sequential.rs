@@ -0,0 +1,104 @@
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_params::PaddingType::{self, *},
+    error::SproutError,
+    initializer::Initializer::*,
+    layer_builder::LayerBuilder,
+    loss_function::LossType::MSE,
+    network::Network,
+    regularizer::Regularizer,
+    sequential::Sequential,
+    shape::Shape,
+};
+
+fn image() -> Sequential {
+    Sequential::new(Shape::Image([2, 6, 6]))
+}
+
+fn invalid_config(result: Result<Network, SproutError>, expected: &str) {
+    match result {
+        Err(SproutError::InvalidConfig(message)) => assert!(message.contains(expected), "{:?} doesn't mention {:?}", message, expected),
+        Err(err) => panic!("expected an invalid configuration, got {}", err),
+        Ok(_) => panic!("expected an invalid configuration mentioning {:?}", expected),
+    }
+}
+
+#[test]
+fn builds_shapes_from_the_input() {
+    let nn: Network = image().conv(3, Same, 1, ReLU).pool(2, 2).conv(2, Valid, 1, TanH).flatten().dense(5, ReLU).dense(2, SoftMax).seed(1).build().unwrap();
+    assert_eq!(nn.layer_names(), ["conv_0", "pool_0", "conv_1", "dense_0", "dense_1"]);
+    assert_eq!(nn.get_nodes(), [2 * 2 * 2, 5, 2]);
+    assert_eq!(nn.input_shape, Some(Shape::Image([2, 6, 6])));
+}
+
+#[test]
+fn zero_kernel_or_stride_is_an_error() {
+    for padding in [Same, Valid, Full] {
+        invalid_config(image().conv(0, padding.clone(), 1, ReLU).flatten().dense(1, Sigmoid).build(), "layer 0: kernel (0)");
+        invalid_config(image().conv(3, padding, 0, ReLU).flatten().dense(1, Sigmoid).build(), "stride (0)");
+    }
+    invalid_config(image().conv(3, Same, 1, ReLU).pool(0, 1).flatten().dense(1, Sigmoid).build(), "layer 1: kernel (0)");
+    invalid_config(image().pool(2, 0).flatten().dense(1, Sigmoid).build(), "stride (0)");
+    invalid_config(Sequential::new(Shape::Flat(3)).dense(0, Sigmoid).build(), "at least one node");
+}
+
+#[test]
+fn flatten_placement() {
+    invalid_config(image().flatten().conv(3, Same, 1, ReLU).build(), "layer 0: conv and pooling layers can't come after flatten()");
+    invalid_config(image().flatten().dense(3, ReLU).pool(2, 2).build(), "layer 1: conv and pooling layers");
+    invalid_config(image().flatten().flatten().dense(1, ReLU).build(), "flatten before layer 0 has nothing to flatten");
+    invalid_config(Sequential::new(Shape::Flat(4)).flatten().dense(1, ReLU).build(), "nothing to flatten");
+    invalid_config(image().conv(3, Same, 1, ReLU).dense(1, ReLU).build(), "layer 1: call flatten() before a dense layer");
+}
+
+#[test]
+fn init_and_regularize_placement() {
+    invalid_config(image().init(Zeros, Zeros).conv(3, Same, 1, ReLU).build(), "init() before layer 0");
+    invalid_config(image().conv(3, Same, 1, ReLU).pool(2, 2).init(Zeros, Zeros).flatten().dense(1, ReLU).build(), "init() before layer 2");
+    invalid_config(image().conv(3, Same, 1, ReLU).flatten().regularize(Regularizer::new().l2(0.1)).dense(1, ReLU).build(), "regularize() before layer 1");
+    invalid_config(image().conv(3, Same, 1, ReLU).regularize(Regularizer::new().l2(-1.0)).flatten().dense(1, ReLU).build(), "layer 0: l2");
+
+    let nn: Network = image()
+        .conv(3, Same, 1, ReLU).init(Constant(0.5), Constant(0.25))
+        .flatten()
+        .dense(2, Sigmoid).init(Zeros, Constant(1.0)).regularize(Regularizer::new().l1(0.1))
+        .build()
+        .unwrap();
+    assert!(nn.layers[0].parameters()[0].iter().all(|w| *w == 0.5));
+    assert_eq!(nn.layers[0].parameters()[1], [0.25]);
+    assert!(nn.layers[1].parameters()[0].iter().all(|w| *w == 0.0));
+    assert_eq!(nn.layers[1].regularizer(), Some(&Regularizer::new().l1(0.1)));
+}
+
+fn builder(kernels: Vec<usize>, strides: Vec<usize>, dense: Vec<usize>) -> LayerBuilder {
+    let mut builder = LayerBuilder::new();
+    builder.set_img([8, 8]);
+    builder.set_cn_layers(kernels.len());
+    builder.set_paddings(vec![PaddingType::Same; kernels.len()]);
+    builder.set_activations(vec![ReLU; kernels.len() + dense.len()]);
+    builder.set_kernels(kernels);
+    builder.set_strides(strides);
+    builder.set_dense_layers(dense);
+    builder
+}
+
+#[test]
+fn layer_builder() {
+    let layers = builder(vec![3, 3], vec![1, 2], vec![4, 2]).try_cnn::<f64>().unwrap();
+    let names: Vec<&str> = layers.iter().map(|layer| layer.type_name()).collect();
+    assert_eq!(names, ["conv", "conv", "dense", "dense"]);
+    assert_eq!(layers[2].parameter_shapes()[0], [16, 4]);
+    assert_eq!(builder(vec![2], vec![2], vec![3]).try_cnn::<f32>().unwrap().len(), 2);
+    //the panicking version builds either precision too
+    let mut nn = Network::<f32>::new(builder(vec![3], vec![2], vec![3]).cnn(), 0.1, 1, MSE);
+    assert_eq!(nn.build(Shape::Image([1, 8, 8])).unwrap(), Shape::Flat(3));
+
+    let mut mismatched = builder(vec![3, 3], vec![1, 1], vec![2]);
+    mismatched.set_strides(vec![1]);
+    assert!(matches!(mismatched.try_cnn::<f64>(), Err(SproutError::InvalidConfig(_))));
+    let mut activations = builder(vec![3], vec![1], vec![2]);
+    activations.set_activations(vec![ReLU]);
+    assert!(matches!(activations.try_cnn::<f64>(), Err(SproutError::InvalidConfig(_))));
+    assert!(matches!(builder(vec![0], vec![1], vec![2]).try_cnn::<f64>(), Err(SproutError::InvalidConfig(_))));
+    assert!(matches!(builder(vec![3], vec![0], vec![2]).try_cnn::<f64>(), Err(SproutError::InvalidConfig(_))));
+}
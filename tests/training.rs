@@ -0,0 +1,78 @@
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_params::PaddingType::*,
+    error::SproutError,
+    layer,
+    loss_function::LossType::*,
+    network::Network,
+    optimizer::Optimizer,
+    shape::Shape,
+    tensor::Tensor,
+};
+
+fn network() -> Network {
+    let mut nn = Network::new(vec![layer::dense([2, 3], TanH), layer::dense([3, 1], Sigmoid)], 0.1, 2, MSE);
+    nn.set_seed(5);
+    nn
+}
+
+fn data() -> Vec<[Vec<f64>; 2]> {
+    vec![[vec![0.0, 1.0], vec![1.0]], [vec![1.0, 0.0], vec![0.0]]]
+}
+
+//an optimizer assigned directly, without going through Sequential, is still checked before any update
+#[test]
+fn invalid_optimizer_is_rejected() {
+    for optimizer in [
+        Optimizer::Adam { beta1: 0.9, beta2: 1.0, epsilon: 1e-8 },
+        Optimizer::Adam { beta1: 0.9, beta2: 0.999, epsilon: -1e-8 },
+        Optimizer::Momentum { momentum: 1.5 },
+    ] {
+        let mut nn = network();
+        nn.optimizer = optimizer;
+        assert!(matches!(nn.try_dense_train(data(), 1), Err(SproutError::InvalidConfig(_))));
+        assert_eq!(nn.epoch, 0);
+    }
+    let mut nn = network();
+    nn.optimizer = Optimizer::adam();
+    nn.try_dense_train(data(), 1).unwrap();
+}
+
+fn shape_mismatch<T: std::fmt::Debug>(result: Result<T, SproutError>, expected: &str) {
+    match result {
+        Err(SproutError::ShapeMismatch(message)) => assert!(message.contains(expected), "{:?} doesn't mention {:?}", message, expected),
+        other => panic!("expected a shape mismatch mentioning {:?}, got {:?}", expected, other),
+    }
+}
+
+#[test]
+fn mismatched_layers_name_the_layer() {
+    let build = |layers, shape| Network::<f64>::new(layers, 0.1, 2, MSE).build(shape);
+
+    shape_mismatch(build(vec![layer::dense([3, 4], TanH), layer::dense([4, 1], Sigmoid)], Shape::Flat(2)), "layer 0: dense layer expects 3 inputs but receives [2]");
+    shape_mismatch(build(vec![layer::dense([2, 3], TanH), layer::dense([4, 1], Sigmoid)], Shape::Flat(2)), "layer 1: dense layer expects 4 inputs but receives [3]");
+    //conv and pool layers need images
+    shape_mismatch(build(vec![layer::dense([2, 3], TanH), layer::conv(3, Valid, 1, ReLU), layer::dense([1, 1], Sigmoid)], Shape::Flat(2)), "layer 1:");
+    shape_mismatch(build(vec![layer::conv(3, Valid, 1, ReLU), layer::pool(5, 1), layer::dense([1, 1], Sigmoid)], Shape::Image([1, 6, 6])), "layer 1:");
+    shape_mismatch(build(vec![layer::conv(3, Valid, 1, ReLU)], Shape::Image([1, 6, 6])), "has to end in a dense layer");
+
+    //a dense layer after an image takes its input size from it, whatever nodes_in says
+    let mut nn = Network::<f64>::new(vec![layer::conv(3, Valid, 1, ReLU), layer::pool(2, 2), layer::dense([4, 3], SoftMax)], 0.1, 2, MSE);
+    assert_eq!(nn.build(Shape::Image([1, 6, 6])).unwrap(), Shape::Flat(3));
+    assert_eq!(nn.get_nodes(), [4, 3]);
+    //the dense layer after it doesn't
+    let mut nn = Network::<f64>::new(vec![layer::conv(3, Valid, 1, ReLU), layer::dense([4, 3], TanH), layer::dense([2, 1], Sigmoid)], 0.1, 2, MSE);
+    shape_mismatch(nn.build(Shape::Image([1, 6, 6])), "layer 2: dense layer expects 2 inputs but receives [3]");
+}
+
+#[test]
+fn unbuilt_networks_dont_predict() {
+    let mut nn = network();
+    let input = Tensor::Flat(vec![0.5, 0.5]);
+    shape_mismatch(nn.try_predict(&input), "layer 0: dense layer has no weights yet, build the network first");
+    shape_mismatch(nn.try_dense_forward(vec![0.5, 0.5]), "build the network first");
+    shape_mismatch(nn.try_predict_batch(&[vec![0.5, 0.5]]), "build the network first");
+
+    nn.build(Shape::Flat(2)).unwrap();
+    assert_eq!(nn.try_predict(&input).unwrap().shape(), Shape::Flat(1));
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeSet;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sprout::{
+    activation::ActivationFunction::*,
+    dataset::{DataLoader, Subset},
+    network::Network,
+    preprocessing::{MinMaxScaler, StandardScaler},
+    sequential::Sequential,
+    shape::Shape,
+    validation::{self, Fold, Partition},
+};
+
+fn rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+//3 classes of 18, 9 and 3 samples, interleaved
+fn labels() -> Vec<usize> {
+    (0..30).map(|i| match i % 10 {
+        0..=5 => 0,
+        6..=8 => 1,
+        _ => 2,
+    }).collect()
+}
+
+fn sorted(indices: &[&[usize]]) -> Vec<usize> {
+    let mut all: Vec<usize> = indices.iter().flat_map(|part| part.iter().copied()).collect();
+    all.sort();
+    all
+}
+
+fn check_partition(partition: &Partition, len: usize) {
+    assert_eq!(sorted(&[&partition.train, &partition.validation, &partition.test]), (0..len).collect::<Vec<_>>());
+}
+
+fn count(labels: &[usize], indices: &[usize], class: usize) -> usize {
+    indices.iter().filter(|&&i| labels[i] == class).count()
+}
+
+#[test]
+fn split_covers_every_sample_once() {
+    let partition = validation::split(50, 0.2, 0.1, &mut rng(1)).unwrap();
+    check_partition(&partition, 50);
+    assert_eq!([partition.train.len(), partition.validation.len(), partition.test.len()], [35, 10, 5]);
+    assert_eq!(partition, validation::split(50, 0.2, 0.1, &mut rng(1)).unwrap());
+    assert_ne!(partition, validation::split(50, 0.2, 0.1, &mut rng(2)).unwrap());
+
+    assert!(validation::split(10, 0.5, 0.5, &mut rng(1)).is_err());
+    assert!(validation::split(10, -0.1, 0.0, &mut rng(1)).is_err());
+    assert!(validation::split(0, 0.2, 0.0, &mut rng(1)).is_err());
+}
+
+#[test]
+fn stratified_split_keeps_class_proportions() {
+    let labels = labels();
+    let sizes: Vec<usize> = (0..3).map(|class| count(&labels, &(0..30).collect::<Vec<_>>(), class)).collect();
+    assert_eq!(sizes, [18, 9, 3]);
+    let partition = validation::stratified_split(&labels, 1.0 / 3.0, 1.0 / 3.0, &mut rng(3)).unwrap();
+    check_partition(&partition, 30);
+    for (class, size) in sizes.into_iter().enumerate() {
+        for part in [&partition.train, &partition.validation, &partition.test] {
+            assert_eq!(count(&labels, part, class), size / 3, "class {}", class);
+        }
+    }
+}
+
+fn check_folds(folds: &[Fold], len: usize, k: usize) {
+    assert_eq!(folds.len(), k);
+    let validation: Vec<&[usize]> = folds.iter().map(|fold| fold.validation.as_slice()).collect();
+    assert_eq!(sorted(&validation), (0..len).collect::<Vec<_>>());
+    for fold in folds {
+        assert_eq!(sorted(&[&fold.train, &fold.validation]), (0..len).collect::<Vec<_>>());
+        let train: BTreeSet<usize> = fold.train.iter().copied().collect();
+        assert!(fold.validation.iter().all(|i| !train.contains(i)));
+    }
+    let sizes: Vec<usize> = folds.iter().map(|fold| fold.validation.len()).collect();
+    assert!(sizes.iter().max().unwrap() - sizes.iter().min().unwrap() <= 1, "{:?}", sizes);
+}
+
+#[test]
+fn k_fold_partitions_the_samples() {
+    for (len, k) in [(10, 2), (23, 5), (7, 7), (30, 4)] {
+        let folds = validation::k_fold(len, k, &mut rng(4)).unwrap();
+        check_folds(&folds, len, k);
+        assert_eq!(folds, validation::k_fold(len, k, &mut rng(4)).unwrap());
+    }
+    assert!(validation::k_fold(5, 1, &mut rng(4)).is_err());
+    assert!(validation::k_fold(5, 6, &mut rng(4)).is_err());
+}
+
+#[test]
+fn stratified_k_fold_spreads_each_class() {
+    let labels = labels();
+    for k in [2, 3, 4, 5] {
+        let folds = validation::stratified_k_fold(&labels, k, &mut rng(5)).unwrap();
+        check_folds(&folds, labels.len(), k);
+        for class in 0..3 {
+            let counts: Vec<usize> = folds.iter().map(|fold| count(&labels, &fold.validation, class)).collect();
+            assert!(counts.iter().max().unwrap() - counts.iter().min().unwrap() <= 1, "k {} class {}: {:?}", k, class, counts);
+        }
+    }
+}
+
+fn data() -> Vec<[Vec<f64>; 2]> {
+    (0..24).map(|i| {
+        let x = i as f64;
+        //a few large values so the scaling depends on which samples it is fitted on
+        let scale = if i % 8 == 3 { 100.0 } else { 1.0 };
+        [vec![x * scale, (x * 0.7).sin()], vec![if i % 3 == 0 { 1.0 } else { 0.0 }]]
+    }).collect()
+}
+
+fn network() -> Network {
+    Sequential::new(Shape::Flat(2)).dense(4, TanH).dense(1, Sigmoid).seed(6).build().unwrap()
+}
+
+//each fold refits the preprocessing on its own training samples, whatever it was fitted on before
+#[test]
+fn cross_validation_refits_preprocessing_per_fold() {
+    let data = data();
+    let inputs: Vec<Vec<f64>> = data.iter().map(|sample| sample[0].clone()).collect();
+    let loader = DataLoader::new(data.clone(), 4);
+    let folds = validation::k_fold(data.len(), 3, &mut rng(7)).unwrap();
+
+    let mut on_everything = network();
+    on_everything.fit_preprocessing(vec![StandardScaler::new().into(), MinMaxScaler::default().into()], &inputs).unwrap();
+    let mut on_nothing_useful = network();
+    on_nothing_useful.fit_preprocessing(vec![StandardScaler::new().into(), MinMaxScaler::default().into()], &[vec![0.0, 1.0], vec![5.0, -1.0]]).unwrap();
+    let results = validation::cross_validate(&on_everything, &loader, &folds, 3).unwrap();
+    assert_eq!(results, validation::cross_validate(&on_nothing_useful, &loader, &folds, 3).unwrap());
+
+    //the same as doing it by hand
+    for (i, fold) in folds.iter().enumerate() {
+        let mut model = on_everything.fresh(on_everything.seed + i as u64);
+        let train: Vec<Vec<f64>> = fold.train.iter().map(|&j| inputs[j].clone()).collect();
+        model.fit_preprocessing(on_everything.preprocessing.clone(), &train).unwrap();
+        model.train(&loader.subset(fold.train.clone()), 3);
+        let validation = Subset::new(loader.dataset().clone(), fold.validation.clone());
+        assert_eq!(model.evaluate(&validation).unwrap(), results.folds[i]);
+    }
+    //and not what the full-data fit gives
+    let leaky: Vec<_> = folds.iter().enumerate().map(|(i, fold)| {
+        let mut model = on_everything.fresh(on_everything.seed + i as u64);
+        model.train(&loader.subset(fold.train.clone()), 3);
+        model.evaluate(&Subset::new(loader.dataset().clone(), fold.validation.clone())).unwrap()
+    }).collect();
+    assert_ne!(leaky, results.folds);
+}
@@ -0,0 +1,108 @@
+use sprout::{
+    activation::ActivationFunction::*,
+    conv_params::PaddingType::*,
+    error::SproutError,
+    network::Network,
+    sequential::Sequential,
+    shape::Shape,
+    tensor::Tensor,
+    weights::{LoadReport, MismatchedWeight, WeightTensor},
+};
+
+fn network(hidden: usize, seed: u64) -> Network {
+    Sequential::new(Shape::Image([1, 5, 5])).conv(3, Valid, 1, ReLU).flatten().dense(hidden, Sigmoid).dense(2, SoftMax).seed(seed).build().unwrap()
+}
+
+fn input(seed: usize) -> Tensor<f64> {
+    Tensor::Image(vec![(0..5).map(|r| (0..5).map(|c| ((seed * 7 + r * 5 + c) % 11) as f64 / 11.0).collect()).collect()])
+}
+
+fn mismatch(result: Result<LoadReport, SproutError>, expected: &str) {
+    match result {
+        Err(SproutError::ShapeMismatch(message)) => assert!(message.contains(expected), "{:?} doesn't mention {:?}", message, expected),
+        other => panic!("expected a shape mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn keys_name_each_layer_parameter() {
+    let keys: Vec<String> = network(4, 1).named_weights().tensors.into_keys().collect();
+    assert_eq!(keys, [
+        "conv_0.bias", "conv_0.weights", "dense_0.biases", "dense_0.weights", "dense_1.biases", "dense_1.weights",
+    ]);
+}
+
+#[test]
+fn strict_load_needs_every_key() {
+    let source = network(4, 1);
+    let mut target = network(4, 2);
+    let before = target.named_weights().tensors;
+
+    let mut missing = source.named_weights();
+    missing.tensors.remove("dense_1.biases");
+    mismatch(target.load_named_weights(&missing, true), "missing: dense_1.biases");
+
+    let mut extra = source.named_weights();
+    extra.tensors.insert("dense_2.weights".to_string(), WeightTensor { shape: vec![1], values: vec![0.0] });
+    mismatch(target.load_named_weights(&extra, true), "unexpected: dense_2.weights");
+
+    //a failed strict load leaves the network alone
+    assert_eq!(target.named_weights().tensors, before);
+    assert!(target.load_named_weights(&source.named_weights(), true).unwrap().is_exact());
+}
+
+#[test]
+fn strict_load_needs_every_shape() {
+    let mut target = network(4, 2);
+    let before = target.named_weights().tensors;
+    mismatch(target.load_named_weights(&network(3, 1).named_weights(), true), "dense_0.weights (expected [9, 4], found [9, 3])");
+
+    //the right shape with the wrong number of values doesn't load either
+    let mut short = network(4, 1).named_weights();
+    short.tensors.get_mut("conv_0.weights").unwrap().values.pop();
+    mismatch(target.load_named_weights(&short, true), "conv_0.weights");
+    assert_eq!(target.named_weights().tensors, before);
+}
+
+#[test]
+fn non_strict_load_fills_what_matches() {
+    let source = network(3, 1);
+    let mut target = network(4, 2);
+    let mut weights = source.named_weights();
+    weights.tensors.remove("dense_1.biases");
+    weights.tensors.insert("dense_9.weights".to_string(), WeightTensor { shape: vec![1], values: vec![0.0] });
+
+    let report = target.load_named_weights(&weights, false).unwrap();
+    assert_eq!(report.loaded, ["conv_0.bias", "conv_0.weights"]);
+    assert_eq!(report.missing, ["dense_1.biases"]);
+    assert_eq!(report.unexpected, ["dense_9.weights"]);
+    assert_eq!(report.mismatched, [
+        MismatchedWeight { key: "dense_0.biases".to_string(), expected: vec![4], found: vec![3] },
+        MismatchedWeight { key: "dense_0.weights".to_string(), expected: vec![9, 4], found: vec![9, 3] },
+        MismatchedWeight { key: "dense_1.weights".to_string(), expected: vec![4, 2], found: vec![3, 2] },
+    ]);
+    assert!(!report.is_exact());
+
+    let loaded = target.named_weights().tensors;
+    assert_eq!(loaded["conv_0.weights"], source.named_weights().tensors["conv_0.weights"]);
+    //the rest keeps the target's own weights
+    let own = network(4, 2).named_weights().tensors;
+    assert_eq!(loaded["dense_0.weights"], own["dense_0.weights"]);
+    assert_eq!(loaded["dense_1.biases"], own["dense_1.biases"]);
+}
+
+#[test]
+fn round_trip_reproduces_predictions() {
+    let name = std::env::temp_dir().join("sprout-weights").display().to_string();
+    let source = network(4, 1);
+    source.try_save_weights(&name).unwrap();
+
+    let mut target = network(4, 2);
+    assert_ne!(target.predict(&input(0)), source.predict(&input(0)));
+    let report = target.try_load_weights(&name, true).unwrap();
+    assert!(report.is_exact());
+    assert_eq!(report.loaded.len(), 6);
+    for seed in 0..5 {
+        assert_eq!(target.predict(&input(seed)), source.predict(&input(seed)));
+    }
+}